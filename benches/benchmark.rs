@@ -1,9 +1,28 @@
-use std::io::Read;
+use std::io::{BufWriter, Read};
 
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use criterion::{Criterion, Throughput, black_box, criterion_group, criterion_main};
+use jstream::path_value_writer::PathValueWriter;
 use jstream::path_value_writer::json_pointer::Options as JSONPointerWriterOptions;
 use jstream::path_value_writer::json_pointer::Writer as JSONPointerWriter;
-use jstream::stream;
+use jstream::{EscapedStr, JsonAtom, Number, Path, PathComponent, stream};
+
+/// throws everything away, so that only reading the input is measured
+struct Discard;
+
+/// only tokenizes `input` with `aws-smithy-json`, the tokenizer jstream used before,
+/// as a baseline for the "tokenize" benchmarks, which also track paths
+fn aws_tokenize(input: &[u8]) {
+    for token in aws_smithy_json::deserialize::json_token_iter(input) {
+        black_box(token.unwrap());
+    }
+}
+
+impl PathValueWriter for Discard {
+    fn write_path_and_value(&mut self, path: Path, value: JsonAtom) -> std::io::Result<()> {
+        black_box((path, value));
+        Ok(())
+    }
+}
 
 fn json_pointer_benchmark(c: &mut Criterion) {
     let mut larger_inputs_group = c.benchmark_group("larger inputs");
@@ -14,6 +33,8 @@ fn json_pointer_benchmark(c: &mut Criterion) {
     let mut buf = Vec::new();
     f.read_to_end(&mut buf).unwrap();
 
+    larger_inputs_group.throughput(Throughput::Bytes(buf.len() as u64));
+
     larger_inputs_group.bench_function("jindex jsonpointer big.json", |b| {
         b.iter(|| {
             let mut writer = vec![];
@@ -24,6 +45,56 @@ fn json_pointer_benchmark(c: &mut Criterion) {
         })
     });
 
+    larger_inputs_group.bench_function("jindex tokenize big.json", |b| {
+        b.iter(|| stream(black_box(&buf), &mut Discard).unwrap())
+    });
+
+    larger_inputs_group.bench_function("aws-smithy-json tokenize big.json", |b| {
+        b.iter(|| aws_tokenize(black_box(&buf)))
+    });
+
+    // the paths of the coordinates in the San Francisco city lots data,
+    // which share most of each path with the one before it.
+    // they are written straight to the writer, as reading input would take most of the time
+    let key = |k| PathComponent::Key(EscapedStr::new(k));
+    let index = PathComponent::Index;
+
+    larger_inputs_group.throughput(Throughput::Elements(2000 * 40 * 3));
+
+    larger_inputs_group.bench_function("jsonpointer writer city lots", |b| {
+        b.iter(|| {
+            let mut writer = BufWriter::new(std::io::sink());
+            let options = JSONPointerWriterOptions::default();
+            let mut sink = JSONPointerWriter::new(&mut writer, options);
+
+            let mut path = [
+                key("features"),
+                index(0),
+                key("geometry"),
+                key("coordinates"),
+                index(0),
+                index(0),
+                index(0),
+            ];
+
+            for feature in 0..2000 {
+                path[1] = index(feature);
+
+                for point in 0..40 {
+                    path[5] = index(point);
+
+                    for (i, coordinate) in
+                        [-122.422003528, 37.808480097, 0.0].into_iter().enumerate()
+                    {
+                        path[6] = index(i);
+                        let value = JsonAtom::Number(Number::Float(black_box(coordinate)));
+                        sink.write_path_and_value(black_box(&path), value).unwrap();
+                    }
+                }
+            }
+        })
+    });
+
     larger_inputs_group.finish();
 
     /////////////////////////////////////////////////
@@ -34,6 +105,8 @@ fn json_pointer_benchmark(c: &mut Criterion) {
     let mut buf = Vec::new();
     f.read_to_end(&mut buf).unwrap();
 
+    smaller_inputs_group.throughput(Throughput::Bytes(buf.len() as u64));
+
     smaller_inputs_group.bench_function("jindex jsonpointer github.json", |b| {
         b.iter(|| {
             let mut writer = vec![];
@@ -43,10 +116,20 @@ fn json_pointer_benchmark(c: &mut Criterion) {
         })
     });
 
+    smaller_inputs_group.bench_function("jindex tokenize github.json", |b| {
+        b.iter(|| stream(black_box(&buf), &mut Discard).unwrap())
+    });
+
+    smaller_inputs_group.bench_function("aws-smithy-json tokenize github.json", |b| {
+        b.iter(|| aws_tokenize(black_box(&buf)))
+    });
+
     let mut f = std::fs::File::open("fixtures/three.json").unwrap();
     let mut buf = Vec::new();
     f.read_to_end(&mut buf).unwrap();
 
+    smaller_inputs_group.throughput(Throughput::Bytes(buf.len() as u64));
+
     smaller_inputs_group.bench_function("jindex jsonpointer three.json", |b| {
         b.iter(|| {
             let mut writer = vec![];
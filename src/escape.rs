@@ -0,0 +1,203 @@
+use crate::token::EscapeError;
+use std::borrow::Cow;
+
+/// appends `s` to `out` as the body of a JSON string,
+/// meaning without the surrounding quotes.
+///
+/// only `"`, `\` and control characters are escaped,
+/// everything else is passed through as-is.
+pub(crate) fn escape_json_str(s: &str, out: &mut String) {
+    let mut start = 0;
+
+    for (i, b) in s.bytes().enumerate() {
+        let escaped: &str = match b {
+            b'"' => "\\\"",
+            b'\\' => "\\\\",
+            b'\n' => "\\n",
+            b'\r' => "\\r",
+            b'\t' => "\\t",
+            0x08 => "\\b",
+            0x0C => "\\f",
+            0x00..=0x1F => {
+                out.push_str(&s[start..i]);
+                out.push_str("\\u00");
+                out.push(HEX[(b >> 4) as usize] as char);
+                out.push(HEX[(b & 0xF) as usize] as char);
+                start = i + 1;
+                continue;
+            }
+            _ => continue,
+        };
+
+        out.push_str(&s[start..i]);
+        out.push_str(escaped);
+        start = i + 1;
+    }
+
+    out.push_str(&s[start..]);
+}
+
+/// decodes the escapes in `s`, the body of a JSON string, borrowing it if it has none
+pub(crate) fn unescape_json_str(s: &str) -> Result<Cow<'_, str>, EscapeError> {
+    let Some(first) = s.find('\\') else {
+        return Ok(Cow::Borrowed(s));
+    };
+
+    let mut out = String::with_capacity(s.len());
+    out.push_str(&s[..first]);
+
+    let mut rest = &s[first..];
+
+    while let Some(i) = rest.find('\\') {
+        out.push_str(&rest[..i]);
+        let escape = &rest[i + 1..];
+
+        let (c, len) = match escape.as_bytes().first() {
+            Some(b'"') => ('"', 1),
+            Some(b'\\') => ('\\', 1),
+            Some(b'/') => ('/', 1),
+            Some(b'b') => ('\u{8}', 1),
+            Some(b'f') => ('\u{c}', 1),
+            Some(b'n') => ('\n', 1),
+            Some(b'r') => ('\r', 1),
+            Some(b't') => ('\t', 1),
+            Some(b'u') => {
+                let high = hex_code_unit(&escape[1..])?;
+
+                // characters outside of the basic multilingual plane are written as
+                // a UTF-16 surrogate pair, each half with its own escape
+                if (0xD800..0xDC00).contains(&high) {
+                    let low = match escape.get(5..7) {
+                        Some("\\u") => hex_code_unit(&escape[7..])?,
+                        _ => return Err(EscapeError("unpaired surrogate")),
+                    };
+
+                    if !(0xDC00..0xE000).contains(&low) {
+                        return Err(EscapeError("unpaired surrogate"));
+                    }
+
+                    let c = char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00))
+                        .expect("surrogate pairs are always valid characters");
+
+                    (c, 11)
+                } else {
+                    let c = char::from_u32(high).ok_or(EscapeError("unpaired surrogate"))?;
+                    (c, 5)
+                }
+            }
+            Some(_) => return Err(EscapeError("invalid escape")),
+            None => return Err(EscapeError("unfinished escape")),
+        };
+
+        out.push(c);
+        rest = &escape[len..];
+    }
+
+    out.push_str(rest);
+
+    Ok(Cow::Owned(out))
+}
+
+/// the 4 hex digits of a `\uXXXX` escape at the start of `s`
+fn hex_code_unit(s: &str) -> Result<u32, EscapeError> {
+    let digits = s
+        .get(..4)
+        .filter(|digits| digits.bytes().all(|b| b.is_ascii_hexdigit()))
+        .ok_or(EscapeError("invalid \\u escape"))?;
+
+    Ok(u32::from_str_radix(digits, 16).unwrap())
+}
+
+const HEX: &[u8; 16] = b"0123456789abcdef";
+
+/// appends the lowercase hex representation of `bytes` to `out`
+pub(crate) fn hex_into(bytes: &[u8], out: &mut String) {
+    for b in bytes {
+        out.push(HEX[(b >> 4) as usize] as char);
+        out.push(HEX[(b & 0xF) as usize] as char);
+    }
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// appends the standard, padded base64 representation of `bytes` to `out`
+pub(crate) fn base64_into(bytes: &[u8], out: &mut String) {
+    for chunk in bytes.chunks(3) {
+        let n = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+
+        out.push(BASE64[(n >> 18) as usize & 0x3F] as char);
+        out.push(BASE64[(n >> 12) as usize & 0x3F] as char);
+
+        if chunk.len() > 1 {
+            out.push(BASE64[(n >> 6) as usize & 0x3F] as char);
+        } else {
+            out.push('=');
+        }
+
+        if chunk.len() > 2 {
+            out.push(BASE64[n as usize & 0x3F] as char);
+        } else {
+            out.push('=');
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{base64_into, escape_json_str, unescape_json_str};
+    use std::borrow::Cow;
+
+    #[test]
+    fn escapes_only_what_it_has_to() {
+        let mut out = String::new();
+        escape_json_str("café \"x\"\\\n\u{1}", &mut out);
+        assert_eq!(out, "café \\\"x\\\"\\\\\\n\\u0001");
+    }
+
+    #[test]
+    fn unescapes() {
+        assert!(matches!(
+            unescape_json_str("café"),
+            Ok(Cow::Borrowed("café"))
+        ));
+
+        for (s, challenge) in [
+            (r#"a\"b\\c\/d"#, "a\"b\\c/d"),
+            (r"\b\f\n\r\t", "\u{8}\u{c}\n\r\t"),
+            (r"caf\u00e9 \u00E9", "café é"),
+            (r"\ud83d\ude00!", "\u{1F600}!"),
+        ] {
+            assert_eq!(unescape_json_str(s).unwrap(), challenge, "{s}");
+        }
+
+        for s in [
+            r"\x41",
+            r"\u12",
+            r"\u12G4",
+            r"\ud83d",
+            r"\ud83dx\ude00",
+            r"\ude00",
+            "\\",
+        ] {
+            assert!(unescape_json_str(s).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn base64() {
+        for (bytes, challenge) in [
+            (&b""[..], ""),
+            (b"f", "Zg=="),
+            (b"fo", "Zm8="),
+            (b"foo", "Zm9v"),
+            (b"foob", "Zm9vYg=="),
+            (&[0xFB, 0xFF], "+/8="),
+        ] {
+            let mut out = String::new();
+            base64_into(bytes, &mut out);
+            assert_eq!(out, challenge);
+        }
+    }
+}
@@ -0,0 +1,222 @@
+//! extracting one value from a document by its JSON Pointer, as the exact bytes it
+//! is written with, without reading any further into the document than that value.
+
+use crate::token::Token;
+use crate::tokenizer::Tokens;
+
+/// returns the bytes of the value at `pointer` in `buf`, exactly as they appear in `buf`,
+/// or `None` if there is no such value.
+///
+/// `pointer` is a JSON Pointer (RFC 6901), i.e., `/features/1234/properties`,
+/// where `~1` stands for `/` and `~0` for `~`. the empty pointer is the whole document.
+/// if `buf` holds more than one top-level value, the first one that has a value at
+/// `pointer` is used.
+///
+/// tokenizing stops at the end of the value, so nothing after it is read, or has to be valid.
+/// `None` is also returned if `buf` is not valid JSON before the value is found.
+pub fn get<'input>(buf: &'input [u8], pointer: &str) -> Option<&'input [u8]> {
+    let pointer: Vec<String> = match pointer {
+        "" => vec![],
+        pointer => pointer
+            .strip_prefix('/')?
+            .split('/')
+            .map(|component| component.replace("~1", "/").replace("~0", "~"))
+            .collect(),
+    };
+
+    // one per open object or array
+    let mut containers: Vec<Container> = vec![];
+    // while reading the value at `pointer`, which is an object or an array,
+    // where it starts, and how many objects and arrays are open within it
+    let mut found: Option<(usize, usize)> = None;
+
+    for token in Tokens::new(buf) {
+        let token = token.ok()?;
+
+        if let Some((start, nested)) = &mut found {
+            match token {
+                Token::StartObject { .. } | Token::StartArray { .. } => *nested += 1,
+                Token::EndObject { offset } | Token::EndArray { offset } => {
+                    if *nested == 0 {
+                        return Some(&buf[*start..=offset.0]);
+                    }
+
+                    *nested -= 1;
+                }
+                _ => {}
+            }
+
+            continue;
+        }
+
+        // whether the value this token starts is at a prefix of `pointer`
+        let is_on_pointer = |containers: &mut Vec<Container>| match containers.last_mut() {
+            None => true,
+            Some(container) => match &mut container.kind {
+                ContainerKind::Object { key_matches } => *key_matches,
+                ContainerKind::Array { next_index } => {
+                    let index = *next_index;
+                    *next_index += 1;
+                    container.is_on_pointer
+                        && index_matches(pointer.get(containers.len() - 1), index)
+                }
+            },
+        };
+
+        match token {
+            Token::ObjectKey { key, .. } => {
+                let depth = containers.len();
+                let container = containers.last_mut()?;
+
+                if let ContainerKind::Object { key_matches } = &mut container.kind {
+                    *key_matches = container.is_on_pointer
+                        && pointer.get(depth - 1).is_some_and(|component| {
+                            key.to_unescaped().ok().is_some_and(|key| key == *component)
+                        });
+                }
+            }
+            Token::StartObject { offset } | Token::StartArray { offset } => {
+                let is_on_pointer = is_on_pointer(&mut containers);
+
+                if is_on_pointer && containers.len() == pointer.len() {
+                    found = Some((offset.0, 0));
+                    continue;
+                }
+
+                containers.push(Container {
+                    kind: if matches!(token, Token::StartObject { .. }) {
+                        ContainerKind::Object { key_matches: false }
+                    } else {
+                        ContainerKind::Array { next_index: 0 }
+                    },
+                    is_on_pointer,
+                });
+            }
+            Token::EndObject { .. } | Token::EndArray { .. } => {
+                containers.pop();
+            }
+            Token::ValueString { value, offset } => {
+                if is_on_pointer(&mut containers) && containers.len() == pointer.len() {
+                    // the quotes aren't part of the escaped string
+                    return Some(&buf[offset.0..offset.0 + value.as_escaped_str().len() + 2]);
+                }
+            }
+            Token::ValueNumber { offset, .. } => {
+                if is_on_pointer(&mut containers) && containers.len() == pointer.len() {
+                    let len = crate::number_len(&buf[offset.0..]);
+                    return Some(&buf[offset.0..offset.0 + len]);
+                }
+            }
+            Token::ValueBool { value, offset } => {
+                if is_on_pointer(&mut containers) && containers.len() == pointer.len() {
+                    let len = if value { 4 } else { 5 };
+                    return Some(&buf[offset.0..offset.0 + len]);
+                }
+            }
+            Token::ValueNull { offset } => {
+                if is_on_pointer(&mut containers) && containers.len() == pointer.len() {
+                    return Some(&buf[offset.0..offset.0 + 4]);
+                }
+            }
+        }
+    }
+
+    None
+}
+
+struct Container {
+    kind: ContainerKind,
+    /// whether this container's path is a prefix of the pointer
+    is_on_pointer: bool,
+}
+
+enum ContainerKind {
+    Object {
+        /// whether the most recent key is the next component of the pointer
+        key_matches: bool,
+    },
+    Array {
+        next_index: usize,
+    },
+}
+
+/// JSON Pointer indexes are decimal, without leading zeros
+fn index_matches(component: Option<&String>, index: usize) -> bool {
+    let Some(component) = component else {
+        return false;
+    };
+
+    let mut b = itoa::Buffer::new();
+    b.format(index) == component
+}
+
+#[cfg(test)]
+mod tests {
+    use super::get;
+
+    fn get_str<'a>(buf: &'a [u8], pointer: &str) -> Option<&'a str> {
+        get(buf, pointer).map(|bytes| std::str::from_utf8(bytes).unwrap())
+    }
+
+    #[test]
+    fn values_of_every_type() {
+        let s = std::fs::read("fixtures/one.json").unwrap();
+
+        assert_eq!(get_str(&s, "/one"), Some("1"));
+        assert_eq!(get_str(&s, "/two"), Some("2.2"));
+        assert_eq!(get_str(&s, "/three-b"), Some(r#""3""#));
+        assert_eq!(get_str(&s, "/four/2"), Some("3"));
+        assert_eq!(
+            get_str(&s, "/five/beta"),
+            Some(
+                r#"{
+            "hey": "How's tricks?"
+        }"#
+            )
+        );
+        assert_eq!(get_str(&s, "/five/beta/hey"), Some(r#""How's tricks?""#));
+        assert_eq!(get_str(&s, "/abool"), Some("true"));
+        assert_eq!(get_str(&s, "/abool2"), Some("false"));
+        assert_eq!(get_str(&s, "/isnull"), Some("null"));
+        assert_eq!(
+            get_str(&s, ""),
+            Some(std::str::from_utf8(&s).unwrap().trim())
+        );
+    }
+
+    #[test]
+    fn missing_values() {
+        let s = std::fs::read("fixtures/one.json").unwrap();
+
+        assert_eq!(get_str(&s, "/missing"), None);
+        assert_eq!(get_str(&s, "/four/4"), None);
+        assert_eq!(get_str(&s, "/four/01"), None);
+        assert_eq!(get_str(&s, "/one/two"), None);
+        assert_eq!(get_str(&s, "/five/alpha/beta"), None);
+        assert_eq!(get_str(&s, "no leading slash"), None);
+    }
+
+    #[test]
+    fn escaped_keys() {
+        let s = br#"{"a/b": {"c~d": [0, {"e": "found"}]}}"#;
+
+        assert_eq!(get_str(s, "/a~1b/c~0d/1/e"), Some(r#""found""#));
+    }
+
+    #[test]
+    fn stops_after_the_value() {
+        // everything after the value is invalid
+        let s = br#"{"a": {"b": [1, 2]}, "c": ]]]"#;
+
+        assert_eq!(get_str(s, "/a/b"), Some("[1, 2]"));
+        assert_eq!(get_str(s, "/c"), None);
+    }
+
+    #[test]
+    fn multiple_top_level_values() {
+        let s = b"{\"a\": 1}\n{\"b\": 2}\n";
+
+        assert_eq!(get_str(s, "/b"), Some("2"));
+        assert_eq!(get_str(s, ""), Some("{\"a\": 1}"));
+    }
+}
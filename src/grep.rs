@@ -0,0 +1,206 @@
+//! matching regular expressions against keys and values, like `grep` on
+//! jstream's output, but without the quoting and escaping of the output getting in the way.
+
+use crate::path_value_writer::{PathValueWriter, write_number};
+use crate::span::Span;
+use crate::{JsonAtom, Path, PathComponent};
+use regex::Regex;
+
+/// what a [`Matcher`] matches its regular expression against
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Target {
+    /// every key in the path, decoded
+    Keys,
+    /// the value. strings are decoded, and other values are matched as they appear in JSON
+    Values,
+    /// either of the above
+    #[default]
+    Both,
+}
+
+#[derive(Clone, Debug)]
+pub struct Matcher {
+    regex: Regex,
+    target: Target,
+    invert: bool,
+}
+
+impl Matcher {
+    pub fn new(regex: Regex, target: Target) -> Self {
+        Self {
+            regex,
+            target,
+            invert: false,
+        }
+    }
+
+    /// whether to match the paths and values that *don't* match the regular expression,
+    /// like `grep -v`. defaults to `false`.
+    pub fn invert(mut self, invert: bool) -> Self {
+        self.invert = invert;
+        self
+    }
+
+    pub fn matches(&self, path: Path, value: &JsonAtom) -> bool {
+        let matches = match self.target {
+            Target::Keys => self.matches_keys(path),
+            Target::Values => self.matches_value(value),
+            Target::Both => self.matches_keys(path) || self.matches_value(value),
+        };
+
+        matches != self.invert
+    }
+
+    fn matches_keys(&self, path: Path) -> bool {
+        path.iter().any(|component| match component {
+            PathComponent::Key(k) => match k.to_unescaped() {
+                Ok(k) => self.regex.is_match(&k),
+                Err(_) => self.regex.is_match(k.as_escaped_str()),
+            },
+            PathComponent::Index(_) => false,
+        })
+    }
+
+    fn matches_value(&self, value: &JsonAtom) -> bool {
+        match value {
+            JsonAtom::String(s) => match s.to_unescaped() {
+                Ok(s) => self.regex.is_match(&s),
+                Err(_) => self.regex.is_match(s.as_escaped_str()),
+            },
+            JsonAtom::Number(n) => {
+                let mut buf = vec![];
+                write_number(&mut buf, *n).expect("writing to a Vec cannot fail");
+                self.regex
+                    .is_match(std::str::from_utf8(&buf).expect("numbers are ASCII"))
+            }
+            JsonAtom::Bool(true) => self.regex.is_match("true"),
+            JsonAtom::Bool(false) => self.regex.is_match("false"),
+            JsonAtom::Null => self.regex.is_match("null"),
+            JsonAtom::EmptyObject => self.regex.is_match("{}"),
+            JsonAtom::EmptyArray => self.regex.is_match("[]"),
+        }
+    }
+}
+
+/// writes only the paths and values that match a [`Matcher`] to the wrapped writer,
+/// counting them as it goes
+pub struct Writer<'writer, W: PathValueWriter + ?Sized> {
+    writer: &'writer mut W,
+    matcher: &'writer Matcher,
+    matched: usize,
+}
+
+impl<'writer, W: PathValueWriter + ?Sized> Writer<'writer, W> {
+    pub fn new(writer: &'writer mut W, matcher: &'writer Matcher) -> Self {
+        Self {
+            writer,
+            matcher,
+            matched: 0,
+        }
+    }
+
+    /// the number of values that have matched so far
+    pub fn matched(&self) -> usize {
+        self.matched
+    }
+}
+
+impl<W: PathValueWriter + ?Sized> PathValueWriter for Writer<'_, W> {
+    fn write_path_and_value(&mut self, path: Path, value: JsonAtom) -> std::io::Result<()> {
+        if self.matcher.matches(path, &value) {
+            self.matched += 1;
+            self.writer.write_path_and_value(path, value)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn write_path_and_value_with_span(
+        &mut self,
+        path: Path,
+        value: JsonAtom,
+        span: Span,
+    ) -> std::io::Result<()> {
+        if self.matcher.matches(path, &value) {
+            self.matched += 1;
+            self.writer
+                .write_path_and_value_with_span(path, value, span)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn write_duplicate_key(
+        &mut self,
+        path: Path,
+        first_offset: usize,
+        second_offset: usize,
+    ) -> std::io::Result<()> {
+        self.writer
+            .write_duplicate_key(path, first_offset, second_offset)
+    }
+
+    fn start_document(&mut self) -> std::io::Result<()> {
+        self.writer.start_document()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Matcher, Target, Writer as GrepWriter};
+    use crate::path_value_writer::json_pointer::{
+        Options as JSONPointerWriterOptions, Writer as JSONPointerWriter,
+    };
+    use regex::Regex;
+
+    fn grep(matcher: &Matcher) -> (String, usize) {
+        let s = br#"{"name": "caf\u00e9", "tags": ["name", "x"], "n\u0061me2": 12, "ok": null}"#;
+
+        let mut buf = vec![];
+        let mut writer = JSONPointerWriter::new(&mut buf, JSONPointerWriterOptions::default());
+        let mut grep_writer = GrepWriter::new(&mut writer, matcher);
+
+        crate::stream(s, &mut grep_writer).unwrap();
+
+        let matched = grep_writer.matched();
+
+        (String::from_utf8(buf).unwrap(), matched)
+    }
+
+    #[test]
+    fn keys_values_and_both() {
+        let name = Regex::new("^name").unwrap();
+
+        assert_eq!(
+            grep(&Matcher::new(name.clone(), Target::Keys)),
+            ("/name\t\"caf\\u00e9\"\n/n\\u0061me2\t12\n".to_string(), 2)
+        );
+
+        assert_eq!(
+            grep(&Matcher::new(name.clone(), Target::Values)),
+            ("/tags/0\t\"name\"\n".to_string(), 1)
+        );
+
+        assert_eq!(grep(&Matcher::new(name, Target::Both)).1, 3);
+
+        // values are decoded, and non-strings are matched as JSON
+        assert_eq!(
+            grep(&Matcher::new(
+                Regex::new("é$|^12$|null").unwrap(),
+                Target::Values
+            ))
+            .1,
+            3
+        );
+    }
+
+    #[test]
+    fn invert() {
+        let matcher = Matcher::new(Regex::new("name").unwrap(), Target::Both).invert(true);
+
+        assert_eq!(
+            grep(&matcher),
+            ("/tags/1\t\"x\"\n/ok\tnull\n".to_string(), 2)
+        );
+    }
+}
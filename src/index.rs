@@ -0,0 +1,492 @@
+//! a sidecar index of where the values near the top of a document are,
+//! for looking up single values in large files without reading all of them.
+//!
+//! the index maps the JSON Pointer of every value down to a given depth to its [`Span`].
+//! looking a value up reads only the span of its nearest indexed ancestor
+//! (or the value itself) from the file, and finds the value within that with [`crate::get::get`].
+//!
+//! an index records the size, modification time and a hash of the start and end
+//! of the file it was built from, and is refused if the file no longer matches.
+
+use crate::input::binary::{Input, write_varint};
+use crate::span::Span;
+use crate::token::Token;
+use crate::tokenizer::Tokens;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// the start of every index file. the last byte is the format version.
+const MAGIC: &[u8; 8] = b"jstridx\x01";
+
+/// how much of each end of the file is hashed
+const HASHED_LEN: u64 = 64 * 1024;
+
+pub struct Options {
+    depth: usize,
+}
+
+impl Options {
+    /// how many levels of the document to index. values deeper than this are found by reading
+    /// their ancestor at this depth, so a greater depth makes lookups read less,
+    /// at the cost of a larger index. defaults to 2.
+    pub fn depth(mut self, depth: usize) -> Self {
+        self.depth = depth;
+        self
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self { depth: 2 }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Index {
+    depth: usize,
+    /// sorted by pointer. pointers are in RFC 6901 form, with keys decoded
+    /// and `~` and `/` escaped as `~0` and `~1`.
+    entries: Vec<(String, Span)>,
+}
+
+impl Index {
+    /// indexes the first top-level value in `buf`
+    pub fn build(buf: &[u8], options: &Options) -> std::io::Result<Self> {
+        let mut entries = vec![];
+        // the pointer of the current value, while it is no deeper than `options.depth`
+        let mut pointer = String::new();
+        let mut containers: Vec<Container> = vec![];
+
+        for token in Tokens::new(buf) {
+            let token =
+                token.map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+            let is_indexed = containers.len() <= options.depth;
+
+            if is_indexed
+                && crate::starts_value(&token)
+                && let Some(container) = containers.last_mut()
+                && let Some(index) = &mut container.next_index
+            {
+                pointer.truncate(container.pointer_len);
+                pointer.push('/');
+                let mut b = itoa::Buffer::new();
+                pointer.push_str(b.format(*index));
+                *index += 1;
+            }
+
+            let span = match token {
+                Token::ObjectKey { key, .. } => {
+                    if is_indexed {
+                        let container = containers
+                            .last()
+                            .expect("object keys must be inside an object, this is a bug");
+                        let key = key
+                            .to_unescaped()
+                            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+                        pointer.truncate(container.pointer_len);
+                        pointer.push('/');
+                        pointer.push_str(&key.replace('~', "~0").replace('/', "~1"));
+                    }
+
+                    continue;
+                }
+                Token::StartObject { offset } | Token::StartArray { offset } => {
+                    containers.push(Container {
+                        start: offset.0,
+                        pointer_len: pointer.len(),
+                        next_index: matches!(token, Token::StartArray { .. }).then_some(0),
+                    });
+
+                    continue;
+                }
+                Token::EndObject { offset } | Token::EndArray { offset } => {
+                    let container = containers
+                        .pop()
+                        .expect("objects and arrays must start before they end, this is a bug");
+
+                    pointer.truncate(container.pointer_len);
+
+                    Span {
+                        start: container.start,
+                        end: offset.0 + 1,
+                    }
+                }
+                Token::ValueString { value, offset } => Span {
+                    start: offset.0,
+                    end: offset.0 + value.as_escaped_str().len() + 2,
+                },
+                Token::ValueNumber { offset, .. } => Span {
+                    start: offset.0,
+                    end: offset.0 + crate::number_len(&buf[offset.0..]),
+                },
+                Token::ValueBool { value, offset } => Span {
+                    start: offset.0,
+                    end: offset.0 + if value { 4 } else { 5 },
+                },
+                Token::ValueNull { offset } => Span {
+                    start: offset.0,
+                    end: offset.0 + 4,
+                },
+            };
+
+            if containers.len() <= options.depth {
+                entries.push((pointer.clone(), span));
+            }
+
+            if containers.is_empty() {
+                break;
+            }
+        }
+
+        // with duplicate keys, the first value is used, as with `get`
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        entries.dedup_by(|(a, _), (b, _)| a == b);
+
+        Ok(Self {
+            depth: options.depth,
+            entries,
+        })
+    }
+
+    /// the span to read to find the value at `pointer`, and the rest of `pointer`
+    /// to look for within it, or `None` if there is no value at `pointer`
+    pub fn lookup<'pointer>(&self, pointer: &'pointer str) -> Option<(Span, &'pointer str)> {
+        if !pointer.is_empty() && !pointer.starts_with('/') {
+            return None;
+        }
+
+        // the end of the first `depth` components of the pointer
+        let split_at = pointer
+            .match_indices('/')
+            .nth(self.depth)
+            .map_or(pointer.len(), |(i, _)| i);
+
+        let (indexed, rest) = pointer.split_at(split_at);
+
+        let i = self
+            .entries
+            .binary_search_by(|(pointer, _)| pointer.as_str().cmp(indexed))
+            .ok()?;
+
+        Some((self.entries[i].1, rest))
+    }
+
+    /// writes the index, along with what it knows about the file it was built from
+    pub fn write<W: Write>(&self, source: &Source, writer: &mut W) -> std::io::Result<()> {
+        let mut buf = vec![];
+
+        buf.extend_from_slice(MAGIC);
+        buf.extend_from_slice(&source.len.to_be_bytes());
+        buf.extend_from_slice(&source.modified_secs.to_be_bytes());
+        buf.extend_from_slice(&source.modified_nanos.to_be_bytes());
+        buf.extend_from_slice(&source.ends_hash);
+        write_varint(&mut buf, self.depth as u64);
+        write_varint(&mut buf, self.entries.len() as u64);
+
+        // pointers are sorted, so each is written as the length it shares
+        // with the one before it, and the rest of it
+        let mut previous = "";
+
+        for (pointer, span) in &self.entries {
+            let shared = previous
+                .bytes()
+                .zip(pointer.bytes())
+                .take_while(|(a, b)| a == b)
+                .count();
+            // don't split a multibyte character
+            let shared = (0..=shared)
+                .rev()
+                .find(|i| pointer.is_char_boundary(*i))
+                .unwrap_or(0);
+
+            write_varint(&mut buf, shared as u64);
+            write_varint(&mut buf, (pointer.len() - shared) as u64);
+            buf.extend_from_slice(&pointer.as_bytes()[shared..]);
+            write_varint(&mut buf, span.start as u64);
+            write_varint(&mut buf, (span.end - span.start) as u64);
+
+            previous = pointer;
+        }
+
+        writer.write_all(&buf)
+    }
+
+    /// reads an index written by [`Index::write`]
+    pub fn read(buf: &[u8]) -> std::io::Result<(Source, Self)> {
+        let mut input = Input::new(buf);
+
+        if input.take(MAGIC.len() as u64).ok() != Some(MAGIC.as_slice()) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "not a jstream index, or one written by a different version of jstream",
+            ));
+        }
+
+        let source = Source {
+            len: input.u64()?,
+            modified_secs: input.u64()?,
+            modified_nanos: input.u32()?,
+            ends_hash: input.take(32)?.try_into().unwrap(),
+        };
+
+        let depth = read_usize(&mut input)?;
+        let len = input.varint()?;
+
+        let mut entries = vec![];
+        let mut pointer = String::new();
+
+        for _ in 0..len {
+            let shared = read_usize(&mut input)?;
+            let rest_len = input.varint()?;
+            let rest = input.take(rest_len)?;
+            let rest = crate::input::binary::text(&input, rest)?;
+
+            if shared > pointer.len() || !pointer.is_char_boundary(shared) {
+                return Err(input.error("invalid pointer"));
+            }
+
+            pointer.truncate(shared);
+            pointer.push_str(rest);
+
+            let start = read_usize(&mut input)?;
+            let len = read_usize(&mut input)?;
+
+            entries.push((
+                pointer.clone(),
+                Span {
+                    start,
+                    end: start + len,
+                },
+            ));
+        }
+
+        Ok((source, Self { depth, entries }))
+    }
+}
+
+/// an open object or array, while building an index
+struct Container {
+    start: usize,
+    /// the length of the pointer of the object or array itself
+    pointer_len: usize,
+    /// for arrays, the index of the next element
+    next_index: Option<usize>,
+}
+
+/// what an index knows about the file it was built from,
+/// to tell whether the file has changed since
+#[derive(Debug, PartialEq, Eq)]
+pub struct Source {
+    len: u64,
+    modified_secs: u64,
+    modified_nanos: u32,
+    /// the SHA-256 of the first and last 64 KiB of the file,
+    /// to catch changes that leave the size and modification time as they were
+    ends_hash: [u8; 32],
+}
+
+impl Source {
+    pub fn of(file: &mut File) -> std::io::Result<Self> {
+        let metadata = file.metadata()?;
+        let len = metadata.len();
+
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default();
+
+        let mut hasher = Sha256::new();
+        let mut buf = vec![0; HASHED_LEN.min(len) as usize];
+
+        file.seek(SeekFrom::Start(0))?;
+        file.read_exact(&mut buf)?;
+        hasher.update(&buf);
+
+        file.seek(SeekFrom::Start(len - buf.len() as u64))?;
+        file.read_exact(&mut buf)?;
+        hasher.update(&buf);
+
+        Ok(Self {
+            len,
+            modified_secs: modified.as_secs(),
+            modified_nanos: modified.subsec_nanos(),
+            ends_hash: hasher.finalize().into(),
+        })
+    }
+}
+
+/// where the index of `path` is kept, unless told otherwise: `path` with `.jsidx` appended
+pub fn default_index_path(path: &Path) -> PathBuf {
+    let mut index_path = path.as_os_str().to_owned();
+    index_path.push(".jsidx");
+    PathBuf::from(index_path)
+}
+
+/// indexes the file at `path`, writing the index to `index_path`
+pub fn build_file(path: &Path, index_path: &Path, options: &Options) -> std::io::Result<()> {
+    let mut file = File::open(path)?;
+
+    let mut buf = vec![];
+    file.read_to_end(&mut buf)?;
+
+    let index = Index::build(&buf, options)?;
+    let source = Source::of(&mut file)?;
+
+    let mut index_file = std::io::BufWriter::new(File::create(index_path)?);
+    index.write(&source, &mut index_file)?;
+    index_file.flush()
+}
+
+/// the value at `pointer` in the file at `path`, exactly as it appears in the file,
+/// reading only as much of the file as the index at `index_path` says it has to.
+///
+/// an error if the file has changed since it was indexed.
+pub fn get_file(path: &Path, index_path: &Path, pointer: &str) -> std::io::Result<Option<Vec<u8>>> {
+    let (indexed_source, index) = Index::read(&std::fs::read(index_path)?)?;
+
+    let mut file = File::open(path)?;
+
+    if Source::of(&mut file)? != indexed_source {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "{} has changed since {} was built from it",
+                path.display(),
+                index_path.display()
+            ),
+        ));
+    }
+
+    let Some((span, rest)) = index.lookup(pointer) else {
+        return Ok(None);
+    };
+
+    let mut buf = vec![0; span.end - span.start];
+    file.seek(SeekFrom::Start(span.start as u64))?;
+    file.read_exact(&mut buf)?;
+
+    Ok(crate::get::get(&buf, rest).map(|value| value.to_vec()))
+}
+
+fn read_usize(input: &mut Input) -> std::io::Result<usize> {
+    let n = input.varint()?;
+    usize::try_from(n).map_err(|_| input.error("offset too large"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Index, Options, Source, build_file, default_index_path, get_file};
+    use crate::span::Span;
+
+    fn lookup<'a>(buf: &'a [u8], index: &Index, pointer: &str) -> Option<&'a str> {
+        let (span, rest) = index.lookup(pointer)?;
+        crate::get::get(&buf[span.start..span.end], rest)
+            .map(|value| std::str::from_utf8(value).unwrap())
+    }
+
+    #[test]
+    fn lookups() {
+        let s = std::fs::read("fixtures/one.json").unwrap();
+
+        for depth in 0..4 {
+            let index = Index::build(&s, &Options::default().depth(depth)).unwrap();
+
+            for pointer in [
+                "",
+                "/one",
+                "/three-b",
+                "/four/2",
+                "/five/beta",
+                "/five/beta/hey",
+            ] {
+                assert_eq!(
+                    lookup(&s, &index, pointer),
+                    crate::get::get(&s, pointer).map(|value| std::str::from_utf8(value).unwrap()),
+                    "{pointer} at depth {depth}"
+                );
+            }
+
+            assert_eq!(lookup(&s, &index, "/missing"), None);
+            assert_eq!(lookup(&s, &index, "/five/beta/missing"), None);
+            assert_eq!(lookup(&s, &index, "missing"), None);
+        }
+
+        // only the ancestor at the indexed depth is read
+        let index = Index::build(&s, &Options::default().depth(1)).unwrap();
+        let (span, rest) = index.lookup("/five/beta/hey").unwrap();
+        assert!(
+            std::str::from_utf8(&s[span.start..span.end])
+                .unwrap()
+                .starts_with(
+                    r#"{
+        "alpha""#
+                )
+        );
+        assert_eq!(rest, "/beta/hey");
+    }
+
+    #[test]
+    fn escaped_keys() {
+        let s = br#"{"a/b": {"c~d": 1, "caf\u00e9": 2}, "x": [true]}"#;
+        let index = Index::build(s, &Options::default()).unwrap();
+
+        assert_eq!(lookup(s, &index, "/a~1b/c~0d"), Some("1"));
+        assert_eq!(lookup(s, &index, "/a~1b/café"), Some("2"));
+        assert_eq!(lookup(s, &index, "/x/0"), Some("true"));
+    }
+
+    #[test]
+    fn write_and_read() {
+        let s = r#"{"café": 1, "café2": [2, 3], "cafe": null}"#.as_bytes();
+        let index = Index::build(s, &Options::default()).unwrap();
+
+        assert_eq!(
+            index.lookup("/cafe"),
+            Some((Span { start: 39, end: 43 }, ""))
+        );
+
+        let source = Source {
+            len: 1,
+            modified_secs: 2,
+            modified_nanos: 3,
+            ends_hash: [4; 32],
+        };
+
+        let mut buf = vec![];
+        index.write(&source, &mut buf).unwrap();
+
+        assert_eq!(Index::read(&buf).unwrap(), (source, index));
+        assert!(Index::read(b"not an index").is_err());
+    }
+
+    #[test]
+    fn files() {
+        let dir = std::env::temp_dir().join(format!("jstream-index-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("doc.json");
+        let index_path = default_index_path(&path);
+        assert_eq!(index_path, dir.join("doc.json.jsidx"));
+
+        std::fs::write(&path, br#"{"a": {"b": [1, 2, {"c": "d"}]}}"#).unwrap();
+        build_file(&path, &index_path, &Options::default().depth(1)).unwrap();
+
+        assert_eq!(
+            get_file(&path, &index_path, "/a/b/2/c").unwrap(),
+            Some(br#""d""#.to_vec())
+        );
+        assert_eq!(get_file(&path, &index_path, "/z").unwrap(), None);
+
+        // the same size, but different contents
+        std::fs::write(&path, br#"{"a": {"b": [1, 2, {"c": "e"}]}}"#).unwrap();
+        assert!(get_file(&path, &index_path, "/a").is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,315 @@
+//! the parts of the CBOR and MessagePack front-ends that don't depend on the encoding.
+//!
+//! both formats are a sequence of self-describing items, where arrays and maps
+//! are a header giving the number of items that follow, so a decoder only has to
+//! yield one [`Item`] at a time and [`drive`] keeps track of where in the
+//! document each item sits, without building the document in memory
+//! (or recursing, so deeply nested input can't overflow the stack).
+//!
+//! values that have no JSON equivalent are mapped as follows:
+//! - byte strings are written as base64 strings
+//! - map keys that are numbers, booleans, null or byte strings are written as
+//!   they would be written as values, without quotes, i.e., `{1: "a"}` has the key `"1"`.
+//!   maps and arrays as keys are an error, as there is no equivalent path
+//! - MessagePack extension types are written as an object of their `type` and base64 `data`
+//! - CBOR tags are dropped, and the tagged item is written as is
+
+use super::OwnedPath;
+use crate::JsonAtom;
+use crate::Number;
+use crate::escape::base64_into;
+use crate::path_value_writer::{PathValueWriter, write_number};
+use std::borrow::Cow;
+
+pub(crate) enum Item<'input> {
+    Null,
+    Bool(bool),
+    Number(Number),
+    Text(Cow<'input, str>),
+    Bytes(Cow<'input, [u8]>),
+    /// `None` for an indefinite-length array, which ends with [`Item::Break`]
+    Array(Option<u64>),
+    /// `None` for an indefinite-length map, which ends with [`Item::Break`].
+    /// the length is the number of key/value pairs
+    Map(Option<u64>),
+    /// ends an indefinite-length array or map
+    Break,
+    Extension {
+        type_: i8,
+        data: &'input [u8],
+    },
+}
+
+pub(crate) trait Decoder<'input> {
+    /// `None` at the end of the input
+    fn next_item(&mut self) -> std::io::Result<Option<Item<'input>>>;
+
+    /// what is being decoded, for errors that say where they are
+    fn input(&self) -> &Input<'input>;
+}
+
+enum Frame {
+    Array { len: Option<u64>, count: u64 },
+    Map { len: Option<u64>, count: u64 },
+}
+
+impl Frame {
+    fn is_complete(&self) -> bool {
+        match self {
+            Frame::Array { len, count } | Frame::Map { len, count } => *len == Some(*count),
+        }
+    }
+
+    fn count(&self) -> u64 {
+        match self {
+            Frame::Array { count, .. } | Frame::Map { count, .. } => *count,
+        }
+    }
+
+    fn advance(&mut self) {
+        match self {
+            Frame::Array { count, .. } | Frame::Map { count, .. } => *count += 1,
+        }
+    }
+}
+
+/// writes every item `decoder` yields, with paths starting from the root for each top-level item,
+/// just like multiple top-level values in JSON input
+pub(crate) fn drive<'input, D, W>(decoder: &mut D, writer: &mut W) -> std::io::Result<()>
+where
+    D: Decoder<'input>,
+    W: PathValueWriter + ?Sized,
+{
+    let mut stack: Vec<Frame> = vec![];
+    let mut path = OwnedPath::default();
+
+    loop {
+        if let Some(frame) = stack.last()
+            && frame.is_complete()
+        {
+            stack.pop();
+            value_done(&mut stack, &mut path);
+            continue;
+        }
+
+        let Some(mut item) = decoder.next_item()? else {
+            if stack.is_empty() {
+                return Ok(());
+            } else {
+                return Err(decoder.input().unexpected_end());
+            }
+        };
+
+        if let Item::Break = item {
+            match stack.pop() {
+                Some(frame @ (Frame::Array { len: None, .. } | Frame::Map { len: None, .. })) => {
+                    if frame.count() == 0 {
+                        let empty = match frame {
+                            Frame::Array { .. } => JsonAtom::EmptyArray,
+                            Frame::Map { .. } => JsonAtom::EmptyObject,
+                        };
+                        path.write_value(writer, empty)?;
+                    }
+                    value_done(&mut stack, &mut path);
+                    continue;
+                }
+                _ => return Err(decoder.input().error("unexpected break")),
+            }
+        }
+
+        match stack.last() {
+            None => writer.start_document()?,
+            Some(Frame::Array { count, .. }) => path.push_index(*count as usize),
+            Some(Frame::Map { .. }) => {
+                path.push_key(&key_to_string(item)?);
+
+                item = match decoder.next_item()? {
+                    None => return Err(decoder.input().unexpected_end()),
+                    Some(Item::Break) => {
+                        return Err(decoder.input().error("map key without a value"));
+                    }
+                    Some(value) => value,
+                };
+            }
+        }
+
+        match item {
+            Item::Array(Some(0)) => {
+                path.write_value(writer, JsonAtom::EmptyArray)?;
+                value_done(&mut stack, &mut path);
+            }
+            Item::Map(Some(0)) => {
+                path.write_value(writer, JsonAtom::EmptyObject)?;
+                value_done(&mut stack, &mut path);
+            }
+            Item::Array(len) => stack.push(Frame::Array { len, count: 0 }),
+            Item::Map(len) => stack.push(Frame::Map { len, count: 0 }),
+            Item::Null => {
+                path.write_value(writer, JsonAtom::Null)?;
+                value_done(&mut stack, &mut path);
+            }
+            Item::Bool(b) => {
+                path.write_value(writer, JsonAtom::Bool(b))?;
+                value_done(&mut stack, &mut path);
+            }
+            Item::Number(n) => {
+                path.write_value(writer, JsonAtom::Number(n))?;
+                value_done(&mut stack, &mut path);
+            }
+            Item::Text(s) => {
+                path.write_string(writer, &s)?;
+                value_done(&mut stack, &mut path);
+            }
+            Item::Bytes(bytes) => {
+                let mut s = String::with_capacity(bytes.len().div_ceil(3) * 4);
+                base64_into(&bytes, &mut s);
+                path.write_string(writer, &s)?;
+                value_done(&mut stack, &mut path);
+            }
+            Item::Extension { type_, data } => {
+                path.push_key("type");
+                let type_ = if type_ < 0 {
+                    Number::NegInt(type_ as i64)
+                } else {
+                    Number::PosInt(type_ as u64)
+                };
+                path.write_value(writer, JsonAtom::Number(type_))?;
+                path.pop();
+
+                path.push_key("data");
+                let mut s = String::with_capacity(data.len().div_ceil(3) * 4);
+                base64_into(data, &mut s);
+                path.write_string(writer, &s)?;
+                path.pop();
+
+                value_done(&mut stack, &mut path);
+            }
+            Item::Break => unreachable!("handled above"),
+        }
+    }
+}
+
+/// pops the finished value's component off `path`, if it has one
+/// (top-level values don't), and moves its parent on to the next
+fn value_done(stack: &mut [Frame], path: &mut OwnedPath) {
+    if let Some(parent) = stack.last_mut() {
+        path.pop();
+        parent.advance();
+    }
+}
+
+fn key_to_string(item: Item) -> std::io::Result<String> {
+    match item {
+        Item::Text(s) => Ok(s.into_owned()),
+        Item::Number(n) => {
+            let mut buf = vec![];
+            write_number(&mut buf, n)?;
+            Ok(String::from_utf8(buf).expect("numbers are ASCII"))
+        }
+        Item::Bool(b) => Ok(b.to_string()),
+        Item::Null => Ok("null".to_string()),
+        Item::Bytes(bytes) => {
+            let mut s = String::new();
+            base64_into(&bytes, &mut s);
+            Ok(s)
+        }
+        Item::Array(_) | Item::Map(_) | Item::Extension { .. } => {
+            Err(invalid("map keys must be scalars"))
+        }
+        Item::Break => Err(invalid("unexpected break")),
+    }
+}
+
+/// a cursor over the input, for decoders
+pub(crate) struct Input<'input> {
+    buf: &'input [u8],
+    offset: usize,
+}
+
+impl<'input> Input<'input> {
+    pub(crate) fn new(buf: &'input [u8]) -> Self {
+        Self { buf, offset: 0 }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.offset >= self.buf.len()
+    }
+
+    pub(crate) fn take(&mut self, n: u64) -> std::io::Result<&'input [u8]> {
+        let n = usize::try_from(n).map_err(|_| self.unexpected_end())?;
+
+        if self.buf.len() - self.offset < n {
+            return Err(self.unexpected_end());
+        }
+
+        let bytes = &self.buf[self.offset..self.offset + n];
+        self.offset += n;
+        Ok(bytes)
+    }
+
+    pub(crate) fn u8(&mut self) -> std::io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> std::io::Result<u16> {
+        Ok(u16::from_be_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn u32(&mut self) -> std::io::Result<u32> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn u64(&mut self) -> std::io::Result<u64> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    /// an unsigned LEB128 integer, as in jstream's own binary formats
+    pub(crate) fn varint(&mut self) -> std::io::Result<u64> {
+        let mut n: u64 = 0;
+
+        for shift in (0..64).step_by(7) {
+            let b = self.u8()?;
+            n |= u64::from(b & 0x7F) << shift;
+
+            if b & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+
+        Err(self.error("integer too large"))
+    }
+
+    /// an error for the byte that was just read
+    pub(crate) fn error(&self, message: &str) -> std::io::Error {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("{message} at byte {}", self.offset.saturating_sub(1)),
+        )
+    }
+
+    fn unexpected_end(&self) -> std::io::Error {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("unexpected end of input at byte {}", self.buf.len()),
+        )
+    }
+}
+
+/// writes `n` as an unsigned LEB128 integer, as read by [`Input::varint`]
+pub(crate) fn write_varint(buf: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        buf.push((n as u8 & 0x7F) | 0x80);
+        n >>= 7;
+    }
+
+    buf.push(n as u8);
+}
+
+/// decodes `bytes` as UTF-8 text, as both formats require
+pub(crate) fn text<'input>(input: &Input, bytes: &'input [u8]) -> std::io::Result<&'input str> {
+    std::str::from_utf8(bytes).map_err(|_| input.error("invalid UTF-8 in string"))
+}
+
+fn invalid(message: &'static str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,258 @@
+//! CBOR (RFC 8949) input.
+//!
+//! a sequence of top-level items (RFC 8742) is written in turn, like multiple
+//! top-level values in JSON input.
+//! see [`super::binary`] for how values that JSON doesn't have are written.
+//! in addition, `undefined` is written as `null`, negative integers below `i64::MIN`
+//! are written as floats, and simple values other than `true`, `false`,
+//! `null` and `undefined` are written as numbers.
+
+use super::binary::{Decoder, Input, Item, drive, text};
+use crate::Number;
+use crate::path_value_writer::PathValueWriter;
+use std::borrow::Cow;
+
+pub fn stream<W: PathValueWriter + ?Sized>(buf: &[u8], writer: &mut W) -> std::io::Result<()> {
+    drive(&mut CborDecoder(Input::new(buf)), writer)
+}
+
+struct CborDecoder<'input>(Input<'input>);
+
+const BREAK: u8 = 0xFF;
+
+impl<'input> CborDecoder<'input> {
+    /// the argument of an item with `info`, the low 5 bits of its initial byte.
+    /// `None` for indefinite length
+    fn argument(&mut self, info: u8) -> std::io::Result<Option<u64>> {
+        match info {
+            0..=23 => Ok(Some(info as u64)),
+            24 => Ok(Some(self.0.u8()? as u64)),
+            25 => Ok(Some(self.0.u16()? as u64)),
+            26 => Ok(Some(self.0.u32()? as u64)),
+            27 => Ok(Some(self.0.u64()?)),
+            31 => Ok(None),
+            _ => Err(self.0.error("reserved additional information")),
+        }
+    }
+
+    fn definite_argument(&mut self, info: u8) -> std::io::Result<u64> {
+        self.argument(info)?
+            .ok_or_else(|| self.0.error("unexpected indefinite length"))
+    }
+
+    /// reads a byte or text string with major type `major`, concatenating the chunks
+    /// of an indefinite-length string
+    fn string(&mut self, major: u8, info: u8) -> std::io::Result<Cow<'input, [u8]>> {
+        if let Some(len) = self.argument(info)? {
+            return Ok(Cow::Borrowed(self.0.take(len)?));
+        }
+
+        let mut bytes = vec![];
+
+        loop {
+            let initial = self.0.u8()?;
+
+            if initial == BREAK {
+                return Ok(Cow::Owned(bytes));
+            }
+
+            if initial >> 5 != major {
+                return Err(self.0.error("invalid chunk in indefinite-length string"));
+            }
+
+            let len = self.definite_argument(initial & 0x1F)?;
+            let chunk = self.0.take(len)?;
+
+            if major == 3 {
+                // each chunk must be valid UTF-8 on its own
+                text(&self.0, chunk)?;
+            }
+
+            bytes.extend_from_slice(chunk);
+        }
+    }
+}
+
+impl<'input> Decoder<'input> for CborDecoder<'input> {
+    fn next_item(&mut self) -> std::io::Result<Option<Item<'input>>> {
+        loop {
+            if self.0.is_empty() {
+                return Ok(None);
+            }
+
+            let initial = self.0.u8()?;
+            let major = initial >> 5;
+            let info = initial & 0x1F;
+
+            let item = match major {
+                0 => Item::Number(Number::PosInt(self.definite_argument(info)?)),
+                1 => {
+                    let n = self.definite_argument(info)?;
+                    match i64::try_from(n) {
+                        Ok(n) => Item::Number(Number::NegInt(-1 - n)),
+                        Err(_) => Item::Number(Number::Float(-1.0 - n as f64)),
+                    }
+                }
+                2 => Item::Bytes(self.string(major, info)?),
+                3 => match self.string(major, info)? {
+                    Cow::Borrowed(bytes) => Item::Text(Cow::Borrowed(text(&self.0, bytes)?)),
+                    Cow::Owned(bytes) => Item::Text(Cow::Owned(
+                        String::from_utf8(bytes).map_err(|_| self.0.error("invalid UTF-8"))?,
+                    )),
+                },
+                4 => Item::Array(self.argument(info)?),
+                5 => Item::Map(self.argument(info)?),
+                6 => {
+                    // tags are dropped
+                    self.definite_argument(info)?;
+                    continue;
+                }
+                _ => match info {
+                    20 => Item::Bool(false),
+                    21 => Item::Bool(true),
+                    22 | 23 => Item::Null,
+                    25 => Item::Number(Number::Float(f16_to_f64(self.0.u16()?))),
+                    26 => Item::Number(Number::Float(f32::from_bits(self.0.u32()?) as f64)),
+                    27 => Item::Number(Number::Float(f64::from_bits(self.0.u64()?))),
+                    31 => Item::Break,
+                    0..=19 => Item::Number(Number::PosInt(info as u64)),
+                    24 => Item::Number(Number::PosInt(self.0.u8()? as u64)),
+                    _ => return Err(self.0.error("reserved additional information")),
+                },
+            };
+
+            return Ok(Some(item));
+        }
+    }
+
+    fn input(&self) -> &Input<'input> {
+        &self.0
+    }
+}
+
+fn f16_to_f64(half: u16) -> f64 {
+    let exponent = (half >> 10) & 0x1F;
+    let mantissa = (half & 0x3FF) as f64;
+
+    let magnitude = match exponent {
+        0 => mantissa * 2f64.powi(-24),
+        31 if mantissa == 0.0 => f64::INFINITY,
+        31 => f64::NAN,
+        _ => (mantissa + 1024.0) * 2f64.powi(exponent as i32 - 25),
+    };
+
+    if half & 0x8000 != 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::stream;
+    use crate::path_value_writer::json_pointer::{
+        Options as JSONPointerWriterOptions, Writer as JSONPointerWriter,
+    };
+
+    fn lines(buf: &[u8]) -> String {
+        let mut out = vec![];
+        let mut writer = JSONPointerWriter::new(
+            &mut out,
+            JSONPointerWriterOptions::default().write_empty_collections(true),
+        );
+        stream(buf, &mut writer).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn matches_json_fixture() {
+        let s = std::fs::read("fixtures/one.json").unwrap();
+
+        let mut json = vec![];
+        let mut writer = JSONPointerWriter::new(
+            &mut json,
+            JSONPointerWriterOptions::default().write_empty_collections(true),
+        );
+        crate::stream(&s, &mut writer).unwrap();
+
+        assert_eq!(
+            lines(&std::fs::read("fixtures/binary/one.cbor").unwrap()),
+            std::str::from_utf8(&json).unwrap()
+        );
+    }
+
+    #[test]
+    fn values_without_a_json_equivalent() {
+        #[rustfmt::skip]
+        let buf = [
+            0xA6, // map(6)
+                0x01, 0x61, b'a', // 1: "a"
+                0xF5, 0x42, 0xFB, 0xFF, // true: h'fbff'
+                0x61, b't', 0xC1, 0x1A, 0x51, 0x4B, 0x67, 0xB0, // "t": 1(1363896240)
+                0x61, b'h', 0xF9, 0x3E, 0x00, // "h": 1.5 as a half float
+                0x61, b'n', 0x3B, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, // "n": -2^64
+                0x61, b'u', 0xF7, // "u": undefined
+            0x80, // a second top-level item, []
+        ];
+
+        assert_eq!(
+            lines(&buf),
+            "/1\t\"a\"\n\
+             /true\t\"+/8=\"\n\
+             /t\t1363896240\n\
+             /h\t1.5\n\
+             /n\t-1.8446744073709552e19\n\
+             /u\tnull\n\
+             \t[]\n"
+        );
+    }
+
+    #[test]
+    fn non_finite_floats() {
+        #[rustfmt::skip]
+        let buf = [
+            0x83, // array(3)
+                0xF9, 0x7C, 0x00, // Infinity as a half float
+                0xFA, 0xFF, 0x80, 0x00, 0x00, // -Infinity as a single float
+                0xFB, 0x7F, 0xF8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // NaN as a double
+        ];
+
+        assert_eq!(lines(&buf), "/0\tInfinity\n/1\t-Infinity\n/2\tNaN\n");
+    }
+
+    #[test]
+    fn indefinite_lengths() {
+        #[rustfmt::skip]
+        let buf = [
+            0xBF, // map(*)
+                0x63, b'a', b'r', b'r', 0x9F, // "arr": [*
+                    0x7F, 0x62, b'a', b'b', 0x61, b'c', 0xFF, // (_ "ab", "c")
+                    0x9F, 0xFF, // [*]
+                    0x20, // -1
+                0xFF,
+                0x63, b'm', b'a', b'p', 0xBF, 0xFF, // "map": {*}
+            0xFF,
+        ];
+
+        assert_eq!(
+            lines(&buf),
+            "/arr/0\t\"abc\"\n/arr/1\t[]\n/arr/2\t-1\n/map\t{}\n"
+        );
+    }
+
+    #[test]
+    fn invalid_cbor() {
+        let mut out = vec![];
+        let mut writer = JSONPointerWriter::new(&mut out, JSONPointerWriterOptions::default());
+
+        // array(2) with only one item
+        assert!(stream(&[0x82, 0x01], &mut writer).is_err());
+        // a map as a map key
+        assert!(stream(&[0xA1, 0xA0, 0x01], &mut writer).is_err());
+        // a break outside of an indefinite-length item
+        assert!(stream(&[0xFF], &mut writer).is_err());
+        // a text string that isn't UTF-8
+        assert!(stream(&[0x61, 0xFF], &mut writer).is_err());
+    }
+}
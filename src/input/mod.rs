@@ -0,0 +1,86 @@
+//! front-ends for input formats other than JSON.
+//!
+//! each one walks its input and drives a [`PathValueWriter`] with the same
+//! [`PathComponent`] paths and [`JsonAtom`] values that [`crate::stream`]
+//! would for the equivalent JSON document,
+//! so every writer works with every input format.
+
+use crate::EscapedStr;
+use crate::escape::escape_json_str;
+use crate::path_value_writer::PathValueWriter;
+use crate::{JsonAtom, PathComponent};
+
+pub(crate) mod binary;
+pub mod cbor;
+pub mod msgpack;
+pub mod toml;
+pub mod yaml;
+
+/// a path whose keys are owned rather than borrowed from the input.
+///
+/// [`PathComponent::Key`] holds an escaped JSON string, which most input formats
+/// don't have lying around to borrow, so keys are escaped into here instead.
+#[derive(Debug, Default)]
+pub(crate) struct OwnedPath {
+    components: Vec<OwnedPathComponent>,
+    /// reused for escaping string values
+    scratch: String,
+}
+
+#[derive(Debug)]
+enum OwnedPathComponent {
+    /// escaped
+    Key(String),
+    Index(usize),
+}
+
+impl OwnedPath {
+    /// `key` is unescaped
+    pub(crate) fn push_key(&mut self, key: &str) {
+        let mut escaped = String::with_capacity(key.len());
+        escape_json_str(key, &mut escaped);
+        self.components.push(OwnedPathComponent::Key(escaped));
+    }
+
+    pub(crate) fn push_index(&mut self, index: usize) {
+        self.components.push(OwnedPathComponent::Index(index));
+    }
+
+    pub(crate) fn pop(&mut self) {
+        self.components.pop();
+    }
+
+    pub(crate) fn write_value<W: PathValueWriter + ?Sized>(
+        &self,
+        writer: &mut W,
+        value: JsonAtom,
+    ) -> std::io::Result<()> {
+        let path: Vec<PathComponent> = self
+            .components
+            .iter()
+            .map(|component| match component {
+                OwnedPathComponent::Key(k) => PathComponent::Key(EscapedStr::new(k)),
+                OwnedPathComponent::Index(i) => PathComponent::Index(*i),
+            })
+            .collect();
+
+        writer.write_path_and_value(&path, value)
+    }
+
+    /// `s` is unescaped
+    pub(crate) fn write_string<W: PathValueWriter + ?Sized>(
+        &mut self,
+        writer: &mut W,
+        s: &str,
+    ) -> std::io::Result<()> {
+        let mut scratch = std::mem::take(&mut self.scratch);
+        scratch.clear();
+        escape_json_str(s, &mut scratch);
+
+        let result = self.write_value(writer, JsonAtom::String(EscapedStr::new(&scratch)));
+
+        self.scratch = scratch;
+
+        result
+    }
+}
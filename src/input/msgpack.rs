@@ -0,0 +1,210 @@
+//! MessagePack input.
+//!
+//! a sequence of top-level values is written in turn, like multiple
+//! top-level values in JSON input.
+//! see [`super::binary`] for how values that JSON doesn't have are written.
+
+use super::binary::{Decoder, Input, Item, drive, text};
+use crate::Number;
+use crate::path_value_writer::PathValueWriter;
+use std::borrow::Cow;
+
+pub fn stream<W: PathValueWriter + ?Sized>(buf: &[u8], writer: &mut W) -> std::io::Result<()> {
+    drive(&mut MsgpackDecoder(Input::new(buf)), writer)
+}
+
+struct MsgpackDecoder<'input>(Input<'input>);
+
+impl<'input> MsgpackDecoder<'input> {
+    fn text(&mut self, len: u64) -> std::io::Result<Item<'input>> {
+        let bytes = self.0.take(len)?;
+        Ok(Item::Text(Cow::Borrowed(text(&self.0, bytes)?)))
+    }
+
+    fn bytes(&mut self, len: u64) -> std::io::Result<Item<'input>> {
+        Ok(Item::Bytes(Cow::Borrowed(self.0.take(len)?)))
+    }
+
+    fn extension(&mut self, len: u64) -> std::io::Result<Item<'input>> {
+        let type_ = self.0.u8()? as i8;
+        let data = self.0.take(len)?;
+        Ok(Item::Extension { type_, data })
+    }
+}
+
+fn int(i: i64) -> Item<'static> {
+    if i < 0 {
+        Item::Number(Number::NegInt(i))
+    } else {
+        Item::Number(Number::PosInt(i as u64))
+    }
+}
+
+impl<'input> Decoder<'input> for MsgpackDecoder<'input> {
+    fn next_item(&mut self) -> std::io::Result<Option<Item<'input>>> {
+        if self.0.is_empty() {
+            return Ok(None);
+        }
+
+        let item = match self.0.u8()? {
+            b @ 0x00..=0x7F => Item::Number(Number::PosInt(b as u64)),
+            b @ 0x80..=0x8F => Item::Map(Some((b & 0x0F) as u64)),
+            b @ 0x90..=0x9F => Item::Array(Some((b & 0x0F) as u64)),
+            b @ 0xA0..=0xBF => self.text((b & 0x1F) as u64)?,
+            0xC0 => Item::Null,
+            0xC1 => return Err(self.0.error("invalid type 0xc1")),
+            0xC2 => Item::Bool(false),
+            0xC3 => Item::Bool(true),
+            0xC4 => {
+                let len = self.0.u8()?;
+                self.bytes(len as u64)?
+            }
+            0xC5 => {
+                let len = self.0.u16()?;
+                self.bytes(len as u64)?
+            }
+            0xC6 => {
+                let len = self.0.u32()?;
+                self.bytes(len as u64)?
+            }
+            0xC7 => {
+                let len = self.0.u8()?;
+                self.extension(len as u64)?
+            }
+            0xC8 => {
+                let len = self.0.u16()?;
+                self.extension(len as u64)?
+            }
+            0xC9 => {
+                let len = self.0.u32()?;
+                self.extension(len as u64)?
+            }
+            0xCA => Item::Number(Number::Float(f32::from_bits(self.0.u32()?) as f64)),
+            0xCB => Item::Number(Number::Float(f64::from_bits(self.0.u64()?))),
+            0xCC => Item::Number(Number::PosInt(self.0.u8()? as u64)),
+            0xCD => Item::Number(Number::PosInt(self.0.u16()? as u64)),
+            0xCE => Item::Number(Number::PosInt(self.0.u32()? as u64)),
+            0xCF => Item::Number(Number::PosInt(self.0.u64()?)),
+            0xD0 => int(self.0.u8()? as i8 as i64),
+            0xD1 => int(self.0.u16()? as i16 as i64),
+            0xD2 => int(self.0.u32()? as i32 as i64),
+            0xD3 => int(self.0.u64()? as i64),
+            0xD4 => self.extension(1)?,
+            0xD5 => self.extension(2)?,
+            0xD6 => self.extension(4)?,
+            0xD7 => self.extension(8)?,
+            0xD8 => self.extension(16)?,
+            0xD9 => {
+                let len = self.0.u8()?;
+                self.text(len as u64)?
+            }
+            0xDA => {
+                let len = self.0.u16()?;
+                self.text(len as u64)?
+            }
+            0xDB => {
+                let len = self.0.u32()?;
+                self.text(len as u64)?
+            }
+            0xDC => Item::Array(Some(self.0.u16()? as u64)),
+            0xDD => Item::Array(Some(self.0.u32()? as u64)),
+            0xDE => Item::Map(Some(self.0.u16()? as u64)),
+            0xDF => Item::Map(Some(self.0.u32()? as u64)),
+            b @ 0xE0..=0xFF => int(b as i8 as i64),
+        };
+
+        Ok(Some(item))
+    }
+
+    fn input(&self) -> &Input<'input> {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::stream;
+    use crate::path_value_writer::json_pointer::{
+        Options as JSONPointerWriterOptions, Writer as JSONPointerWriter,
+    };
+
+    fn lines(buf: &[u8]) -> String {
+        let mut out = vec![];
+        let mut writer = JSONPointerWriter::new(
+            &mut out,
+            JSONPointerWriterOptions::default().write_empty_collections(true),
+        );
+        stream(buf, &mut writer).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn matches_json_fixture() {
+        let s = std::fs::read("fixtures/one.json").unwrap();
+
+        let mut json = vec![];
+        let mut writer = JSONPointerWriter::new(
+            &mut json,
+            JSONPointerWriterOptions::default().write_empty_collections(true),
+        );
+        crate::stream(&s, &mut writer).unwrap();
+
+        assert_eq!(
+            lines(&std::fs::read("fixtures/binary/one.msgpack").unwrap()),
+            std::str::from_utf8(&json).unwrap()
+        );
+    }
+
+    #[test]
+    fn values_without_a_json_equivalent() {
+        #[rustfmt::skip]
+        let buf = [
+            0x86, // map(6)
+                0xFF, 0xA1, b'a', // -1: "a"
+                0xC0, 0xC4, 0x02, 0xFB, 0xFF, // nil: bin8 fbff
+                0xA1, b'e', 0xD6, 0xFF, 0x51, 0x4B, 0x67, 0xB0, // "e": fixext4 -1 (a timestamp)
+                0xA1, b'f', 0xCA, 0x3F, 0xC0, 0x00, 0x00, // "f": float32 1.5
+                0xA1, b'i', 0xD3, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // "i": i64::MIN
+                0xA1, b'u', 0xCF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, // "u": u64::MAX
+            0x90, // a second top-level value, []
+        ];
+
+        assert_eq!(
+            lines(&buf),
+            "/-1\t\"a\"\n\
+             /null\t\"+/8=\"\n\
+             /e/type\t-1\n\
+             /e/data\t\"UUtnsA==\"\n\
+             /f\t1.5\n\
+             /i\t-9223372036854775808\n\
+             /u\t18446744073709551615\n\
+             \t[]\n"
+        );
+    }
+
+    #[test]
+    fn non_finite_floats() {
+        #[rustfmt::skip]
+        let buf = [
+            0x93, // array(3)
+                0xCA, 0x7F, 0x80, 0x00, 0x00, // float32 Infinity
+                0xCB, 0xFF, 0xF0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // float64 -Infinity
+                0xCA, 0x7F, 0xC0, 0x00, 0x00, // float32 NaN
+        ];
+
+        assert_eq!(lines(&buf), "/0\tInfinity\n/1\t-Infinity\n/2\tNaN\n");
+    }
+
+    #[test]
+    fn invalid_msgpack() {
+        let mut out = vec![];
+        let mut writer = JSONPointerWriter::new(&mut out, JSONPointerWriterOptions::default());
+
+        // fixarray(2) with only one item
+        assert!(stream(&[0x92, 0x01], &mut writer).is_err());
+        // str8 longer than the input
+        assert!(stream(&[0xD9, 0x05, b'a'], &mut writer).is_err());
+        // the never-used type
+        assert!(stream(&[0xC1], &mut writer).is_err());
+    }
+}
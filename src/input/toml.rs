@@ -0,0 +1,142 @@
+//! TOML input.
+//!
+//! tables (including inline tables) become objects and arrays (including arrays of tables)
+//! become arrays, keeping the order they appear in the document.
+//! datetimes are written as strings in their RFC 3339 form, as TOML itself writes them.
+
+use super::OwnedPath;
+use crate::JsonAtom;
+use crate::Number;
+use crate::path_value_writer::PathValueWriter;
+use toml::{Table, Value};
+
+pub fn stream<W: PathValueWriter + ?Sized>(buf: &[u8], writer: &mut W) -> std::io::Result<()> {
+    let s = std::str::from_utf8(buf)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+    let table: Table = s
+        .parse()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+    let mut path = OwnedPath::default();
+
+    writer.start_document()?;
+    walk_table(&table, &mut path, writer)
+}
+
+fn walk_table<W: PathValueWriter + ?Sized>(
+    table: &Table,
+    path: &mut OwnedPath,
+    writer: &mut W,
+) -> std::io::Result<()> {
+    if table.is_empty() {
+        return path.write_value(writer, JsonAtom::EmptyObject);
+    }
+
+    for (key, value) in table {
+        path.push_key(key);
+        walk(value, path, writer)?;
+        path.pop();
+    }
+
+    Ok(())
+}
+
+fn walk<W: PathValueWriter + ?Sized>(
+    value: &Value,
+    path: &mut OwnedPath,
+    writer: &mut W,
+) -> std::io::Result<()> {
+    match value {
+        Value::Table(table) => walk_table(table, path, writer),
+        Value::Array(array) if array.is_empty() => path.write_value(writer, JsonAtom::EmptyArray),
+        Value::Array(array) => {
+            for (i, value) in array.iter().enumerate() {
+                path.push_index(i);
+                walk(value, path, writer)?;
+                path.pop();
+            }
+            Ok(())
+        }
+        Value::String(s) => path.write_string(writer, s),
+        Value::Integer(i) => {
+            let n = if *i < 0 {
+                Number::NegInt(*i)
+            } else {
+                Number::PosInt(*i as u64)
+            };
+            path.write_value(writer, JsonAtom::Number(n))
+        }
+        Value::Float(f) => path.write_value(writer, JsonAtom::Number(Number::Float(*f))),
+        Value::Boolean(b) => path.write_value(writer, JsonAtom::Bool(*b)),
+        Value::Datetime(datetime) => path.write_string(writer, &datetime.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::stream;
+    use crate::path_value_writer::json_pointer::{
+        Options as JSONPointerWriterOptions, Writer as JSONPointerWriter,
+    };
+
+    #[test]
+    fn tables_arrays_and_scalars() {
+        let s = br#"
+title = "jstream"
+released = 1979-05-27T07:32:00Z
+birthday = 1979-05-27
+
+[package]
+name = "jstream"
+version = "0.1.0"
+edition = 2024
+keywords = []
+
+[package.metadata]
+"quoted key/with slash" = { inline = true }
+
+[[bin]]
+name = "jstream"
+ratio = -0.5
+
+[[bin]]
+name = "other"
+offset = -3
+"#;
+
+        let mut buf = vec![];
+        let mut writer = JSONPointerWriter::new(
+            &mut buf,
+            JSONPointerWriterOptions::default().write_empty_collections(true),
+        );
+
+        stream(s, &mut writer).unwrap();
+
+        let challenge = r#"/title	"jstream"
+/released	"1979-05-27T07:32:00Z"
+/birthday	"1979-05-27"
+/package/name	"jstream"
+/package/version	"0.1.0"
+/package/edition	2024
+/package/keywords	[]
+/package/metadata/quoted key/with slash/inline	true
+/bin/0/name	"jstream"
+/bin/0/ratio	-0.5
+/bin/1/name	"other"
+/bin/1/offset	-3
+"#;
+
+        assert_eq!(std::str::from_utf8(&buf).unwrap(), challenge);
+    }
+
+    #[test]
+    fn invalid_toml() {
+        let mut buf = vec![];
+        let mut writer = JSONPointerWriter::new(&mut buf, JSONPointerWriterOptions::default());
+
+        let e = stream(b"a = ", &mut writer).unwrap_err();
+
+        assert_eq!(e.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
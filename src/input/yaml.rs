@@ -0,0 +1,191 @@
+//! YAML input.
+//!
+//! every document in a multi-document stream (separated by `---`) is written
+//! in turn, with paths starting from the root, just like
+//! multiple top-level values in JSON input.
+//! anchors and aliases are resolved, so aliased values appear at every path
+//! that refers to them.
+
+use super::OwnedPath;
+use crate::JsonAtom;
+use crate::Number;
+use crate::path_value_writer::PathValueWriter;
+use yaml_rust2::{Yaml, YamlLoader};
+
+pub fn stream<W: PathValueWriter + ?Sized>(buf: &[u8], writer: &mut W) -> std::io::Result<()> {
+    let s = std::str::from_utf8(buf)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+    let documents = YamlLoader::load_from_str(s)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+    let mut path = OwnedPath::default();
+
+    for document in &documents {
+        writer.start_document()?;
+        walk(document, &mut path, writer)?;
+    }
+
+    Ok(())
+}
+
+fn walk<W: PathValueWriter + ?Sized>(
+    yaml: &Yaml,
+    path: &mut OwnedPath,
+    writer: &mut W,
+) -> std::io::Result<()> {
+    match yaml {
+        Yaml::Hash(hash) if hash.is_empty() => path.write_value(writer, JsonAtom::EmptyObject),
+        Yaml::Hash(hash) => {
+            for (key, value) in hash {
+                path.push_key(&key_to_string(key)?);
+                walk(value, path, writer)?;
+                path.pop();
+            }
+            Ok(())
+        }
+        Yaml::Array(array) if array.is_empty() => path.write_value(writer, JsonAtom::EmptyArray),
+        Yaml::Array(array) => {
+            for (i, value) in array.iter().enumerate() {
+                path.push_index(i);
+                walk(value, path, writer)?;
+                path.pop();
+            }
+            Ok(())
+        }
+        Yaml::String(s) => path.write_string(writer, s),
+        Yaml::Integer(i) => path.write_value(writer, JsonAtom::Number(integer(*i))),
+        Yaml::Real(_) => match yaml.as_f64() {
+            Some(f) => path.write_value(writer, JsonAtom::Number(Number::Float(f))),
+            None => Err(invalid("invalid YAML float")),
+        },
+        Yaml::Boolean(b) => path.write_value(writer, JsonAtom::Bool(*b)),
+        Yaml::Null => path.write_value(writer, JsonAtom::Null),
+        Yaml::Alias(_) => Err(invalid("unresolved YAML alias")),
+        Yaml::BadValue => Err(invalid("invalid YAML value")),
+    }
+}
+
+/// YAML keys can be any scalar, not just strings, so they're written as they would
+/// appear in the YAML text, i.e., `{1: a, true: b}` has keys `"1"` and `"true"`.
+/// collections can't be keys here, as there is no equivalent path.
+fn key_to_string(key: &Yaml) -> std::io::Result<String> {
+    match key {
+        Yaml::String(s) | Yaml::Real(s) => Ok(s.clone()),
+        Yaml::Integer(i) => Ok(i.to_string()),
+        Yaml::Boolean(b) => Ok(b.to_string()),
+        Yaml::Null => Ok("null".to_string()),
+        _ => Err(invalid("YAML mapping keys must be scalars")),
+    }
+}
+
+fn integer(i: i64) -> Number {
+    if i < 0 {
+        Number::NegInt(i)
+    } else {
+        Number::PosInt(i as u64)
+    }
+}
+
+fn invalid(message: &'static str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::stream;
+    use crate::path_value_writer::json_pointer::{
+        Options as JSONPointerWriterOptions, Writer as JSONPointerWriter,
+    };
+
+    #[test]
+    fn multi_document_stream() {
+        let s = br#"
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: "web \"frontend\""
+  labels: {}
+spec:
+  replicas: 3
+  template:
+    spec:
+      containers:
+        - name: web
+          image: nginx:1.25
+          ports: [{containerPort: 80}]
+          args: []
+---
+apiVersion: v1
+kind: Service
+spec:
+  ratio: 0.5
+  enabled: yes
+  debug: false
+  selector: ~
+  1: numeric key
+"#;
+
+        let mut buf = vec![];
+        let mut writer = JSONPointerWriter::new(
+            &mut buf,
+            JSONPointerWriterOptions::default().write_empty_collections(true),
+        );
+
+        stream(s, &mut writer).unwrap();
+
+        let challenge = r#"/apiVersion	"apps/v1"
+/kind	"Deployment"
+/metadata/name	"web \"frontend\""
+/metadata/labels	{}
+/spec/replicas	3
+/spec/template/spec/containers/0/name	"web"
+/spec/template/spec/containers/0/image	"nginx:1.25"
+/spec/template/spec/containers/0/ports/0/containerPort	80
+/spec/template/spec/containers/0/args	[]
+/apiVersion	"v1"
+/kind	"Service"
+/spec/ratio	0.5
+/spec/enabled	"yes"
+/spec/debug	false
+/spec/selector	null
+/spec/1	"numeric key"
+"#;
+
+        assert_eq!(std::str::from_utf8(&buf).unwrap(), challenge);
+    }
+
+    #[test]
+    fn matches_json_for_json_compatible_yaml() {
+        // JSON is (almost) a subset of YAML
+        let s = std::fs::read("fixtures/one.json").unwrap();
+
+        let mut json = vec![];
+        let mut writer = JSONPointerWriter::new(&mut json, JSONPointerWriterOptions::default());
+        crate::stream(&s, &mut writer).unwrap();
+
+        let mut yaml = vec![];
+        let mut writer = JSONPointerWriter::new(&mut yaml, JSONPointerWriterOptions::default());
+        stream(&s, &mut writer).unwrap();
+
+        assert_eq!(
+            std::str::from_utf8(&yaml).unwrap(),
+            std::str::from_utf8(&json).unwrap()
+        );
+    }
+
+    #[test]
+    fn anchors_are_resolved() {
+        let s = b"base: &base {a: 1}\nderived: *base\n";
+
+        let mut buf = vec![];
+        let mut writer = JSONPointerWriter::new(&mut buf, JSONPointerWriterOptions::default());
+
+        stream(s, &mut writer).unwrap();
+
+        assert_eq!(
+            std::str::from_utf8(&buf).unwrap(),
+            "/base/a\t1\n/derived/a\t1\n"
+        );
+    }
+}
@@ -0,0 +1,790 @@
+//! a tokenizer for JSONC and JSON5 that produces the same tokens as
+//! [`crate::tokenizer::Tokens`], so the rest of jstream
+//! doesn't need to know which syntax the input was written in.
+//! numbers and string escapes are checked by the same rules as in that tokenizer,
+//! plus the extra forms JSON5 allows.
+//!
+//! it can also tokenize plain JSON, and carry on where it left off when more input
+//! arrives, for [`crate::streamer::Streamer`].
+
+use crate::Syntax;
+use crate::escape::escape_json_str;
+use crate::token::{EscapedStr, Number, Offset, SyntaxError, Token};
+use std::borrow::Cow;
+use std::iter::Peekable;
+use std::str::Chars;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    Initial,
+    ArrayFirstValueOrEnd,
+    ArrayNextValueOrEnd,
+    ObjectFirstKeyOrEnd,
+    ObjectNextKeyOrEnd,
+    ObjectFieldValue,
+}
+
+pub(crate) struct Tokens<'input> {
+    input: &'input [u8],
+    index: usize,
+    /// the byte offset of `input` in the whole input, added to the offsets of tokens and errors
+    offset: usize,
+    syntax: Syntax,
+    state_stack: Vec<State>,
+    /// whether more input may follow `input`, in which case tokenizing stops,
+    /// rather than failing, at the end of `input`
+    partial: bool,
+    /// set after an error, so that iteration stops
+    failed: bool,
+}
+
+/// the containers that tokenizing stopped inside of, to carry on from with more input
+#[derive(Debug)]
+pub(crate) struct Stack(Vec<State>);
+
+impl Default for Stack {
+    fn default() -> Self {
+        Self(vec![State::Initial])
+    }
+}
+
+impl<'input> Tokens<'input> {
+    pub(crate) fn jsonc(input: &'input [u8]) -> Self {
+        Self::resume(input, 0, Syntax::Jsonc, Stack::default(), false)
+    }
+
+    /// `input` must have been passed through [`normalize_json5_strings`] first,
+    /// as this only reads double-quoted JSON strings
+    pub(crate) fn json5(input: &'input [u8]) -> Self {
+        Self::resume(input, 0, Syntax::Json5, Stack::default(), false)
+    }
+
+    /// tokenizes `input`, which starts at byte `offset` of the whole input,
+    /// inside of the containers in `stack`.
+    ///
+    /// if `partial`, tokenizing stops before a token that `input` ends in the middle of,
+    /// or before, where [`Tokens::suspend`] says, rather than failing.
+    /// `input` must not end in the middle of a string, comment, number or literal.
+    pub(crate) fn resume(
+        input: &'input [u8],
+        offset: usize,
+        syntax: Syntax,
+        stack: Stack,
+        partial: bool,
+    ) -> Self {
+        Self {
+            input,
+            index: 0,
+            offset,
+            syntax,
+            state_stack: stack.0,
+            partial,
+            failed: false,
+        }
+    }
+
+    /// how many bytes of `input` were tokenized, and the containers they end inside of
+    pub(crate) fn suspend(self) -> (usize, Stack) {
+        (self.index, Stack(self.state_stack))
+    }
+
+    fn error(&self, message: &'static str) -> SyntaxError {
+        SyntaxError::new(message, self.offset + self.index)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.index).copied()
+    }
+
+    fn peek_expect(&self) -> Result<u8, SyntaxError> {
+        self.peek()
+            .ok_or_else(|| self.error("unexpected end of input"))
+    }
+
+    fn state(&self) -> State {
+        *self.state_stack.last().unwrap()
+    }
+
+    fn replace_state(&mut self, state: State) {
+        *self.state_stack.last_mut().unwrap() = state;
+    }
+
+    /// skips whitespace and comments
+    fn skip_insignificant(&mut self) -> Result<(), SyntaxError> {
+        loop {
+            let rest = &self.input[self.index..];
+
+            match rest {
+                [b' ' | b'\t' | b'\n' | b'\r', ..] => self.index += 1,
+                // vertical tab and form feed
+                [0x0B | 0x0C, ..] if self.syntax == Syntax::Json5 => self.index += 1,
+                // no-break space
+                [0xC2, 0xA0, ..] if self.syntax == Syntax::Json5 => self.index += 2,
+                // byte order mark, line separator, paragraph separator
+                [0xEF, 0xBB, 0xBF, ..] | [0xE2, 0x80, 0xA8 | 0xA9, ..]
+                    if self.syntax == Syntax::Json5 =>
+                {
+                    self.index += 3
+                }
+                [b'/', b'/', ..] if self.syntax != Syntax::Json => {
+                    self.index += rest.iter().position(|b| *b == b'\n').unwrap_or(rest.len());
+                }
+                [b'/', b'*', ..] if self.syntax != Syntax::Json => {
+                    let Some(end) = rest[2..].windows(2).position(|w| w == b"*/") else {
+                        return Err(self.error("unterminated block comment"));
+                    };
+                    self.index += end + 4;
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// reads a double-quoted string, returning its escaped contents
+    fn read_string(&mut self) -> Result<&'input str, SyntaxError> {
+        let start = self.index + 1;
+        let mut i = start;
+
+        loop {
+            match self.input.get(i) {
+                Some(b'"') => break,
+                Some(b'\\') => match self.input.get(i + 1) {
+                    Some(b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't') => i += 2,
+                    Some(b'u') if is_hex_escape(&self.input[i..]) => i += 6,
+                    None => {
+                        self.index = self.input.len();
+                        return Err(self.error("unterminated string"));
+                    }
+                    Some(_) => {
+                        self.index = i;
+                        return Err(self.error("invalid escape in string"));
+                    }
+                },
+                Some(0x00..=0x1F) => {
+                    self.index = i;
+                    return Err(self.error("unescaped control character in string"));
+                }
+                Some(_) => i += 1,
+                None => {
+                    self.index = self.input.len();
+                    return Err(self.error("unterminated string"));
+                }
+            }
+        }
+
+        let s = std::str::from_utf8(&self.input[start..i]).map_err(|_| {
+            self.index = start;
+            self.error("invalid UTF-8 in string")
+        })?;
+
+        self.index = i + 1;
+
+        Ok(s)
+    }
+
+    /// reads a JSON5 identifier, used as an unquoted object key.
+    /// anything non-ASCII is allowed, rather than checking Unicode categories.
+    fn read_identifier(&mut self) -> Result<&'input str, SyntaxError> {
+        let start = self.index;
+
+        while let Some(b) = self.peek() {
+            match b {
+                b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_' | b'$' | 0x80.. => self.index += 1,
+                // identifiers can contain `\uXXXX` escapes, which are also valid in JSON strings
+                b'\\' if is_hex_escape(&self.input[self.index..]) => self.index += 6,
+                _ => break,
+            }
+        }
+
+        if self.index == start || self.input[start].is_ascii_digit() {
+            self.index = start;
+            return Err(self.error("expected an object key"));
+        }
+
+        let end = self.index.min(self.input.len());
+
+        std::str::from_utf8(&self.input[start..end]).map_err(|_| {
+            self.index = start;
+            self.error("invalid UTF-8 in object key")
+        })
+    }
+
+    fn read_literal(
+        &mut self,
+        literal: &'static [u8],
+        token: Token<'input>,
+    ) -> Result<Token<'input>, SyntaxError> {
+        if self.input[self.index..].starts_with(literal) {
+            self.index += literal.len();
+            Ok(token)
+        } else {
+            Err(self.error("invalid literal"))
+        }
+    }
+
+    fn read_number(&mut self) -> Result<Token<'input>, SyntaxError> {
+        let offset = Offset(self.offset + self.index);
+        let start = self.index;
+
+        let negative = match self.peek() {
+            Some(b'-') => {
+                self.index += 1;
+                true
+            }
+            Some(b'+') if self.syntax == Syntax::Json5 => {
+                self.index += 1;
+                false
+            }
+            _ => false,
+        };
+
+        let unsigned_start = self.index;
+        let rest = &self.input[unsigned_start..];
+
+        if self.syntax == Syntax::Json5 {
+            if rest.starts_with(b"Infinity") {
+                self.index += b"Infinity".len();
+                let value = if negative {
+                    f64::NEG_INFINITY
+                } else {
+                    f64::INFINITY
+                };
+                return Ok(Token::ValueNumber {
+                    offset,
+                    value: Number::Float(value),
+                });
+            }
+
+            if rest.starts_with(b"NaN") {
+                self.index += b"NaN".len();
+                return Ok(Token::ValueNumber {
+                    offset,
+                    value: Number::Float(f64::NAN),
+                });
+            }
+
+            if rest.starts_with(b"0x") || rest.starts_with(b"0X") {
+                self.index += 2;
+                let digits_start = self.index;
+                while let Some(b'0'..=b'9' | b'a'..=b'f' | b'A'..=b'F') = self.peek() {
+                    self.index += 1;
+                }
+                let digits = std::str::from_utf8(&self.input[digits_start..self.index]).unwrap();
+                let value = u64::from_str_radix(digits, 16)
+                    .map_err(|_| SyntaxError::new("invalid number", self.offset + start))?;
+                return Ok(Token::ValueNumber {
+                    offset,
+                    value: integer(negative, value),
+                });
+            }
+        }
+
+        let mut floating = false;
+
+        while let Some(b) = self.peek() {
+            match b {
+                b'0'..=b'9' => {}
+                b'.' | b'e' | b'E' => floating = true,
+                b'+' | b'-' if matches!(self.input[self.index - 1], b'e' | b'E') => {}
+                _ => break,
+            }
+            self.index += 1;
+        }
+
+        let digits = std::str::from_utf8(&self.input[unsigned_start..self.index]).unwrap();
+
+        // the same rules as the JSON tokenizer: the float parser decides which
+        // decimal points are allowed, floats must be finite, and integers
+        // too large for a `u64` become floats, so must be finite too
+        let parse_float = || {
+            digits
+                .parse::<f64>()
+                .ok()
+                .filter(|f| f.is_finite())
+                .map(|f| Number::Float(if negative { -f } else { f }))
+        };
+
+        let value = if digits.is_empty() {
+            None
+        } else if floating {
+            parse_float()
+        } else {
+            match digits.parse::<u64>() {
+                Ok(i) => Some(integer(negative, i)),
+                Err(_) => parse_float(),
+            }
+        };
+
+        match value {
+            Some(value) => Ok(Token::ValueNumber { offset, value }),
+            None => Err(SyntaxError::new("invalid number", self.offset + start)),
+        }
+    }
+
+    fn read_value(&mut self) -> Result<Token<'input>, SyntaxError> {
+        let offset = Offset(self.offset + self.index);
+
+        match self.peek_expect()? {
+            b'{' => {
+                self.index += 1;
+                self.state_stack.push(State::ObjectFirstKeyOrEnd);
+                Ok(Token::StartObject { offset })
+            }
+            b'[' => {
+                self.index += 1;
+                self.state_stack.push(State::ArrayFirstValueOrEnd);
+                Ok(Token::StartArray { offset })
+            }
+            b'"' => Ok(Token::ValueString {
+                offset,
+                value: EscapedStr::new(self.read_string()?),
+            }),
+            b'n' => self.read_literal(b"null", Token::ValueNull { offset }),
+            b't' => self.read_literal(
+                b"true",
+                Token::ValueBool {
+                    offset,
+                    value: true,
+                },
+            ),
+            b'f' => self.read_literal(
+                b"false",
+                Token::ValueBool {
+                    offset,
+                    value: false,
+                },
+            ),
+            b'-' | b'0'..=b'9' => self.read_number(),
+            b'+' | b'.' | b'I' | b'N' if self.syntax == Syntax::Json5 => self.read_number(),
+            _ => Err(self.error("expected a value")),
+        }
+    }
+
+    fn read_key(&mut self) -> Result<Token<'input>, SyntaxError> {
+        let offset = Offset(self.offset + self.index);
+
+        let key = match self.peek_expect()? {
+            b'"' => self.read_string()?,
+            _ if self.syntax == Syntax::Json5 => self.read_identifier()?,
+            _ => return Err(self.error("expected an object key")),
+        };
+
+        self.skip_insignificant()?;
+
+        if self.peek_expect()? != b':' {
+            return Err(self.error("expected ':'"));
+        }
+
+        self.index += 1;
+        self.replace_state(State::ObjectFieldValue);
+
+        Ok(Token::ObjectKey {
+            offset,
+            key: EscapedStr::new(key),
+        })
+    }
+
+    fn end_container(&mut self) -> Token<'input> {
+        let offset = Offset(self.offset + self.index);
+        let end = self.input[self.index];
+        self.index += 1;
+        self.state_stack.pop();
+
+        if end == b'}' {
+            Token::EndObject { offset }
+        } else {
+            Token::EndArray { offset }
+        }
+    }
+
+    fn next_token(&mut self) -> Option<Result<Token<'input>, SyntaxError>> {
+        if let Err(e) = self.skip_insignificant() {
+            return Some(Err(e));
+        }
+
+        let result = match (self.state(), self.peek()) {
+            (State::Initial, None) => return None,
+            (State::Initial, Some(_)) => self.read_value(),
+            (_, None) => Err(self.error("unexpected end of input")),
+            (State::ArrayFirstValueOrEnd, Some(b']')) => Ok(self.end_container()),
+            (State::ArrayFirstValueOrEnd, Some(_)) => {
+                self.replace_state(State::ArrayNextValueOrEnd);
+                self.read_value()
+            }
+            (State::ArrayNextValueOrEnd, Some(b']')) => Ok(self.end_container()),
+            (State::ObjectFirstKeyOrEnd, Some(b'}')) => Ok(self.end_container()),
+            (State::ObjectFirstKeyOrEnd, Some(_)) => self.read_key(),
+            (State::ObjectNextKeyOrEnd, Some(b'}')) => Ok(self.end_container()),
+            (State::ArrayNextValueOrEnd | State::ObjectNextKeyOrEnd, Some(b',')) => {
+                self.index += 1;
+
+                if let Err(e) = self.skip_insignificant() {
+                    return Some(Err(e));
+                }
+
+                // trailing commas
+                match (self.state(), self.peek()) {
+                    (State::ArrayNextValueOrEnd, Some(b']'))
+                    | (State::ObjectNextKeyOrEnd, Some(b'}'))
+                        if self.syntax != Syntax::Json =>
+                    {
+                        Ok(self.end_container())
+                    }
+                    (State::ArrayNextValueOrEnd, _) => self.read_value(),
+                    _ => self.read_key(),
+                }
+            }
+            (State::ArrayNextValueOrEnd, Some(_)) => Err(self.error("expected ',' or ']'")),
+            (State::ObjectNextKeyOrEnd, Some(_)) => Err(self.error("expected ',' or '}'")),
+            (State::ObjectFieldValue, Some(_)) => {
+                self.replace_state(State::ObjectNextKeyOrEnd);
+                self.read_value()
+            }
+        };
+
+        Some(result)
+    }
+}
+
+impl<'input> Iterator for Tokens<'input> {
+    type Item = Result<Token<'input>, SyntaxError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let (index, state) = (self.index, self.state());
+        let next = self.next_token();
+
+        if let Some(Err(_)) = next {
+            // the rest of the token, or the token after the one started, hasn't arrived yet
+            if self.partial && self.index >= self.input.len() {
+                self.index = index;
+                self.replace_state(state);
+                return None;
+            }
+
+            self.failed = true;
+        }
+
+        next
+    }
+}
+
+/// whether `s` starts with a `\uXXXX` escape
+fn is_hex_escape(s: &[u8]) -> bool {
+    s.starts_with(b"\\u")
+        && s.get(2..6)
+            .is_some_and(|d| d.iter().all(u8::is_ascii_hexdigit))
+}
+
+/// like the JSON tokenizer, negative integers that don't fit in an `i64` become floats
+fn integer(negative: bool, value: u64) -> Number {
+    if !negative {
+        Number::PosInt(value)
+    } else if value <= i64::MAX as u64 + 1 {
+        Number::NegInt((value as i64).wrapping_neg())
+    } else {
+        Number::Float(-(value as f64))
+    }
+}
+
+/// rewrites every JSON5 string that is not also a valid JSON string
+/// (single-quoted strings, and strings with JSON5-only escapes like `\x41` or `\'`)
+/// as a double-quoted JSON string. everything else is left as-is.
+///
+/// most documents have no such strings, in which case `buf` is returned unchanged.
+pub(crate) fn normalize_json5_strings(buf: &[u8]) -> Cow<'_, [u8]> {
+    let mut out: Option<Vec<u8>> = None;
+    // how much of `buf` has already been copied to `out`
+    let mut copied = 0;
+    let mut i = 0;
+
+    while i < buf.len() {
+        match &buf[i..] {
+            [b'/', b'/', rest @ ..] => {
+                i += 2 + rest.iter().position(|b| *b == b'\n').unwrap_or(rest.len());
+            }
+            [b'/', b'*', rest @ ..] => {
+                i += 2 + rest
+                    .windows(2)
+                    .position(|w| w == b"*/")
+                    .map(|end| end + 2)
+                    .unwrap_or(rest.len());
+            }
+            [quote @ (b'"' | b'\''), ..] => {
+                let quote = *quote;
+                let start = i;
+                let mut needs_rewrite = quote == b'\'';
+                i += 1;
+
+                while i < buf.len() && buf[i] != quote {
+                    if buf[i] == b'\\' {
+                        if !matches!(
+                            buf.get(i + 1),
+                            Some(b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't' | b'u')
+                        ) {
+                            needs_rewrite = true;
+                        }
+                        i += 1;
+                    }
+                    i += 1;
+                }
+
+                // an unterminated string is left for the tokenizer to report
+                if i >= buf.len() {
+                    break;
+                }
+
+                i += 1;
+
+                // as is a string that can't be unescaped
+                if needs_rewrite && let Some(unescaped) = unescape_json5(&buf[start + 1..i - 1]) {
+                    let out = out.get_or_insert_with(|| Vec::with_capacity(buf.len()));
+                    out.extend_from_slice(&buf[copied..start]);
+                    out.push(b'"');
+                    let mut escaped = String::new();
+                    escape_json_str(&unescaped, &mut escaped);
+                    out.extend_from_slice(escaped.as_bytes());
+                    out.push(b'"');
+                    copied = i;
+                }
+            }
+            _ => i += 1,
+        }
+    }
+
+    match out {
+        Some(mut out) => {
+            out.extend_from_slice(&buf[copied..]);
+            Cow::Owned(out)
+        }
+        None => Cow::Borrowed(buf),
+    }
+}
+
+/// decodes the contents of a JSON5 string, without its quotes,
+/// or returns `None` if it isn't UTF-8, a `\x` or `\u` escape isn't followed by
+/// enough hex digits, or a `\u` escape is half of a surrogate pair without the other half
+fn unescape_json5(s: &[u8]) -> Option<String> {
+    let s = std::str::from_utf8(s).ok()?;
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+
+        let Some(escaped) = chars.next() else {
+            break;
+        };
+
+        match escaped {
+            'b' => out.push('\u{8}'),
+            'f' => out.push('\u{c}'),
+            'n' => out.push('\n'),
+            'r' => out.push('\r'),
+            't' => out.push('\t'),
+            'v' => out.push('\u{b}'),
+            '0' => out.push('\0'),
+            'x' => out.push(char::from(hex_digits(&mut chars, 2)? as u8)),
+            'u' => {
+                let unit = hex_digits(&mut chars, 4)?;
+
+                let mut units = vec![unit];
+
+                if (0xD800..=0xDBFF).contains(&unit) {
+                    let mut lookahead = chars.clone();
+                    if lookahead.next() == Some('\\')
+                        && lookahead.next() == Some('u')
+                        && let Some(low @ 0xDC00..=0xDFFF) = hex_digits(&mut lookahead, 4)
+                    {
+                        units.push(low);
+                        chars = lookahead;
+                    }
+                }
+
+                for c in char::decode_utf16(units) {
+                    out.push(c.ok()?);
+                }
+            }
+            // line continuations
+            '\n' | '\u{2028}' | '\u{2029}' => {}
+            '\r' => {
+                if chars.peek() == Some(&'\n') {
+                    chars.next();
+                }
+            }
+            // every other escaped character, including quotes and `\`, is itself
+            c => out.push(c),
+        }
+    }
+
+    Some(out)
+}
+
+/// reads the `count` hex digits of a `\x` or `\u` escape
+fn hex_digits(chars: &mut Peekable<Chars>, count: usize) -> Option<u16> {
+    (0..count).try_fold(0, |value, _| {
+        Some(value << 4 | chars.next()?.to_digit(16)? as u16)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::path_value_writer::json_pointer::{
+        Options as JSONPointerWriterOptions, Writer as JSONPointerWriter,
+    };
+    use crate::{Options, Syntax, stream_with_options};
+
+    fn lines(s: &[u8], syntax: Syntax) -> std::io::Result<String> {
+        let mut buf = vec![];
+        let mut writer = JSONPointerWriter::new(&mut buf, JSONPointerWriterOptions::default());
+        stream_with_options(s, &mut writer, &Options::default().syntax(syntax))?;
+        Ok(String::from_utf8(buf).unwrap())
+    }
+
+    #[test]
+    fn jsonc() {
+        let s = br#"
+        // tsconfig.json
+        {
+            /* the compiler options */
+            "compilerOptions": {
+                "strict": true, // very strict
+                "paths": ["a/*", "b/*",],
+            },
+        }"#;
+
+        assert_eq!(
+            lines(s, Syntax::Jsonc).unwrap(),
+            "/compilerOptions/strict\ttrue\n/compilerOptions/paths/0\t\"a/*\"\n/compilerOptions/paths/1\t\"b/*\"\n"
+        );
+
+        assert!(lines(s, Syntax::Json).is_err());
+    }
+
+    #[test]
+    fn jsonc_is_not_json5() {
+        assert!(lines(b"{a: 1}", Syntax::Jsonc).is_err());
+        assert!(lines(b"['a']", Syntax::Jsonc).is_err());
+        assert!(lines(b"[0x10]", Syntax::Jsonc).is_err());
+        assert!(lines(b"[.5]", Syntax::Jsonc).is_err());
+        assert!(lines(b"[1, /* unterminated", Syntax::Jsonc).is_err());
+    }
+
+    #[test]
+    fn json5() {
+        let s = br#"{
+            // comments
+            unquoted: 'and you can quote me on that',
+            singleQuotes: 'I can use "double quotes" here, and \'single\' ones',
+            lineBreaks: "Look, Mom! \
+No \\n's!",
+            hexadecimal: 0xdecaf,
+            negativeHex: -0x10,
+            leadingDecimalPoint: .8675309, andTrailing: 8675309.,
+            positiveSign: +1,
+            trailingComma: 'in objects', andIn: ['arrays',],
+            "backwardsCompatible": "with JSON",
+            $dollar_and_under_score: '\x41B',
+            infinities: [Infinity, -Infinity],
+        }"#;
+
+        assert_eq!(
+            lines(s, Syntax::Json5).unwrap(),
+            "/unquoted\t\"and you can quote me on that\"\n\
+            /singleQuotes\t\"I can use \\\"double quotes\\\" here, and 'single' ones\"\n\
+            /lineBreaks\t\"Look, Mom! No \\\\n's!\"\n\
+            /hexadecimal\t912559\n\
+            /negativeHex\t-16\n\
+            /leadingDecimalPoint\t0.8675309\n\
+            /andTrailing\t8675309.0\n\
+            /positiveSign\t1\n\
+            /trailingComma\t\"in objects\"\n\
+            /andIn/0\t\"arrays\"\n\
+            /backwardsCompatible\t\"with JSON\"\n\
+            /$dollar_and_under_score\t\"AB\"\n\
+            /infinities/0\tInfinity\n\
+            /infinities/1\t-Infinity\n"
+        );
+    }
+
+    #[test]
+    fn json5_nan() {
+        assert_eq!(lines(b"[NaN]", Syntax::Json5).unwrap(), "/0\tNaN\n");
+    }
+
+    #[test]
+    fn lenient_syntaxes_match_json_on_json_input() {
+        for fixture in [
+            "fixtures/one.json",
+            "fixtures/github.json",
+            "fixtures/three.json",
+        ] {
+            let s = std::fs::read(fixture).unwrap();
+            let json = lines(&s, Syntax::Json).unwrap();
+            assert_eq!(lines(&s, Syntax::Jsonc).unwrap(), json, "{fixture}");
+            assert_eq!(lines(&s, Syntax::Json5).unwrap(), json, "{fixture}");
+        }
+    }
+
+    #[test]
+    fn lenient_syntaxes_match_json_on_edge_cases() {
+        // an integer with more digits than `f64::MAX`
+        let too_large = format!("[-1{}]", "0".repeat(309));
+
+        for s in [
+            "[18446744073709551616, -9223372036854775809]",
+            "[1e400000000000000000000]",
+            &too_large,
+            "[1., -.5, 1E+2]",
+            r#"["\u00e9\/"]"#,
+            r#"["\u00"]"#,
+            r#"["\x41"]"#,
+            "[1e999]",
+            "[1.2.3]",
+            "[-]",
+        ] {
+            let json = lines(s.as_bytes(), Syntax::Json).map_err(|e| e.to_string());
+            for syntax in [Syntax::Jsonc, Syntax::Json5] {
+                let lenient = lines(s.as_bytes(), syntax).map_err(|e| e.to_string());
+                // JSON5 allows `\x` escapes
+                if syntax == Syntax::Json5 && s.contains("\\x") {
+                    assert_eq!(lenient.unwrap(), "/0\t\"A\"\n");
+                } else {
+                    assert_eq!(lenient.is_ok(), json.is_ok(), "{s} {syntax:?}");
+                    if let Ok(json) = &json {
+                        assert_eq!(&lenient.unwrap(), json, "{s} {syntax:?}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn json5_strings_that_cannot_be_unescaped() {
+        for s in [
+            r#"['\u00']"#,
+            r#"['\x4']"#,
+            r#"["\u00"]"#,
+            r#"["\x+1"]"#,
+            "{\\u00: 1}",
+            // unpaired surrogates
+            r#"['\ud800']"#,
+            r#"['\udc00\ud800']"#,
+            r#"["\x41\ud83d"]"#,
+        ] {
+            assert!(lines(s.as_bytes(), Syntax::Json5).is_err(), "{s}");
+        }
+
+        assert!(lines(b"['\xff']", Syntax::Json5).is_err());
+        assert_eq!(
+            lines(br#"['\ud83d\ude00']"#, Syntax::Json5).unwrap(),
+            "/0\t\"\u{1F600}\"\n"
+        );
+    }
+}
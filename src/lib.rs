@@ -11,16 +11,35 @@
 //   in the case that we are inside an object and moving from one key to the next,
 //   rather than popping the 2nd newest and pushing the newest
 
-use aws_smithy_json::deserialize::Token;
 use path_value_writer::PathValueWriter;
+use span::Span;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use token::Token;
 
+mod escape;
+pub mod get;
+pub mod grep;
+pub mod index;
+pub mod input;
+mod lenient;
+pub mod path_pattern;
 pub mod path_value_writer;
+pub mod predicate;
+pub mod redact;
+pub mod span;
+pub mod streamer;
+mod token;
+mod tokenizer;
+pub mod validate;
+
+pub use token::{EscapeError, EscapedStr, Number, SyntaxError};
 
 pub type Path<'input> = &'input [PathComponent<'input>];
 
 #[derive(Clone, Copy, Debug)]
 pub enum PathComponent<'input> {
-    Key(aws_smithy_json::deserialize::EscapedStr<'input>),
+    Key(EscapedStr<'input>),
     Index(usize),
 }
 
@@ -29,16 +48,19 @@ pub enum PathComponent<'input> {
 /// this includes empty collections, but not collections
 /// which contain elements
 pub enum JsonAtom<'input> {
-    String(aws_smithy_json::deserialize::EscapedStr<'input>),
+    String(EscapedStr<'input>),
     Null,
     Bool(bool),
-    Number(aws_smithy_types::Number),
+    Number(Number),
     EmptyObject,
     EmptyArray,
 }
 
+/// `K` is how object keys are kept while checking for duplicate keys:
+/// borrowed from the input, or owned, for a [`streamer::Streamer`],
+/// where each piece of the input is gone by the time the next one arrives
 #[derive(Debug, Default)]
-struct State<'input> {
+struct State<'input, K = Cow<'input, str>> {
     /// the current path, in order from least deep to most deep, i.e.,
     /// `{"a": {"b": {"c": 1}}}`
     /// corresponds to:
@@ -48,9 +70,159 @@ struct State<'input> {
     /// `{"a": {"b": {"c": 1}}}`
     /// has depth = 3
     depth: usize,
+    /// for each open object, the keys seen so far and the byte offsets at which they appear.
+    /// only maintained when checking for duplicate keys.
+    object_keys: Vec<HashMap<K, usize>>,
+    /// for each open array, which of its elements are written.
+    /// only maintained when sampling arrays.
+    arrays: Vec<SampledArray>,
+    /// while inside an array element that isn't written, the depth of that array.
+    /// nothing is written until the element ends.
+    skipping_from_depth: Option<usize>,
+    /// used for [`ArraySampling::Rate`] and [`ArraySampling::Reservoir`]
+    rng: SplitMix64,
+    /// the byte offset of the most recent `{` or `[`,
+    /// which is where an empty object or array starts
+    last_open_offset: usize,
+    /// what to do about duplicate keys. `object_keys` is only maintained to look for them
+    /// if they aren't allowed.
+    duplicate_keys: DuplicateKeys,
+    sampling: ArraySampling,
+}
+
+/// an open array, when sampling arrays
+struct SampledArray {
+    /// the depth of the array itself, which is also the depth of its elements' values
+    depth: usize,
+    /// with [`ArraySampling::Reservoir`], the indexes of the sampled elements and their events,
+    /// which are only written once the array ends, in index order
+    reservoir: Vec<(usize, BufferedEvents)>,
+    /// the position in `reservoir` of the element being read, if it is sampled
+    current: Option<usize>,
+}
+
+/// events kept to be written later, with their paths.
+/// they are copied, so that they can outlive the input they were read from.
+type BufferedEvents = Vec<(Vec<OwnedPathComponent>, OwnedEvent)>;
+
+#[derive(Debug)]
+enum OwnedPathComponent {
+    Key(String),
+    Index(usize),
+}
+
+impl From<&PathComponent<'_>> for OwnedPathComponent {
+    fn from(component: &PathComponent) -> Self {
+        match component {
+            PathComponent::Key(k) => OwnedPathComponent::Key(k.as_escaped_str().to_owned()),
+            PathComponent::Index(i) => OwnedPathComponent::Index(*i),
+        }
+    }
+}
+
+impl OwnedPathComponent {
+    fn borrow(&self) -> PathComponent<'_> {
+        match self {
+            OwnedPathComponent::Key(k) => PathComponent::Key(EscapedStr::new(k)),
+            OwnedPathComponent::Index(i) => PathComponent::Index(*i),
+        }
+    }
+}
+
+/// an [`Event`], with any string in it copied
+#[derive(Debug)]
+enum OwnedEvent {
+    Value(OwnedAtom, Span),
+    DuplicateKey { first: usize, second: usize },
+    DocumentStart,
 }
 
-impl<'input> State<'input> {
+#[derive(Debug)]
+enum OwnedAtom {
+    String(String),
+    Null,
+    Bool(bool),
+    Number(Number),
+    EmptyObject,
+    EmptyArray,
+}
+
+impl From<Event<'_>> for OwnedEvent {
+    fn from(event: Event) -> Self {
+        match event {
+            Event::Value(value, span) => {
+                let value = match value {
+                    JsonAtom::String(s) => OwnedAtom::String(s.as_escaped_str().to_owned()),
+                    JsonAtom::Null => OwnedAtom::Null,
+                    JsonAtom::Bool(b) => OwnedAtom::Bool(b),
+                    JsonAtom::Number(n) => OwnedAtom::Number(n),
+                    JsonAtom::EmptyObject => OwnedAtom::EmptyObject,
+                    JsonAtom::EmptyArray => OwnedAtom::EmptyArray,
+                };
+                OwnedEvent::Value(value, span)
+            }
+            Event::DuplicateKey { first, second } => OwnedEvent::DuplicateKey { first, second },
+            Event::DocumentStart => OwnedEvent::DocumentStart,
+        }
+    }
+}
+
+impl OwnedEvent {
+    fn borrow(&self) -> Event<'_> {
+        match self {
+            OwnedEvent::Value(value, span) => {
+                let value = match value {
+                    OwnedAtom::String(s) => JsonAtom::String(EscapedStr::new(s)),
+                    OwnedAtom::Null => JsonAtom::Null,
+                    OwnedAtom::Bool(b) => JsonAtom::Bool(*b),
+                    OwnedAtom::Number(n) => JsonAtom::Number(*n),
+                    OwnedAtom::EmptyObject => JsonAtom::EmptyObject,
+                    OwnedAtom::EmptyArray => JsonAtom::EmptyArray,
+                };
+                Event::Value(value, *span)
+            }
+            OwnedEvent::DuplicateKey { first, second } => Event::DuplicateKey {
+                first: *first,
+                second: *second,
+            },
+            OwnedEvent::DocumentStart => Event::DocumentStart,
+        }
+    }
+}
+
+impl std::fmt::Debug for SampledArray {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SampledArray")
+            .field("depth", &self.depth)
+            .field(
+                "reservoir",
+                &self.reservoir.iter().map(|(i, _)| i).collect::<Vec<_>>(),
+            )
+            .field("current", &self.current)
+            .finish()
+    }
+}
+
+impl<'input, K> State<'input, K>
+where
+    K: From<Cow<'input, str>> + Eq + std::hash::Hash + Default,
+{
+    fn new(options: &Options) -> Self {
+        let mut state = Self {
+            duplicate_keys: options.duplicate_keys,
+            sampling: options.array_sampling,
+            ..Default::default()
+        };
+
+        if let ArraySampling::Rate { seed, .. } | ArraySampling::Reservoir { seed, .. } =
+            options.array_sampling
+        {
+            state.rng = SplitMix64(seed);
+        }
+
+        state
+    }
+
     fn pop_path(&mut self) {
         self.path.pop();
     }
@@ -103,10 +275,7 @@ impl<'input> State<'input> {
     /// which results in paths for values of:
     /// /a/b 1
     /// /a/c 2
-    fn add_new_object_key_to_path(
-        &mut self,
-        key: aws_smithy_json::deserialize::EscapedStr<'input>,
-    ) {
+    fn add_new_object_key_to_path(&mut self, key: EscapedStr<'input>) {
         // if this method is called (in the `ObjectKey` branch),
         // that means we have already hit a `StartObject` token,
         // (as object keys must be within objects),
@@ -129,70 +298,688 @@ impl<'input> State<'input> {
             self.path.push(PathComponent::Key(key));
         }
     }
-}
 
-pub fn stream<W: PathValueWriter>(buf: &[u8], writer: &mut W) -> std::io::Result<()> {
-    let mut state = State::default();
+    fn start_tracking_object_keys(&mut self) {
+        self.object_keys.push(HashMap::new());
+    }
+
+    fn stop_tracking_object_keys(&mut self) {
+        self.object_keys.pop();
+    }
 
-    let tokens = aws_smithy_json::deserialize::json_token_iter(buf);
+    /// records that `key` appears at `offset` in the innermost open object,
+    /// returning the offset of its previous appearance, if any.
+    ///
+    /// keys are compared unescaped, so `"a"` and `"\u0061"` are the same key.
+    fn track_object_key(&mut self, key: EscapedStr<'input>, offset: usize) -> Option<usize> {
+        let key = key
+            .to_unescaped()
+            .unwrap_or(Cow::Borrowed(key.as_escaped_str()));
 
-    for token in tokens {
-        let token = token.map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        let keys = self
+            .object_keys
+            .last_mut()
+            .expect("object keys must be inside an object, this is a bug");
+
+        match keys.entry(K::from(key)) {
+            std::collections::hash_map::Entry::Occupied(entry) => Some(*entry.get()),
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                entry.insert(offset);
+                None
+            }
+        }
+    }
+
+    fn start_sampling_array(&mut self) {
+        self.arrays.push(SampledArray {
+            depth: self.depth,
+            reservoir: vec![],
+            current: None,
+        });
+    }
+
+    /// called at the start of each element of the innermost array,
+    /// to decide whether it is written
+    fn sample_array_element(&mut self, sampling: ArraySampling) {
+        let Some(PathComponent::Index(index)) = self.path.last() else {
+            unreachable!("array elements must have an index, this is a bug")
+        };
+        let index = *index;
+
+        let array = self
+            .arrays
+            .last_mut()
+            .expect("array elements must be inside an array, this is a bug");
+
+        let is_written = match sampling {
+            ArraySampling::All => true,
+            ArraySampling::Limit(limit) => index < limit,
+            ArraySampling::Rate { rate, .. } => self.rng.next_f64() < rate,
+            // algorithm R: the first `size` elements fill the reservoir,
+            // then each later element replaces a random one with probability size / (index + 1)
+            ArraySampling::Reservoir { size, .. } => {
+                array.current = if index < size {
+                    array.reservoir.push((index, vec![]));
+                    Some(array.reservoir.len() - 1)
+                } else {
+                    let j = self.rng.below(index as u64 + 1) as usize;
+                    (j < size).then(|| {
+                        array.reservoir[j] = (index, vec![]);
+                        j
+                    })
+                };
+
+                array.current.is_some()
+            }
+        };
+
+        if !is_written {
+            self.skipping_from_depth = Some(self.depth);
+        }
+    }
+
+    /// advances the state machine past `token`, a token of `buf`,
+    /// calling `f` with the current path for each [`Event`] it causes.
+    /// `buf` starts at byte `buf_offset` of the input.
+    fn handle_token<F>(
+        &mut self,
+        buf: &[u8],
+        buf_offset: usize,
+        token: Token<'input>,
+        f: &mut F,
+    ) -> std::io::Result<()>
+    where
+        F: for<'a> FnMut(Path<'a>, Event<'a>) -> std::io::Result<()>,
+    {
+        if self.depth == 0 && starts_value(&token) {
+            f(&self.path, Event::DocumentStart)?;
+        }
+
+        let sample_arrays = self.sampling != ArraySampling::All;
+
+        if sample_arrays
+            && self.skipping_from_depth.is_none()
+            && starts_value(&token)
+            && self
+                .arrays
+                .last()
+                .is_some_and(|array| array.depth == self.depth)
+        {
+            self.sample_array_element(self.sampling);
+        }
 
         match token {
-            Token::ValueString { value, .. } => {
-                writer.write_path_and_value(&state.path, JsonAtom::String(value))?;
+            Token::ValueString { value, offset } => {
+                emit(
+                    &mut self.arrays,
+                    self.skipping_from_depth,
+                    f,
+                    &self.path,
+                    Event::Value(
+                        JsonAtom::String(value),
+                        Span {
+                            start: offset.0,
+                            // the quotes aren't part of the escaped string
+                            end: offset.0 + value.as_escaped_str().len() + 2,
+                        },
+                    ),
+                )?;
             }
-            Token::ValueNumber { value, .. } => {
-                writer.write_path_and_value(&state.path, JsonAtom::Number(value))?;
+            Token::ValueNumber { value, offset } => {
+                emit(
+                    &mut self.arrays,
+                    self.skipping_from_depth,
+                    f,
+                    &self.path,
+                    Event::Value(
+                        JsonAtom::Number(value),
+                        Span {
+                            start: offset.0,
+                            end: offset.0 + number_len(&buf[offset.0 - buf_offset..]),
+                        },
+                    ),
+                )?;
             }
-            Token::ValueBool { value, .. } => {
-                writer.write_path_and_value(&state.path, JsonAtom::Bool(value))?;
+            Token::ValueBool { value, offset } => {
+                emit(
+                    &mut self.arrays,
+                    self.skipping_from_depth,
+                    f,
+                    &self.path,
+                    Event::Value(
+                        JsonAtom::Bool(value),
+                        Span {
+                            start: offset.0,
+                            end: offset.0 + if value { 4 } else { 5 },
+                        },
+                    ),
+                )?;
             }
-            Token::ValueNull { .. } => {
-                writer.write_path_and_value(&state.path, JsonAtom::Null)?;
+            Token::ValueNull { offset } => {
+                emit(
+                    &mut self.arrays,
+                    self.skipping_from_depth,
+                    f,
+                    &self.path,
+                    Event::Value(
+                        JsonAtom::Null,
+                        Span {
+                            start: offset.0,
+                            end: offset.0 + 4,
+                        },
+                    ),
+                )?;
             }
-            Token::ObjectKey { key, .. } => {
-                state.add_new_object_key_to_path(key);
+            Token::ObjectKey { key, offset } => {
+                self.add_new_object_key_to_path(key);
+
+                // checked as they are read, rather than as they are written,
+                // so that array elements that aren't written are checked too
+                if self.duplicate_keys != DuplicateKeys::Allow
+                    && let Some(first) = self.track_object_key(key, offset.0)
+                    && check_duplicate_key(self.duplicate_keys, &self.path, first, offset.0)?
+                {
+                    emit(
+                        &mut self.arrays,
+                        self.skipping_from_depth,
+                        f,
+                        &self.path,
+                        Event::DuplicateKey {
+                            first,
+                            second: offset.0,
+                        },
+                    )?;
+                }
             }
-            Token::StartObject { .. } => state.increment_depth(),
-            Token::StartArray { .. } => {
-                state.increment_depth();
-                state.add_new_array_index_to_path()
+            Token::StartObject { offset } => {
+                self.increment_depth();
+                self.last_open_offset = offset.0;
+
+                if self.duplicate_keys != DuplicateKeys::Allow {
+                    self.start_tracking_object_keys();
+                }
             }
-            // for Token::EndObject and Token::EndArray:
+            Token::StartArray { offset } => {
+                self.increment_depth();
+                self.last_open_offset = offset.0;
+                self.add_new_array_index_to_path();
+
+                if sample_arrays {
+                    self.start_sampling_array();
+                }
+            }
+            // for Token::EndObject:
             //
-            // if depth > state.path.len() here,
-            // at the end of an object/array,
-            // it means we inside an empty object/array,
+            // if depth > self.path.len() here,
+            // at the end of an object,
+            // it means we inside an empty object,
             // and should not pop the most recent path,
             // as the most recent path was from the level above,
             // not this level
-            Token::EndObject { .. } => {
-                writer.write_path_and_value(&state.path, JsonAtom::EmptyObject)?;
-                if state.depth <= state.path.len() {
-                    state.pop_path()
+            Token::EndObject { offset } => {
+                if self.depth <= self.path.len() {
+                    self.pop_path()
+                } else {
+                    emit(
+                        &mut self.arrays,
+                        self.skipping_from_depth,
+                        f,
+                        &self.path,
+                        Event::Value(
+                            JsonAtom::EmptyObject,
+                            Span {
+                                start: self.last_open_offset,
+                                end: offset.0 + 1,
+                            },
+                        ),
+                    )?;
+                }
+                self.decrement_depth();
+
+                if self.duplicate_keys != DuplicateKeys::Allow {
+                    self.stop_tracking_object_keys();
                 }
-                state.decrement_depth();
             }
-            Token::EndArray { .. } => {
-                writer.write_path_and_value(&state.path, JsonAtom::EmptyArray)?;
+            // arrays always push an index, so an array is empty
+            // if that index was never incremented past 0
+            Token::EndArray { offset } => {
+                // the sampled elements of this array are written to wherever
+                // the array itself would have been written
+                if sample_arrays {
+                    let array = self
+                        .arrays
+                        .pop()
+                        .expect("arrays must be started before they end, this is a bug");
+
+                    let mut reservoir = array.reservoir;
+                    reservoir.sort_unstable_by_key(|(index, _)| *index);
 
-                if state.depth <= state.path.len() {
-                    state.pop_path()
+                    let mut borrowed_path = vec![];
+
+                    for (path, event) in reservoir.iter().flat_map(|(_, events)| events) {
+                        borrowed_path.clear();
+                        borrowed_path.extend(path.iter().map(OwnedPathComponent::borrow));
+                        let event = event.borrow();
+                        emit(
+                            &mut self.arrays,
+                            self.skipping_from_depth,
+                            f,
+                            &borrowed_path,
+                            event,
+                        )?;
+                    }
+                }
+
+                if let Some(PathComponent::Index(0)) = self.path.last() {
+                    emit(
+                        &mut self.arrays,
+                        self.skipping_from_depth,
+                        f,
+                        &self.path[..self.path.len() - 1],
+                        Event::Value(
+                            JsonAtom::EmptyArray,
+                            Span {
+                                start: self.last_open_offset,
+                                end: offset.0 + 1,
+                            },
+                        ),
+                    )?;
                 }
-                state.decrement_depth();
+
+                self.pop_path();
+                self.decrement_depth();
             }
         }
 
         if is_terminal(&token) {
-            state.maybe_increment_most_recent_array_index();
+            // the end of an element that wasn't written
+            if self.skipping_from_depth == Some(self.depth) {
+                self.skipping_from_depth = None;
+            }
+
+            self.maybe_increment_most_recent_array_index();
+        }
+
+        Ok(())
+    }
+}
+
+/// passes `path` and `event` to `f`, unless they are in an array element that is skipped,
+/// or in one that is in a reservoir sample, in which case they are kept until the array ends
+fn emit<F>(
+    arrays: &mut [SampledArray],
+    skipping_from_depth: Option<usize>,
+    f: &mut F,
+    path: Path,
+    event: Event,
+) -> std::io::Result<()>
+where
+    F: for<'a> FnMut(Path<'a>, Event<'a>) -> std::io::Result<()>,
+{
+    if skipping_from_depth.is_some() {
+        return Ok(());
+    }
+
+    if let Some(array) = arrays.last_mut()
+        && let Some(current) = array.current
+    {
+        let path = path.iter().map(OwnedPathComponent::from).collect();
+        array.reservoir[current].1.push((path, event.into()));
+        return Ok(());
+    }
+
+    f(path, event)
+}
+
+/// a small, fast pseudorandom number generator (SplitMix64),
+/// so that array samples are the same every time for the same seed
+#[derive(Debug, Default)]
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// uniform in `[0, 1)`
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// uniform in `[0, n)`
+    fn below(&mut self, n: u64) -> u64 {
+        ((self.next_u64() as u128 * n as u128) >> 64) as u64
+    }
+}
+
+/// options for [`stream_with_options`]
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    syntax: Syntax,
+    duplicate_keys: DuplicateKeys,
+    array_sampling: ArraySampling,
+}
+
+impl Options {
+    /// which dialect of JSON the input is written in. defaults to [`Syntax::Json`].
+    pub fn syntax(mut self, syntax: Syntax) -> Self {
+        self.syntax = syntax;
+        self
+    }
+
+    /// what to do when a key appears more than once in the same object.
+    /// defaults to [`DuplicateKeys::Allow`].
+    pub fn duplicate_keys(mut self, duplicate_keys: DuplicateKeys) -> Self {
+        self.duplicate_keys = duplicate_keys;
+        self
+    }
+
+    /// which elements of each array are written, at every level of nesting.
+    /// defaults to [`ArraySampling::All`].
+    pub fn array_sampling(mut self, array_sampling: ArraySampling) -> Self {
+        self.array_sampling = array_sampling;
+        self
+    }
+}
+
+/// which elements of each array are written, for a first look at a large document.
+///
+/// elements that aren't written are still read, and checked for duplicate keys with
+/// [`DuplicateKeys::Error`] and [`DuplicateKeys::Warn`], but nothing inside of them is written,
+/// and paths keep the elements' original indexes.
+/// an array with no elements written is not written as an empty array.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ArraySampling {
+    /// every element
+    #[default]
+    All,
+    /// the first `n` elements
+    Limit(usize),
+    /// each element with probability `rate`, chosen pseudorandomly.
+    /// the same `seed` always chooses the same elements of the same document.
+    Rate { rate: f64, seed: u64 },
+    /// a uniformly random sample of `size` elements, chosen pseudorandomly.
+    /// the same `seed` always chooses the same elements of the same document.
+    ///
+    /// as any element might replace one chosen earlier, the chosen elements are
+    /// kept in memory, and only written, in index order, once the array ends.
+    Reservoir { size: usize, seed: u64 },
+}
+
+/// the dialects of JSON that can be read.
+/// all of them produce the same paths and values for the same data.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Syntax {
+    /// plain JSON
+    #[default]
+    Json,
+    /// JSON with `//` and `/* */` comments and trailing commas,
+    /// as in `tsconfig.json` and VS Code settings
+    Jsonc,
+    /// [JSON5](https://spec.json5.org/): JSONC, plus unquoted keys,
+    /// single-quoted strings, hexadecimal numbers, `Infinity`, `NaN`,
+    /// and numbers with a leading `+` or a leading or trailing `.`.
+    ///
+    /// strings that are not also valid JSON strings are rewritten before tokenizing,
+    /// so byte offsets after such a string no longer line up with the input.
+    Json5,
+}
+
+/// what to do when a key appears more than once in the same object, i.e.,
+/// `{"a": 1, "a": 2}`.
+///
+/// JSON parsers disagree about which of the values such a document means,
+/// so duplicate keys can be used to make different programs see different data.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// don't check. every value is written, under the same path.
+    #[default]
+    Allow,
+    /// stop with an [`std::io::ErrorKind::InvalidData`] error at the second key
+    Error,
+    /// print a warning to stderr, and continue as with [`DuplicateKeys::Report`]
+    Warn,
+    /// call [`PathValueWriter::write_duplicate_key`], and continue
+    Report,
+}
+
+/// what [`for_each_event`] reports, along with the current path
+enum Event<'input> {
+    /// a value, and where it is in the input
+    Value(JsonAtom<'input>, Span),
+    /// the last key in the path already appeared in the same object.
+    /// `first` and `second` are the byte offsets of the two keys.
+    DuplicateKey { first: usize, second: usize },
+    /// a top-level value starts, with an empty path
+    DocumentStart,
+}
+
+pub fn stream<W: PathValueWriter + ?Sized>(buf: &[u8], writer: &mut W) -> std::io::Result<()> {
+    stream_with_options(buf, writer, &Options::default())
+}
+
+pub fn stream_with_options<W: PathValueWriter + ?Sized>(
+    buf: &[u8],
+    writer: &mut W,
+    options: &Options,
+) -> std::io::Result<()> {
+    for_each_event(buf, options, |path, event| write_event(writer, path, event))
+}
+
+/// passes `event` on to `writer`
+fn write_event<W: PathValueWriter + ?Sized>(
+    writer: &mut W,
+    path: Path,
+    event: Event,
+) -> std::io::Result<()> {
+    match event {
+        Event::Value(value, span) => writer.write_path_and_value_with_span(path, value, span),
+        Event::DuplicateKey { first, second } => writer.write_duplicate_key(path, first, second),
+        Event::DocumentStart => writer.start_document(),
+    }
+}
+
+/// like [`stream_with_options`], but reading from `reader` and writing to an async `writer`.
+/// the same options give the same paths and values as [`stream_with_options`].
+///
+/// the input is read a chunk at a time and fed to a [`streamer::Streamer`],
+/// and the values each chunk completes are written, with each write awaited,
+/// before the next chunk is read. so values are written as soon as they arrive,
+/// a slow writer holds up reading rather than values piling up in memory,
+/// and control is given back to the runtime after each chunk,
+/// so that large inputs don't hold up other tasks.
+///
+/// as with [`stream_with_options`] and [`streamer::Streamer::finish`], input that ends
+/// in the middle of a value is an error, once everything before it has been written.
+#[cfg(feature = "async")]
+pub async fn stream_async<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    options: &Options,
+) -> std::io::Result<()>
+where
+    R: tokio::io::AsyncRead + Unpin,
+    W: path_value_writer::AsyncPathValueWriter + ?Sized,
+{
+    use tokio::io::AsyncReadExt;
+
+    /// how much of the input to read at a time
+    const CHUNK_SIZE: usize = 64 * 1024;
+
+    let mut streamer = streamer::Streamer::new(options.clone());
+    let mut chunk = vec![0; CHUNK_SIZE];
+
+    // the events of the current chunk and their paths,
+    // as they can't be written from inside of the streamer, which isn't async
+    let mut events: BufferedEvents = vec![];
+
+    loop {
+        let read = reader.read(&mut chunk).await?;
+
+        let mut buffer = |path: Path, event: Event| {
+            events.push((
+                path.iter().map(OwnedPathComponent::from).collect(),
+                event.into(),
+            ));
+            Ok(())
+        };
+
+        // what was read before a syntax error is written before the error is returned
+        if read == 0 {
+            let result = streamer.finish_events(&mut buffer);
+            write_buffered_events(&mut events, writer).await?;
+            return result;
+        }
+
+        let result = streamer.feed_events(&chunk[..read], &mut buffer);
+        write_buffered_events(&mut events, writer).await?;
+        result?;
+
+        YieldNow(false).await;
+    }
+}
+
+/// writes `events` to `writer`, in order, leaving `events` empty
+#[cfg(feature = "async")]
+async fn write_buffered_events<W>(
+    events: &mut BufferedEvents,
+    writer: &mut W,
+) -> std::io::Result<()>
+where
+    W: path_value_writer::AsyncPathValueWriter + ?Sized,
+{
+    for (path, event) in events.drain(..) {
+        let path: Vec<_> = path.iter().map(OwnedPathComponent::borrow).collect();
+
+        match event.borrow() {
+            Event::Value(value, span) => {
+                writer
+                    .write_path_and_value_with_span(&path, value, span)
+                    .await?
+            }
+            Event::DuplicateKey { first, second } => {
+                writer.write_duplicate_key(&path, first, second).await?
+            }
+            Event::DocumentStart => writer.start_document().await?,
+        }
+    }
+
+    Ok(())
+}
+
+/// warns about or errors on a duplicate key, as `duplicate_keys` says,
+/// returning whether it should be passed on to the writer
+fn check_duplicate_key(
+    duplicate_keys: DuplicateKeys,
+    path: Path,
+    first: usize,
+    second: usize,
+) -> std::io::Result<bool> {
+    match duplicate_keys {
+        DuplicateKeys::Allow => Ok(false),
+        DuplicateKeys::Error => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "duplicate key at {} (bytes {first} and {second})",
+                path_value_writer::json_pointer::to_string(path)
+            ),
+        )),
+        DuplicateKeys::Warn => {
+            eprintln!(
+                "warning: duplicate key at {} (bytes {first} and {second})",
+                path_value_writer::json_pointer::to_string(path)
+            );
+            Ok(true)
         }
+        DuplicateKeys::Report => Ok(true),
+    }
+}
+
+/// drives the path-tracking state machine over `buf`,
+/// calling `f` with the current path for each [`Event`]
+fn for_each_event<F>(buf: &[u8], options: &Options, f: F) -> std::io::Result<()>
+where
+    F: for<'input> FnMut(Path<'input>, Event<'input>) -> std::io::Result<()>,
+{
+    match options.syntax {
+        Syntax::Json => for_each_token_event(buf, tokenizer::Tokens::new(buf), options, f),
+        Syntax::Jsonc => for_each_token_event(buf, lenient::Tokens::jsonc(buf), options, f),
+        Syntax::Json5 => {
+            let normalized = lenient::normalize_json5_strings(buf);
+            for_each_token_event(&normalized, lenient::Tokens::json5(&normalized), options, f)
+        }
+    }
+}
+
+/// `tokens` must be the tokens of `buf`
+fn for_each_token_event<'input, I, F>(
+    buf: &[u8],
+    tokens: I,
+    options: &Options,
+    mut f: F,
+) -> std::io::Result<()>
+where
+    I: Iterator<Item = Result<Token<'input>, SyntaxError>>,
+    F: for<'a> FnMut(Path<'a>, Event<'a>) -> std::io::Result<()>,
+{
+    let mut state: State = State::new(options);
+
+    for token in tokens {
+        let token = token.map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        state.handle_token(buf, 0, token, &mut |path, event| f(path, event))?;
     }
 
     Ok(())
 }
 
+/// gives control back to the async runtime once, whichever runtime it is
+#[cfg(feature = "async")]
+struct YieldNow(bool);
+
+#[cfg(feature = "async")]
+impl std::future::Future for YieldNow {
+    type Output = ();
+
+    fn poll(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<()> {
+        if self.0 {
+            std::task::Poll::Ready(())
+        } else {
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            std::task::Poll::Pending
+        }
+    }
+}
+
+/// whether `token` is the first token of a value
+pub(crate) fn starts_value(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::ValueString { .. }
+            | Token::ValueNumber { .. }
+            | Token::ValueBool { .. }
+            | Token::ValueNull { .. }
+            | Token::StartObject { .. }
+            | Token::StartArray { .. }
+    )
+}
+
+/// the length of the number at the start of `buf`.
+/// JSON5 numbers can be hexadecimal, `Infinity` or `NaN`,
+/// so this looks for the end of the number rather than at what it is made of.
+pub(crate) fn number_len(buf: &[u8]) -> usize {
+    buf.iter()
+        .position(|b| matches!(b, b' ' | b'\t' | b'\n' | b'\r' | b',' | b']' | b'}' | b'/'))
+        .unwrap_or(buf.len())
+}
+
 fn is_terminal(token: &Token) -> bool {
     matches!(
         token,
@@ -204,3 +991,479 @@ fn is_terminal(token: &Token) -> bool {
             | Token::EndArray { .. }
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{ArraySampling, DuplicateKeys, Options, Syntax, stream_with_options};
+    use crate::path_value_writer::PathValueWriter;
+    use crate::path_value_writer::json_pointer::{
+        Options as JSONPointerWriterOptions, Writer as JSONPointerWriter, to_string,
+    };
+    use crate::span::Span;
+    use crate::{JsonAtom, Path};
+
+    #[derive(Default)]
+    struct DuplicateKeyCollector {
+        duplicates: Vec<(String, usize, usize)>,
+    }
+
+    impl PathValueWriter for DuplicateKeyCollector {
+        fn write_path_and_value(&mut self, _path: Path, _value: JsonAtom) -> std::io::Result<()> {
+            Ok(())
+        }
+
+        fn write_duplicate_key(
+            &mut self,
+            path: Path,
+            first_offset: usize,
+            second_offset: usize,
+        ) -> std::io::Result<()> {
+            self.duplicates
+                .push((to_string(path), first_offset, second_offset));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn duplicate_keys_are_reported_per_object() {
+        let s =
+            br#"{"a": 1, "b": {"a": 2, "c": 3, "\u0063": 4}, "a": 5, "d": [{"e": 1}, {"e": 2}]}"#;
+
+        let mut writer = DuplicateKeyCollector::default();
+
+        stream_with_options(
+            s,
+            &mut writer,
+            &Options::default().duplicate_keys(DuplicateKeys::Report),
+        )
+        .unwrap();
+
+        assert_eq!(
+            writer.duplicates,
+            vec![
+                ("/b/\\u0063".to_string(), 23, 31),
+                ("/a".to_string(), 1, 45)
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicate_keys_error() {
+        let s = br#"{"a": 1, "a": 2}"#;
+
+        let mut writer = DuplicateKeyCollector::default();
+
+        let e = stream_with_options(
+            s,
+            &mut writer,
+            &Options::default().duplicate_keys(DuplicateKeys::Error),
+        )
+        .unwrap_err();
+
+        assert_eq!(e.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(e.to_string(), "duplicate key at /a (bytes 1 and 9)");
+    }
+
+    #[test]
+    fn duplicate_keys_in_elements_that_are_not_written() {
+        let s = br#"[{"a": 1}, {"b": 1, "b": 2}]"#;
+
+        for array_sampling in [
+            ArraySampling::Limit(1),
+            ArraySampling::Rate { rate: 0.0, seed: 0 },
+            ArraySampling::Reservoir { size: 1, seed: 0 },
+        ] {
+            let options = Options::default().array_sampling(array_sampling);
+
+            let e = stream_with_options(
+                s,
+                &mut DuplicateKeyCollector::default(),
+                &options.clone().duplicate_keys(DuplicateKeys::Error),
+            )
+            .unwrap_err();
+            assert_eq!(
+                e.to_string(),
+                "duplicate key at /1/b (bytes 12 and 20)",
+                "{array_sampling:?}"
+            );
+
+            // but they are only reported with the elements they are in
+            let mut writer = DuplicateKeyCollector::default();
+            stream_with_options(
+                s,
+                &mut writer,
+                &options.duplicate_keys(DuplicateKeys::Report),
+            )
+            .unwrap();
+            assert!(writer.duplicates.is_empty(), "{array_sampling:?}");
+        }
+    }
+
+    #[test]
+    fn duplicate_keys_allowed_by_default() {
+        let s = br#"{"a": 1, "a": 2}"#;
+
+        let mut writer = DuplicateKeyCollector::default();
+
+        stream_with_options(s, &mut writer, &Options::default()).unwrap();
+
+        assert!(writer.duplicates.is_empty());
+    }
+
+    /// each value's path and the text of its span
+    #[derive(Default)]
+    struct SpanCollector<'input> {
+        buf: &'input [u8],
+        spans: Vec<(String, &'input str)>,
+    }
+
+    impl PathValueWriter for SpanCollector<'_> {
+        fn write_path_and_value(&mut self, _path: Path, _value: JsonAtom) -> std::io::Result<()> {
+            unreachable!("spans are always known when streaming JSON")
+        }
+
+        fn write_path_and_value_with_span(
+            &mut self,
+            path: Path,
+            _value: JsonAtom,
+            span: Span,
+        ) -> std::io::Result<()> {
+            self.spans.push((
+                to_string(path),
+                std::str::from_utf8(&self.buf[span.start..span.end]).unwrap(),
+            ));
+            Ok(())
+        }
+    }
+
+    fn spans<'input>(s: &'input [u8], options: &Options) -> Vec<(String, &'input str)> {
+        let mut writer = SpanCollector {
+            buf: s,
+            ..Default::default()
+        };
+
+        stream_with_options(s, &mut writer, options).unwrap();
+
+        writer.spans
+    }
+
+    #[test]
+    fn spans_of_every_type() {
+        let s =
+            br#"{"s": "a \"b\"", "n": -1.5e3, "t": true, "f": false, "z": null, "o": { }, "a": [
+        ], "x": [0]}"#;
+
+        assert_eq!(
+            spans(s, &Options::default()),
+            vec![
+                ("/s".to_string(), r#""a \"b\"""#),
+                ("/n".to_string(), "-1.5e3"),
+                ("/t".to_string(), "true"),
+                ("/f".to_string(), "false"),
+                ("/z".to_string(), "null"),
+                ("/o".to_string(), "{ }"),
+                ("/a".to_string(), "[\n        ]"),
+                ("/x/0".to_string(), "0"),
+            ]
+        );
+    }
+
+    #[test]
+    fn spans_with_comments() {
+        let s = b"[1 /* one */, 0x1F// hex\n, Infinity, {/* } */}]";
+
+        assert_eq!(
+            spans(s, &Options::default().syntax(Syntax::Json5)),
+            vec![
+                ("/0".to_string(), "1"),
+                ("/1".to_string(), "0x1F"),
+                ("/2".to_string(), "Infinity"),
+                ("/3".to_string(), "{/* } */}"),
+            ]
+        );
+    }
+
+    fn sampled(s: &[u8], array_sampling: ArraySampling) -> String {
+        let mut buf = vec![];
+        let mut writer = JSONPointerWriter::new(
+            &mut buf,
+            JSONPointerWriterOptions::default().write_empty_collections(true),
+        );
+
+        stream_with_options(
+            s,
+            &mut writer,
+            &Options::default().array_sampling(array_sampling),
+        )
+        .unwrap();
+
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn array_limit() {
+        let s = br#"{"a": [1, 2, [3, 4, 5], {"b": [6, 7, 8]}], "c": [[1, 2, 3], [], {}], "d": []}"#;
+
+        assert_eq!(
+            sampled(s, ArraySampling::Limit(2)),
+            "/a/0\t1\n/a/1\t2\n/c/0/0\t1\n/c/0/1\t2\n/c/1\t[]\n/d\t[]\n"
+        );
+
+        assert_eq!(sampled(s, ArraySampling::Limit(0)), "/d\t[]\n");
+
+        assert_eq!(
+            sampled(s, ArraySampling::Limit(usize::MAX)),
+            sampled(s, ArraySampling::All)
+        );
+    }
+
+    #[test]
+    fn array_sample_rate() {
+        let s = format!(
+            "[{}]",
+            (0..100)
+                .map(|i| format!(r#"{{"i": {i}}}"#))
+                .collect::<Vec<_>>()
+                .join(",")
+        );
+        let s = s.as_bytes();
+
+        let half = sampled(s, ArraySampling::Rate { rate: 0.5, seed: 1 });
+        let lines = half.lines().count();
+
+        assert!((30..70).contains(&lines), "{lines}");
+        // elements keep their indexes
+        assert!(half.lines().all(|line| {
+            let (path, value) = line.split_once('\t').unwrap();
+            path == format!("/{value}/i")
+        }));
+
+        // the same seed samples the same elements, and a different one doesn't
+        assert_eq!(half, sampled(s, ArraySampling::Rate { rate: 0.5, seed: 1 }));
+        assert_ne!(half, sampled(s, ArraySampling::Rate { rate: 0.5, seed: 2 }));
+
+        assert_eq!(sampled(s, ArraySampling::Rate { rate: 0.0, seed: 1 }), "");
+        assert_eq!(
+            sampled(s, ArraySampling::Rate { rate: 1.0, seed: 1 }),
+            sampled(s, ArraySampling::All)
+        );
+    }
+
+    #[test]
+    fn array_reservoir() {
+        let inner = format!(
+            "[{}]",
+            (0..10).map(|i| i.to_string()).collect::<Vec<_>>().join(",")
+        );
+        let s = format!("{{\"a\": [{}], \"b\": []}}", vec![inner; 10].join(","));
+        let s = s.as_bytes();
+
+        let sample = sampled(s, ArraySampling::Reservoir { size: 3, seed: 7 });
+
+        // 3 of the outer arrays, and 3 elements of each, and the empty array
+        assert_eq!(sample.lines().count(), 10, "{sample}");
+        assert!(sample.ends_with("/b\t[]\n"));
+
+        // written in index order
+        let indexes: Vec<(usize, usize)> = sample
+            .lines()
+            .filter_map(|line| {
+                let (path, _) = line.split_once('\t').unwrap();
+                let mut components = path.strip_prefix("/a/")?.split('/');
+                Some((
+                    components.next()?.parse().unwrap(),
+                    components.next()?.parse().unwrap(),
+                ))
+            })
+            .collect();
+
+        let mut sorted = indexes.clone();
+        sorted.sort();
+        assert_eq!(indexes, sorted);
+
+        assert_eq!(
+            sample,
+            sampled(s, ArraySampling::Reservoir { size: 3, seed: 7 })
+        );
+
+        assert_eq!(
+            sampled(s, ArraySampling::Reservoir { size: 10, seed: 7 }),
+            sampled(s, ArraySampling::All)
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn stream_async_and_streamer_match_stream() {
+        use crate::path_value_writer::json_pointer::AsyncWriter as AsyncJSONPointerWriter;
+        use crate::stream_async;
+        use crate::streamer::Streamer;
+        use tokio::io::AsyncWriteExt;
+
+        let one = std::fs::read("fixtures/one.json").unwrap();
+        let json5 = br#"// comment
+            {a: 'b', "c": [1, 2, 3,], d: {}, e: [],}"#;
+        let duplicates = br#"{"a": 1, "b": {"c": 2, "c": 3}}"#;
+
+        for (s, options) in [
+            (one.as_slice(), Options::default()),
+            (
+                one.as_slice(),
+                Options::default().array_sampling(ArraySampling::Limit(1)),
+            ),
+            (json5.as_slice(), Options::default().syntax(Syntax::Json5)),
+            (
+                duplicates.as_slice(),
+                Options::default().duplicate_keys(DuplicateKeys::Error),
+            ),
+            // input that ends inside of a container, or between a key and its value
+            (br#"{"a": [1"#.as_slice(), Options::default()),
+            (br#"{"a": 1, "b": [2, "#.as_slice(), Options::default()),
+            (br#"[{"a""#.as_slice(), Options::default()),
+            (br#"{"a": "#.as_slice(), Options::default()),
+        ] {
+            let writer_options = JSONPointerWriterOptions::default()
+                .write_empty_collections(true)
+                .write_offsets(true);
+
+            let mut challenge = vec![];
+            let challenge_result = stream_with_options(
+                s,
+                &mut JSONPointerWriter::new(&mut challenge, writer_options),
+                &options,
+            );
+
+            let mut fed = vec![];
+            let fed_result = {
+                let mut writer = JSONPointerWriter::new(&mut fed, writer_options);
+                let mut streamer = Streamer::new(options.clone());
+
+                s.chunks(5)
+                    .try_for_each(|chunk| streamer.feed(chunk, &mut writer))
+                    .and_then(|()| streamer.finish(&mut writer))
+            };
+
+            assert_eq!(
+                fed_result.map_err(|e| e.to_string()),
+                challenge_result
+                    .as_ref()
+                    .map(|_| ())
+                    .map_err(|e| e.to_string())
+            );
+            assert_eq!(
+                String::from_utf8_lossy(&fed),
+                String::from_utf8_lossy(&challenge)
+            );
+
+            // a small buffer, so the input arrives in many chunks
+            let (mut client, mut server) = tokio::io::duplex(64);
+
+            let send = async move {
+                client.write_all(s).await.unwrap();
+                // closes the stream
+                drop(client);
+            };
+
+            let receive = async {
+                let mut output = vec![];
+                let result = stream_async(
+                    &mut server,
+                    &mut AsyncJSONPointerWriter::new(&mut output, writer_options),
+                    &options,
+                )
+                .await;
+                (result, output)
+            };
+
+            let ((), (result, output)) = tokio::join!(send, receive);
+
+            assert_eq!(
+                result.map_err(|e| e.to_string()),
+                challenge_result.map_err(|e| e.to_string())
+            );
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                String::from_utf8(challenge).unwrap()
+            );
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn stream_async_writes_values_as_they_arrive() {
+        use crate::path_value_writer::json_pointer::AsyncWriter as AsyncJSONPointerWriter;
+        use crate::stream_async;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (mut client, mut server) = tokio::io::duplex(64);
+        let (mut output, mut output_reader) = tokio::io::duplex(1024);
+
+        let receive = async {
+            let result = stream_async(
+                &mut server,
+                &mut AsyncJSONPointerWriter::new(&mut output, JSONPointerWriterOptions::default()),
+                &Options::default(),
+            )
+            .await;
+            drop(output);
+            result
+        };
+
+        let send = async {
+            client.write_all(b"[1, 2, ").await.unwrap();
+
+            // the first values are written while the input is still open
+            let mut first = [0; 10];
+            let read_first = output_reader.read_exact(&mut first);
+            let give_up = async {
+                for _ in 0..1000 {
+                    tokio::task::yield_now().await;
+                }
+            };
+            tokio::select! {
+                read = read_first => { read.unwrap(); }
+                () = give_up => panic!("nothing was written before the input was closed"),
+            }
+            assert_eq!(&first, b"/0\t1\n/1\t2\n");
+
+            client.write_all(b"3]").await.unwrap();
+            drop(client);
+
+            let mut rest = vec![];
+            output_reader.read_to_end(&mut rest).await.unwrap();
+            assert_eq!(rest, b"/2\t3\n");
+        };
+
+        let (result, ()) = tokio::join!(receive, send);
+        result.unwrap();
+
+        // input that ends in the middle of a value is an error, after what came before it
+        let mut output = vec![];
+        let result = stream_async(
+            &mut br#"{"a": 1, "b": [2"#.as_slice(),
+            &mut AsyncJSONPointerWriter::new(&mut output, JSONPointerWriterOptions::default()),
+            &Options::default(),
+        )
+        .await;
+        assert!(result.is_err());
+        assert_eq!(output, b"/a\t1\n/b/0\t2\n");
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn stream_async_is_send() {
+        use crate::path_value_writer::json_pointer::AsyncWriter as AsyncJSONPointerWriter;
+
+        fn assert_send<T: Send>(_: T) {}
+
+        let mut reader: &[u8] = b"{}";
+        let mut output = vec![];
+        let mut writer = AsyncJSONPointerWriter::new(&mut output, Default::default());
+
+        assert_send(crate::stream_async(
+            &mut reader,
+            &mut writer,
+            &Options::default(),
+        ));
+    }
+}
@@ -1,22 +1,405 @@
-use clap::Parser;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use jstream::ArraySampling;
+use jstream::grep::{Matcher, Target, Writer as GrepWriter};
+use jstream::path_pattern::PathPattern;
+use jstream::path_value_writer::PathValueWriter;
+use jstream::path_value_writer::binary::{Options as BinaryWriterOptions, Writer as BinaryWriter};
+use jstream::path_value_writer::env::{Options as EnvWriterOptions, Writer as EnvWriter};
+use jstream::path_value_writer::json::{Options as JSONWriterOptions, Writer as JSONWriter};
 use jstream::path_value_writer::json_pointer::{
     Options as JSONPointerWriterOptions, Writer as JSONPointerWriter,
 };
+use jstream::path_value_writer::paths::{Options as PathsWriterOptions, Writer as PathsWriter};
+use jstream::path_value_writer::properties::{
+    IndexStyle as PropertiesIndexStyle, Options as PropertiesWriterOptions,
+    Writer as PropertiesWriter,
+};
+use jstream::path_value_writer::sorted::{Options as SortedWriterOptions, Writer as SortedWriter};
+#[cfg(feature = "sqlite")]
+use jstream::path_value_writer::sqlite::{Options as SqliteWriterOptions, Writer as SqliteWriter};
+use jstream::predicate::{Predicate, Writer as PredicateWriter};
+use jstream::redact::{Redactor, Replacement, Writer as RedactWriter};
 use std::error::Error;
-use std::io::{BufWriter, Read};
+use std::io::{BufWriter, Read, Write};
 use std::mem::ManuallyDrop;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Enumerate the paths through a JSON document.
 #[derive(Parser, Debug)]
-#[clap(author, version, about, name = "jstream")]
+#[clap(
+    author,
+    version,
+    about,
+    name = "jstream",
+    args_conflicts_with_subcommands = true
+)]
 struct Options {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// A JSON file path
     #[arg()]
     json_location: Option<PathBuf>,
+
+    /// The input format
+    #[arg(long, value_enum, default_value_t = InputFormat::Json)]
+    from: InputFormat,
+
+    /// Accept JSON with comments and trailing commas
+    #[arg(long, conflicts_with = "json5")]
+    jsonc: bool,
+
+    /// Accept JSON5: JSON with comments, trailing commas,
+    /// unquoted keys, single-quoted strings, hex numbers, Infinity, and NaN
+    #[arg(long)]
+    json5: bool,
+
+    /// The output format
+    #[arg(short, long, value_enum, default_value_t = Output::Lines)]
+    output: Output,
+
+    /// Only write the first N elements of every array
+    #[arg(long, value_name = "N", conflicts_with_all = ["sample_rate", "reservoir"])]
+    array_limit: Option<usize>,
+
+    /// Only write a pseudorandom sample of the elements of every array,
+    /// each element being written with probability P
+    #[arg(long, value_name = "P", value_parser = parse_probability, conflicts_with = "reservoir")]
+    sample_rate: Option<f64>,
+
+    /// Only write a pseudorandom sample of N elements of every array.
+    /// The sampled elements are kept in memory until the end of their array
+    #[arg(long, value_name = "N")]
+    reservoir: Option<usize>,
+
+    /// The seed for `--sample-rate` and `--reservoir`. The same seed always samples
+    /// the same elements of the same document
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// Write lines sorted by path, with array indexes in numeric order,
+    /// so that output is the same from one version to the next. Lines are
+    /// written once all of the input has been read
+    #[arg(long, conflicts_with_all = ["output", "paths_only"])]
+    sort: bool,
+
+    /// With `--sort`, roughly how much memory to sort in before sorting
+    /// batches of lines in temporary files and merging them
+    #[arg(
+        long,
+        value_name = "MIB",
+        default_value_t = 256,
+        value_parser = parse_sort_memory,
+        requires = "sort"
+    )]
+    sort_memory: usize,
+
+    /// Only write each distinct path once, without values, with array indexes
+    /// replaced by `--index-placeholder`
+    #[arg(long, conflicts_with = "output")]
+    paths_only: bool,
+
+    /// With `--paths-only`, written in place of array indexes
+    #[arg(long, value_name = "PLACEHOLDER", default_value = "*")]
+    index_placeholder: String,
+
+    /// With `--paths-only`, write the number of times each path occurs after it.
+    /// Paths are written once all of the input has been read
+    #[arg(long, requires = "paths_only")]
+    path_counts: bool,
+
+    /// With `-o env`, written at the start of every variable name
+    #[arg(long, value_name = "PREFIX", default_value = "")]
+    env_prefix: String,
+
+    /// With `-o env`, written between the components of each variable name
+    #[arg(long, value_name = "JOINER", default_value = "__")]
+    env_joiner: String,
+
+    /// With `-o binary`, write each distinct key and string value in full only once
+    #[arg(long)]
+    intern_strings: bool,
+
+    /// With `-o properties`, how array indexes are written
+    #[arg(long, value_enum, default_value_t = IndexStyle::Brackets)]
+    index_style: IndexStyle,
+
+    /// Insert the paths and values into the `path_values` table of this SQLite database,
+    /// creating it if needed, rather than writing them to stdout
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["output", "paths_only", "sort"])]
+    sqlite: Option<PathBuf>,
+
+    /// With `--sqlite`, the `doc_id` of the inserted rows.
+    /// Defaults to the JSON file path, or `-` for stdin
+    #[cfg(feature = "sqlite")]
+    #[arg(long, requires = "sqlite")]
+    doc_id: Option<String>,
+
+    /// Write string values with their escapes decoded, i.e. `café` rather than `caf\u00e9`.
+    /// Control characters are still escaped
+    #[arg(long)]
+    raw_strings: bool,
+
+    /// Write string values without surrounding quotes
+    #[arg(long)]
+    unquoted_strings: bool,
+
+    /// Write the byte offset in the input at which each value starts after it, in another column.
+    /// Only for JSON input
+    #[arg(long)]
+    offsets: bool,
+
+    /// Check that the input is valid JSON, writing each problem found
+    /// as its byte offset, path, and description, rather than writing paths and values.
+    /// Exits with status 1 if the input is not valid
+    #[arg(long)]
+    validate: bool,
+
+    /// The standard that `--validate` checks against
+    #[arg(long, value_enum, default_value_t = ValidationProfile::Rfc8259)]
+    validation_profile: ValidationProfile,
+
+    /// What to do when a key appears more than once in the same object.
+    /// `error` and `warn` also check array elements left out by sampling
+    #[arg(long, value_enum, default_value_t = DuplicateKeys::Allow)]
+    duplicate_keys: DuplicateKeys,
+
+    /// Only write values matching this predicate, i.e. `is string and =~ /@example\.com$/`
+    /// or `> 1000 or is null`. Values can be tested with `is string|number|integer|bool|null|empty`,
+    /// compared with `== != < <= > >=`, and matched with `=~ /REGEX/`, and tests combined with
+    /// `and`, `or`, `not` and parentheses
+    #[arg(long = "where", value_name = "PREDICATE")]
+    predicate: Option<Predicate>,
+
+    /// Redact string and number values at paths matching this pattern,
+    /// i.e. `/users/*/email` or `**/ssn`. May be given more than once
+    #[arg(long = "redact", value_name = "PATTERN")]
+    redact_patterns: Vec<PathPattern>,
+
+    /// What to replace redacted values with
+    #[arg(long, value_enum, default_value_t = RedactWith::Mask)]
+    redact_with: RedactWith,
+
+    /// The secret key for `--redact-with hash`
+    #[arg(long, env = "JSTREAM_REDACT_KEY", hide_env_values = true)]
+    redact_key: Option<String>,
+
+    /// Write the input document back out as JSON with redacted values replaced,
+    /// rather than writing paths and values
+    #[arg(
+        long,
+        requires = "redact_patterns",
+        conflicts_with_all = [
+            "jsonc",
+            "json5",
+            "output",
+            "sort",
+            "paths_only",
+            "predicate",
+            "duplicate_keys",
+            "array_limit",
+            "sample_rate",
+            "reservoir",
+        ]
+    )]
+    redact_json: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Write the paths and values whose keys or values match a regular expression
+    ///
+    /// Keys and string values are decoded before matching.
+    /// Exits with status 1 if nothing matched, like grep
+    Grep(GrepOptions),
+    /// Write the value at a JSON Pointer, exactly as it appears in the input
+    ///
+    /// Stops reading at the end of the value.
+    /// Exits with status 1 if there is no value at the pointer
+    Get(GetOptions),
+    /// Build or use an index of where values are in a JSON file, for fast repeated lookups
+    #[command(subcommand)]
+    Index(IndexCommand),
+}
+
+#[derive(Subcommand, Debug)]
+enum IndexCommand {
+    /// Index where the values down to `--depth` are in a JSON file
+    Build(IndexBuildOptions),
+    /// Write the value at a JSON Pointer, reading only the part of the file the index points to
+    ///
+    /// Exits with status 1 if there is no value at the pointer,
+    /// and 2 if the file has changed since it was indexed
+    Get(IndexGetOptions),
+}
+
+#[derive(Args, Debug)]
+struct IndexBuildOptions {
+    /// A JSON file path
+    file: PathBuf,
+
+    /// How many levels of the document to index. Deeper values are found by reading
+    /// their ancestor at this depth
+    #[arg(long, default_value_t = 2)]
+    depth: usize,
+
+    /// Where to write the index. Defaults to the file path with `.jsidx` appended
+    #[arg(long, value_name = "PATH")]
+    index: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct IndexGetOptions {
+    /// A JSON file path
+    file: PathBuf,
+
+    /// A JSON Pointer, i.e., `/features/0/properties`
+    pointer: String,
+
+    /// Where to read the index from. Defaults to the file path with `.jsidx` appended
+    #[arg(long, value_name = "PATH")]
+    index: Option<PathBuf>,
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
+#[derive(Args, Debug)]
+struct GetOptions {
+    /// A JSON Pointer, i.e., `/features/0/properties`. The empty pointer is the whole document
+    pointer: String,
+
+    /// A JSON file path. Reads stdin if there is none
+    file: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct GrepOptions {
+    /// A regular expression, matched against decoded keys and string values
+    pattern: String,
+
+    /// JSON file paths. Reads stdin if there are none
+    files: Vec<PathBuf>,
+
+    /// The input format
+    #[arg(long, value_enum, default_value_t = InputFormat::Json)]
+    from: InputFormat,
+
+    /// Only match against keys. Any key in a path can match
+    #[arg(long, conflicts_with = "values")]
+    keys: bool,
+
+    /// Only match against values. Values other than strings are matched as they appear in JSON
+    #[arg(long)]
+    values: bool,
+
+    /// Ignore case
+    #[arg(short, long)]
+    ignore_case: bool,
+
+    /// Write the paths and values that don't match
+    #[arg(short = 'v', long)]
+    invert_match: bool,
+
+    /// Only write the number of matching values
+    #[arg(short, long)]
+    count: bool,
+
+    /// Only write the names of files with matching values
+    #[arg(short = 'l', long)]
+    files_with_matches: bool,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum InputFormat {
+    /// JSON, or JSONC/JSON5 with `--jsonc`/`--json5`
+    Json,
+    /// TOML
+    Toml,
+    /// CBOR
+    Cbor,
+    /// MessagePack
+    Msgpack,
+    /// YAML, including multi-document streams
+    Yaml,
+    /// Paths and values written by `-o binary`
+    Binary,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Output {
+    /// One line per value: its JSON Pointer path and the value
+    Lines,
+    /// A JSON document rebuilt from the paths and values
+    Json,
+    /// Like `json`, but indented
+    JsonPretty,
+    /// One shell-quoted environment variable assignment per value,
+    /// i.e. `DATABASE__REPLICAS__0__HOST=db1`
+    Env,
+    /// One Java `.properties` entry per value, i.e. `a.b[0].c=value`
+    Properties,
+    /// jstream's compact binary format, which `--from binary` reads back
+    Binary,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum IndexStyle {
+    /// `a.b[0].c`
+    Brackets,
+    /// `a.b.0.c`
+    Dotted,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum ValidationProfile {
+    /// RFC 8259 JSON
+    Rfc8259,
+    /// I-JSON (RFC 7493)
+    IJson,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum DuplicateKeys {
+    /// Write every value
+    Allow,
+    /// Stop with an error
+    Error,
+    /// Print a warning to stderr and continue
+    Warn,
+    /// Write a line with the path and the byte offsets of both keys, and continue.
+    /// Only for `-o lines`
+    Report,
+}
+
+impl From<DuplicateKeys> for jstream::DuplicateKeys {
+    fn from(duplicate_keys: DuplicateKeys) -> Self {
+        match duplicate_keys {
+            DuplicateKeys::Allow => jstream::DuplicateKeys::Allow,
+            DuplicateKeys::Error => jstream::DuplicateKeys::Error,
+            DuplicateKeys::Warn => jstream::DuplicateKeys::Warn,
+            DuplicateKeys::Report => jstream::DuplicateKeys::Report,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum RedactWith {
+    /// "***"
+    Mask,
+    /// A keyed hash of the value
+    Hash,
+    /// A value of the same type and similar shape
+    Fake,
+}
+
+fn main() {
+    // errors are written with Display rather than the Debug of returning them from main
+    if let Err(e) = run() {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
     // https://github.com/rust-lang/rust/issues/46016
     #[cfg(target_family = "unix")]
     {
@@ -26,24 +409,479 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let options = Options::parse();
 
-    let buf = if let Some(json_location) = &options.json_location {
-        std::fs::read(json_location)?
+    if let Some(Command::Grep(grep_options)) = &options.command {
+        // like grep: 0 if anything matched, 1 if nothing did, and 2 for errors
+        match grep(grep_options) {
+            Ok(true) => return Ok(()),
+            Ok(false) => std::process::exit(1),
+            Err(e) => {
+                eprintln!("Error: {e}");
+                std::process::exit(2);
+            }
+        }
+    }
+
+    if let Some(Command::Get(get_options)) = &options.command {
+        let buf = read_input(get_options.file.as_deref())?;
+
+        let Some(value) = jstream::get::get(&buf, &get_options.pointer) else {
+            std::process::exit(1);
+        };
+
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(value)?;
+        stdout.write_all(b"\n")?;
+
+        return Ok(());
+    }
+
+    if let Some(Command::Index(index_command)) = &options.command {
+        match index_command {
+            IndexCommand::Build(build_options) => {
+                let index_path = build_options
+                    .index
+                    .clone()
+                    .unwrap_or_else(|| jstream::index::default_index_path(&build_options.file));
+
+                jstream::index::build_file(
+                    &build_options.file,
+                    &index_path,
+                    &jstream::index::Options::default().depth(build_options.depth),
+                )?;
+            }
+            IndexCommand::Get(get_options) => {
+                let index_path = get_options
+                    .index
+                    .clone()
+                    .unwrap_or_else(|| jstream::index::default_index_path(&get_options.file));
+
+                // 1 if there is no value at the pointer, as with `get`, and 2 for errors,
+                // such as the index being out of date
+                let value = match jstream::index::get_file(
+                    &get_options.file,
+                    &index_path,
+                    &get_options.pointer,
+                ) {
+                    Ok(Some(value)) => value,
+                    Ok(None) => std::process::exit(1),
+                    Err(e) => {
+                        eprintln!("Error: {e}");
+                        std::process::exit(2);
+                    }
+                };
+
+                let mut stdout = std::io::stdout().lock();
+                stdout.write_all(&value)?;
+                stdout.write_all(b"\n")?;
+            }
+        }
+
+        return Ok(());
+    }
+
+    if !matches!(options.from, InputFormat::Json) {
+        if options.validate || options.redact_json {
+            return Err("--validate and --redact-json only support JSON input".into());
+        }
+
+        if options.jsonc || options.json5 {
+            return Err("--jsonc and --json5 only support JSON input".into());
+        }
+
+        if options.array_limit.is_some()
+            || options.sample_rate.is_some()
+            || options.reservoir.is_some()
+        {
+            return Err(
+                "--array-limit, --sample-rate and --reservoir only support JSON input".into(),
+            );
+        }
+    }
+
+    let buf = ManuallyDrop::new(read_input(options.json_location.as_deref())?);
+
+    let mut stdout = BufWriter::new(std::io::stdout().lock());
+
+    if options.validate {
+        let profile = match options.validation_profile {
+            ValidationProfile::Rfc8259 => jstream::validate::Profile::Rfc8259,
+            ValidationProfile::IJson => jstream::validate::Profile::IJson,
+        };
+
+        let violations = jstream::validate::validate(&buf, profile);
+
+        for violation in &violations {
+            writeln!(stdout, "{violation}")?;
+        }
+
+        stdout.flush()?;
+
+        if !violations.is_empty() {
+            std::process::exit(1);
+        }
+
+        return Ok(());
+    }
+
+    let redactor = if options.redact_patterns.is_empty() {
+        None
+    } else {
+        let replacement = match options.redact_with {
+            RedactWith::Mask => Replacement::Mask,
+            RedactWith::Hash => {
+                let Some(key) = &options.redact_key else {
+                    return Err(
+                        "--redact-with hash requires --redact-key or JSTREAM_REDACT_KEY".into(),
+                    );
+                };
+                Replacement::Hash {
+                    key: key.as_bytes().to_vec(),
+                }
+            }
+            RedactWith::Fake => Replacement::Fake,
+        };
+
+        Some(Redactor::new(options.redact_patterns, replacement))
+    };
+
+    if options.redact_json {
+        if let Some(redactor) = &redactor {
+            jstream::redact::rewrite(&buf, redactor, &mut stdout)?;
+        }
+        return Ok(());
+    }
+
+    let syntax = if options.jsonc {
+        jstream::Syntax::Jsonc
+    } else if options.json5 {
+        jstream::Syntax::Json5
+    } else {
+        jstream::Syntax::Json
+    };
+
+    // the JSON writer needs to be told about duplicate keys to keep their values apart
+    let duplicate_keys = match (options.duplicate_keys, options.output) {
+        (DuplicateKeys::Allow, Output::Json | Output::JsonPretty) => jstream::DuplicateKeys::Report,
+        (duplicate_keys, _) => duplicate_keys.into(),
+    };
+
+    let stream_options = jstream::Options::default()
+        .syntax(syntax)
+        .duplicate_keys(duplicate_keys)
+        .array_sampling(
+            match (options.array_limit, options.sample_rate, options.reservoir) {
+                (Some(limit), _, _) => ArraySampling::Limit(limit),
+                (_, Some(rate), _) => ArraySampling::Rate {
+                    rate,
+                    seed: options.seed,
+                },
+                (_, _, Some(size)) => ArraySampling::Reservoir {
+                    size,
+                    seed: options.seed,
+                },
+                _ => ArraySampling::All,
+            },
+        );
+
+    let adapters = Adapters {
+        from: options.from,
+        stream_options: &stream_options,
+        predicate: options.predicate.as_ref(),
+        redactor: redactor.as_ref(),
+    };
+
+    #[cfg(feature = "sqlite")]
+    if let Some(sqlite) = &options.sqlite {
+        let doc_id = match (&options.doc_id, &options.json_location) {
+            (Some(doc_id), _) => doc_id.clone(),
+            (None, Some(json_location)) => json_location.display().to_string(),
+            (None, None) => "-".to_string(),
+        };
+
+        let conn = rusqlite::Connection::open(sqlite)?;
+        let mut sqlite_writer = SqliteWriter::new(&conn, doc_id, SqliteWriterOptions::default())?;
+        stream_through_adapters(&buf, &adapters, &mut sqlite_writer)?;
+        sqlite_writer.finish()?;
+
+        return Ok(());
+    }
+
+    if options.paths_only {
+        let mut paths_writer = PathsWriter::new(
+            &mut stdout,
+            PathsWriterOptions::default()
+                .index_placeholder(&options.index_placeholder)
+                .count(options.path_counts),
+        );
+        stream_through_adapters(&buf, &adapters, &mut paths_writer)?;
+        paths_writer.finish()?;
+
+        return Ok(());
+    }
+
+    match options.output {
+        Output::Lines => {
+            let json_pointer_options = JSONPointerWriterOptions::default()
+                .decode_strings(options.raw_strings)
+                .quote_strings(!options.unquoted_strings)
+                .write_offsets(options.offsets)
+                .write_duplicate_keys(matches!(options.duplicate_keys, DuplicateKeys::Report));
+
+            if options.sort {
+                let mut sorted_writer = SortedWriter::new(
+                    &mut stdout,
+                    SortedWriterOptions::default()
+                        .json_pointer(json_pointer_options)
+                        .memory_limit(options.sort_memory.saturating_mul(1024 * 1024)),
+                );
+                stream_through_adapters(&buf, &adapters, &mut sorted_writer)?;
+                sorted_writer.finish()?;
+            } else {
+                let mut json_pointer_writer =
+                    JSONPointerWriter::new(&mut stdout, json_pointer_options);
+                stream_through_adapters(&buf, &adapters, &mut json_pointer_writer)?;
+            }
+        }
+        Output::Json | Output::JsonPretty => {
+            let mut json_writer = JSONWriter::new(
+                &mut stdout,
+                JSONWriterOptions::default().pretty(matches!(options.output, Output::JsonPretty)),
+            );
+            stream_through_adapters(&buf, &adapters, &mut json_writer)?;
+            json_writer.finish()?;
+        }
+        Output::Env => {
+            let mut env_writer = EnvWriter::new(
+                &mut stdout,
+                EnvWriterOptions::default()
+                    .prefix(&options.env_prefix)
+                    .joiner(&options.env_joiner),
+            );
+            stream_through_adapters(&buf, &adapters, &mut env_writer)?;
+        }
+        Output::Properties => {
+            let index_style = match options.index_style {
+                IndexStyle::Brackets => PropertiesIndexStyle::Brackets,
+                IndexStyle::Dotted => PropertiesIndexStyle::Dotted,
+            };
+            let mut properties_writer = PropertiesWriter::new(
+                &mut stdout,
+                PropertiesWriterOptions::default().index_style(index_style),
+            );
+            stream_through_adapters(&buf, &adapters, &mut properties_writer)?;
+        }
+        Output::Binary => {
+            let mut binary_writer = BinaryWriter::new(
+                &mut stdout,
+                BinaryWriterOptions::default().intern_strings(options.intern_strings),
+            )?;
+            stream_through_adapters(&buf, &adapters, &mut binary_writer)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// how paths and values are read and changed before they reach the output writer
+struct Adapters<'a> {
+    from: InputFormat,
+    stream_options: &'a jstream::Options,
+    predicate: Option<&'a Predicate>,
+    redactor: Option<&'a Redactor>,
+}
+
+/// wraps `writer` in whichever writers are needed to
+/// implement the given command line options, and streams `buf` through them
+fn stream_through_adapters(
+    buf: &[u8],
+    adapters: &Adapters,
+    writer: &mut dyn PathValueWriter,
+) -> std::io::Result<()> {
+    let mut redact_writer;
+    let writer: &mut dyn PathValueWriter = if let Some(redactor) = adapters.redactor {
+        redact_writer = RedactWriter::new(writer, redactor);
+        &mut redact_writer
+    } else {
+        writer
+    };
+
+    // values are filtered before they are redacted, so predicates see the original values
+    let mut predicate_writer;
+    let writer: &mut dyn PathValueWriter = if let Some(predicate) = adapters.predicate {
+        predicate_writer = PredicateWriter::new(writer, predicate);
+        &mut predicate_writer
+    } else {
+        writer
+    };
+
+    match adapters.from {
+        InputFormat::Json => jstream::stream_with_options(buf, writer, adapters.stream_options),
+        InputFormat::Toml => jstream::input::toml::stream(buf, writer),
+        InputFormat::Yaml => jstream::input::yaml::stream(buf, writer),
+        InputFormat::Cbor => jstream::input::cbor::stream(buf, writer),
+        InputFormat::Msgpack => jstream::input::msgpack::stream(buf, writer),
+        InputFormat::Binary => jstream::path_value_writer::binary::stream(buf, writer),
+    }
+}
+
+fn parse_probability(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(p) if (0.0..=1.0).contains(&p) => Ok(p),
+        _ => Err("must be a number from 0 to 1".to_string()),
+    }
+}
+
+fn parse_sort_memory(s: &str) -> Result<usize, String> {
+    match s.parse::<usize>() {
+        Ok(mib) if mib > 0 => Ok(mib),
+        _ => Err("must be a whole number of MiB, at least 1".to_string()),
+    }
+}
+
+/// reads all of the file at `location`, or stdin if there is none
+fn read_input(location: Option<&Path>) -> std::io::Result<Vec<u8>> {
+    if let Some(location) = location {
+        std::fs::read(location)
     } else {
         let mut buf = vec![];
         let stdin = std::io::stdin();
         let mut stdin = stdin.lock();
         stdin.read_to_end(&mut buf)?;
-        buf
+        Ok(buf)
+    }
+}
+
+/// returns whether anything matched
+fn grep(options: &GrepOptions) -> Result<bool, Box<dyn Error>> {
+    let regex = regex::RegexBuilder::new(&options.pattern)
+        .case_insensitive(options.ignore_case)
+        .build()?;
+
+    let target = match (options.keys, options.values) {
+        (true, false) => Target::Keys,
+        (false, true) => Target::Values,
+        _ => Target::Both,
     };
 
-    let buf = ManuallyDrop::new(buf);
+    let matcher = Matcher::new(regex, target).invert(options.invert_match);
+
+    let stream_options = jstream::Options::default();
+
+    let adapters = Adapters {
+        from: options.from,
+        stream_options: &stream_options,
+        predicate: None,
+        redactor: None,
+    };
+
+    let inputs: Vec<Option<&Path>> = if options.files.is_empty() {
+        vec![None]
+    } else {
+        options
+            .files
+            .iter()
+            .map(|file| Some(file.as_path()))
+            .collect()
+    };
+
+    // like grep, output is prefixed with the file name when there is more than one file
+    let with_names = inputs.len() > 1;
 
     let mut stdout = BufWriter::new(std::io::stdout().lock());
+    let mut any_matched = false;
 
-    let mut json_pointer_writer =
-        JSONPointerWriter::new(&mut stdout, JSONPointerWriterOptions::default());
+    for input in inputs {
+        let name = input.map_or("(standard input)".into(), |input| {
+            input.display().to_string()
+        });
 
-    jstream::stream(&buf, &mut json_pointer_writer)?;
+        let buf = read_input(input)?;
 
-    Ok(())
+        let mut discard = Discard;
+        let mut prefixed;
+        let mut writer: &mut dyn Write = if with_names {
+            prefixed = PrefixLines::new(&mut stdout, format!("{name}:"));
+            &mut prefixed
+        } else {
+            &mut stdout
+        };
+        let mut json_pointer_writer =
+            JSONPointerWriter::new(&mut writer, JSONPointerWriterOptions::default());
+
+        let writer: &mut dyn PathValueWriter = if options.count || options.files_with_matches {
+            &mut discard
+        } else {
+            &mut json_pointer_writer
+        };
+
+        let mut grep_writer = GrepWriter::new(writer, &matcher);
+        stream_through_adapters(&buf, &adapters, &mut grep_writer)?;
+        let matched = grep_writer.matched();
+
+        if options.files_with_matches {
+            if matched > 0 {
+                writeln!(stdout, "{name}")?;
+            }
+        } else if options.count {
+            if with_names {
+                write!(stdout, "{name}:")?;
+            }
+            writeln!(stdout, "{matched}")?;
+        }
+
+        any_matched |= matched > 0;
+    }
+
+    stdout.flush()?;
+
+    Ok(any_matched)
+}
+
+/// a writer that writes nothing
+struct Discard;
+
+impl PathValueWriter for Discard {
+    fn write_path_and_value(
+        &mut self,
+        _path: jstream::Path,
+        _value: jstream::JsonAtom,
+    ) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// writes `prefix` at the start of every line written to `writer`
+struct PrefixLines<'writer, W: Write> {
+    writer: &'writer mut W,
+    prefix: String,
+    at_line_start: bool,
+}
+
+impl<'writer, W: Write> PrefixLines<'writer, W> {
+    fn new(writer: &'writer mut W, prefix: String) -> Self {
+        Self {
+            writer,
+            prefix,
+            at_line_start: true,
+        }
+    }
+}
+
+impl<W: Write> Write for PrefixLines<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        for line in buf.split_inclusive(|b| *b == b'\n') {
+            if self.at_line_start {
+                self.writer.write_all(self.prefix.as_bytes())?;
+            }
+
+            self.writer.write_all(line)?;
+            self.at_line_start = line.ends_with(b"\n");
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
 }
@@ -0,0 +1,216 @@
+use crate::{Path, PathComponent};
+use std::borrow::Cow;
+use std::convert::Infallible;
+use std::str::FromStr;
+
+/// a JSON Pointer-like pattern that matches paths, i.e.,
+/// `/users/*/email` or `**/ssn`.
+///
+/// - `*` matches exactly one path component, key or index
+/// - `**` matches zero or more path components
+/// - anything else matches a key with that (unescaped) name,
+///   or an index with that decimal value
+///
+/// as in JSON Pointer, `~1` stands for `/` and `~0` for `~`.
+/// the leading `/` is optional.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PathPattern {
+    components: Vec<PatternComponent>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum PatternComponent {
+    Literal {
+        key: String,
+        /// the index the literal also matches, if it is one written the way indexes are
+        index: Option<usize>,
+    },
+    AnyOne,
+    AnyDepth,
+}
+
+impl PathPattern {
+    pub fn new(pattern: &str) -> Self {
+        let pattern = pattern.strip_prefix('/').unwrap_or(pattern);
+
+        let components = if pattern.is_empty() {
+            vec![]
+        } else {
+            pattern
+                .split('/')
+                .map(|component| match component {
+                    "*" => PatternComponent::AnyOne,
+                    "**" => PatternComponent::AnyDepth,
+                    literal => {
+                        let index = literal
+                            .parse::<usize>()
+                            .ok()
+                            .filter(|i| itoa::Buffer::new().format(*i) == literal);
+
+                        PatternComponent::Literal {
+                            key: literal.replace("~1", "/").replace("~0", "~"),
+                            index,
+                        }
+                    }
+                })
+                .collect()
+        };
+
+        Self { components }
+    }
+
+    pub fn matches(&self, path: Path) -> bool {
+        self.matches_unescaped(&unescape(path))
+    }
+
+    /// whether any of `patterns` matches `path`, unescaping its keys only once
+    pub fn any_matches(patterns: &[PathPattern], path: Path) -> bool {
+        if patterns.is_empty() {
+            return false;
+        }
+
+        let path = unescape(path);
+        patterns
+            .iter()
+            .any(|pattern| pattern.matches_unescaped(&path))
+    }
+
+    /// glob matching, where `**` is the star. when a component doesn't match,
+    /// the last `**` takes one more component and matching carries on from there,
+    /// so that no component is compared more than once for each `**`.
+    fn matches_unescaped(&self, path: &[Component]) -> bool {
+        let pattern = &self.components;
+
+        let mut p = 0;
+        let mut c = 0;
+        // the last `**` seen, and where in the path what follows it is being tried
+        let mut backtrack: Option<(usize, usize)> = None;
+
+        while c < path.len() {
+            match pattern.get(p) {
+                Some(PatternComponent::AnyDepth) => {
+                    p += 1;
+                    backtrack = Some((p, c));
+                    continue;
+                }
+                Some(PatternComponent::AnyOne) => {
+                    p += 1;
+                    c += 1;
+                    continue;
+                }
+                Some(PatternComponent::Literal { key, index }) if path[c].matches(key, *index) => {
+                    p += 1;
+                    c += 1;
+                    continue;
+                }
+                _ => {}
+            }
+
+            match &mut backtrack {
+                Some((after_star, start)) => {
+                    *start += 1;
+                    p = *after_star;
+                    c = *start;
+                }
+                None => return false,
+            }
+        }
+
+        pattern[p..]
+            .iter()
+            .all(|component| *component == PatternComponent::AnyDepth)
+    }
+}
+
+impl FromStr for PathPattern {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::new(s))
+    }
+}
+
+/// a path component as patterns compare it, with its key unescaped
+enum Component<'path> {
+    Key(Cow<'path, str>),
+    Index(usize),
+}
+
+impl Component<'_> {
+    fn matches(&self, key: &str, index: Option<usize>) -> bool {
+        match self {
+            Component::Key(k) => k == key,
+            Component::Index(i) => index == Some(*i),
+        }
+    }
+}
+
+fn unescape<'path>(path: Path<'path>) -> Vec<Component<'path>> {
+    path.iter()
+        .map(|component| match component {
+            PathComponent::Key(k) => Component::Key(
+                k.to_unescaped()
+                    .unwrap_or(Cow::Borrowed(k.as_escaped_str())),
+            ),
+            PathComponent::Index(i) => Component::Index(*i),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PathPattern;
+    use crate::EscapedStr;
+    use crate::PathComponent;
+
+    fn key(k: &str) -> PathComponent<'_> {
+        PathComponent::Key(EscapedStr::new(k))
+    }
+
+    #[test]
+    fn literals_and_wildcards() {
+        let path = [key("users"), PathComponent::Index(3), key("email")];
+
+        assert!(PathPattern::new("/users/*/email").matches(&path));
+        assert!(PathPattern::new("/users/3/email").matches(&path));
+        assert!(PathPattern::new("**/email").matches(&path));
+        assert!(PathPattern::new("/**").matches(&path));
+        assert!(PathPattern::new("/users/**/email").matches(&path));
+
+        assert!(!PathPattern::new("/users/*").matches(&path));
+        assert!(!PathPattern::new("/users/4/email").matches(&path));
+        assert!(!PathPattern::new("**/ssn").matches(&path));
+    }
+
+    #[test]
+    fn several_any_depths() {
+        let path: Vec<_> = ["a", "b", "a", "b", "c", "a", "c"]
+            .into_iter()
+            .map(key)
+            .collect();
+
+        assert!(PathPattern::new("**/a/**/c").matches(&path));
+        assert!(PathPattern::new("/a/**/b/**/a/*").matches(&path));
+        assert!(PathPattern::new("**/**/c/**").matches(&path));
+        assert!(!PathPattern::new("**/a/**/b/**/d").matches(&path));
+        assert!(!PathPattern::new("/b/**").matches(&path));
+
+        assert!(PathPattern::new("/**").matches(&[]));
+        assert!(!PathPattern::new("/*").matches(&[]));
+
+        // indexes only match the way they are written
+        assert!(PathPattern::new("/a/**/7").matches(&[key("a"), PathComponent::Index(7)]));
+        assert!(!PathPattern::new("/a/07").matches(&[key("a"), PathComponent::Index(7)]));
+
+        // would take on the order of 40^4 comparisons trying every suffix for every `**`
+        let long: Vec<_> = (0..40).map(|_| key("x")).collect();
+        assert!(!PathPattern::new("**/x/**/x/**/x/**/x/**/y").matches(&long));
+    }
+
+    #[test]
+    fn keys_are_compared_unescaped() {
+        let path = [key("a\\u002fb"), key("c~d")];
+
+        assert!(PathPattern::new("/a~1b/c~0d").matches(&path));
+    }
+}
@@ -0,0 +1,383 @@
+//! a compact binary format for paths and values, for storing flattened documents
+//! and replaying them later with [`stream`] without parsing JSON or jstream's text output.
+//!
+//! the format is a header followed by one record per value:
+//!
+//! - header: the 8 bytes `jstrbin\x01`, where the last byte is the format version,
+//!   then a flags byte, where bit 0 means strings are interned
+//! - record: its length in bytes, then how many components of the previous record's path
+//!   it shares, how many components follow, the components, and the value
+//! - component: `0` and the index, or a string (below) for a key
+//! - value: `0` null, `1` false, `2` true, `3` and a non-negative integer,
+//!   `4` and a negative integer, zigzag encoded, `5` and a float as 8 little-endian bytes,
+//!   `6` empty object, `7` empty array, or a string
+//! - string: `8`, its length, and its bytes, as escaped in JSON. if strings are interned,
+//!   each string written this way is given the next number, starting from 0, and
+//!   `9` and that number stands for the string from then on
+//!
+//! lengths, counts, indexes and integers are unsigned LEB128.
+
+use super::PathValueWriter;
+use crate::EscapedStr;
+use crate::Number;
+use crate::input::binary::{Input, text, write_varint};
+use crate::{JsonAtom, Path, PathComponent};
+use std::collections::HashMap;
+use std::io::Write;
+
+const MAGIC: &[u8; 8] = b"jstrbin\x01";
+
+const FLAG_INTERNED: u8 = 1;
+
+const INDEX: u8 = 0;
+const NULL: u8 = 0;
+const FALSE: u8 = 1;
+const TRUE: u8 = 2;
+const POS_INT: u8 = 3;
+const NEG_INT: u8 = 4;
+const FLOAT: u8 = 5;
+const EMPTY_OBJECT: u8 = 6;
+const EMPTY_ARRAY: u8 = 7;
+const STRING: u8 = 8;
+const INTERNED_STRING: u8 = 9;
+
+pub struct Writer<'writer, W: Write> {
+    writer: &'writer mut W,
+    /// the path of the previous record, which the next one is written relative to
+    previous: Vec<OwnedPathComponent>,
+    /// with [`Options::intern_strings`], the number of each string written so far
+    interned: Option<HashMap<String, u64>>,
+    /// the record being written, as its length has to be written first
+    record: Vec<u8>,
+    /// the length of the record
+    record_len: Vec<u8>,
+}
+
+#[derive(Debug)]
+enum OwnedPathComponent {
+    Key(String),
+    Index(usize),
+}
+
+impl PartialEq<PathComponent<'_>> for OwnedPathComponent {
+    fn eq(&self, other: &PathComponent) -> bool {
+        match (self, other) {
+            (Self::Key(a), PathComponent::Key(b)) => a == b.as_escaped_str(),
+            (Self::Index(a), PathComponent::Index(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl<'writer, W: Write> Writer<'writer, W> {
+    /// writes the header
+    pub fn new(writer: &'writer mut W, options: Options) -> std::io::Result<Self> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[if options.intern_strings {
+            FLAG_INTERNED
+        } else {
+            0
+        }])?;
+
+        Ok(Self {
+            writer,
+            previous: vec![],
+            interned: options.intern_strings.then(HashMap::new),
+            record: vec![],
+            record_len: vec![],
+        })
+    }
+
+    fn write_string(&mut self, s: &str) {
+        if let Some(interned) = &mut self.interned {
+            if let Some(n) = interned.get(s) {
+                self.record.push(INTERNED_STRING);
+                write_varint(&mut self.record, *n);
+                return;
+            }
+
+            interned.insert(s.to_string(), interned.len() as u64);
+        }
+
+        self.record.push(STRING);
+        write_varint(&mut self.record, s.len() as u64);
+        self.record.extend_from_slice(s.as_bytes());
+    }
+}
+
+#[derive(Default)]
+pub struct Options {
+    intern_strings: bool,
+}
+
+impl Options {
+    /// whether to write each distinct key and string value in full only the first time,
+    /// and as a number after that. this makes documents with repetitive keys and values
+    /// much smaller, but every distinct string is kept in memory while writing.
+    /// defaults to `false`.
+    pub fn intern_strings(mut self, intern_strings: bool) -> Self {
+        self.intern_strings = intern_strings;
+        self
+    }
+}
+
+impl<W: Write> PathValueWriter for Writer<'_, W> {
+    fn write_path_and_value(&mut self, path: Path, value: JsonAtom) -> std::io::Result<()> {
+        self.record.clear();
+
+        let shared = self
+            .previous
+            .iter()
+            .zip(path)
+            .take_while(|(a, b)| **a == **b)
+            .count();
+
+        write_varint(&mut self.record, shared as u64);
+        write_varint(&mut self.record, (path.len() - shared) as u64);
+
+        self.previous.truncate(shared);
+
+        for component in &path[shared..] {
+            match component {
+                PathComponent::Key(k) => {
+                    self.write_string(k.as_escaped_str());
+                    self.previous
+                        .push(OwnedPathComponent::Key(k.as_escaped_str().to_string()));
+                }
+                PathComponent::Index(i) => {
+                    self.record.push(INDEX);
+                    write_varint(&mut self.record, *i as u64);
+                    self.previous.push(OwnedPathComponent::Index(*i));
+                }
+            }
+        }
+
+        match value {
+            JsonAtom::Null => self.record.push(NULL),
+            JsonAtom::Bool(false) => self.record.push(FALSE),
+            JsonAtom::Bool(true) => self.record.push(TRUE),
+            JsonAtom::Number(Number::PosInt(n)) => {
+                self.record.push(POS_INT);
+                write_varint(&mut self.record, n);
+            }
+            JsonAtom::Number(Number::NegInt(n)) => {
+                self.record.push(NEG_INT);
+                write_varint(&mut self.record, ((n << 1) ^ (n >> 63)) as u64);
+            }
+            JsonAtom::Number(Number::Float(f)) => {
+                self.record.push(FLOAT);
+                self.record.extend_from_slice(&f.to_le_bytes());
+            }
+            JsonAtom::EmptyObject => self.record.push(EMPTY_OBJECT),
+            JsonAtom::EmptyArray => self.record.push(EMPTY_ARRAY),
+            JsonAtom::String(s) => self.write_string(s.as_escaped_str()),
+        }
+
+        self.record_len.clear();
+        write_varint(&mut self.record_len, self.record.len() as u64);
+        self.writer.write_all(&self.record_len)?;
+        self.writer.write_all(&self.record)
+    }
+}
+
+/// reads records written by [`Writer`], writing their paths and values to `writer`
+pub fn stream<W: PathValueWriter + ?Sized>(buf: &[u8], writer: &mut W) -> std::io::Result<()> {
+    let mut input = Input::new(buf);
+
+    if input.take(MAGIC.len() as u64).ok() != Some(MAGIC.as_slice()) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "not jstream binary output, or output from a different version of jstream",
+        ));
+    }
+
+    let interned = input.u8()? & FLAG_INTERNED != 0;
+
+    let mut strings: Vec<&str> = vec![];
+    let mut path: Vec<PathComponent> = vec![];
+
+    while !input.is_empty() {
+        let len = input.varint()?;
+        let mut record = Input::new(input.take(len)?);
+
+        let shared = record.varint()?;
+        let new = record.varint()?;
+
+        if shared > path.len() as u64 {
+            return Err(record.error("more path components shared than the previous path has"));
+        }
+
+        path.truncate(shared as usize);
+
+        for _ in 0..new {
+            let component = match record.u8()? {
+                INDEX => PathComponent::Index(
+                    usize::try_from(record.varint()?)
+                        .map_err(|_| record.error("index too large"))?,
+                ),
+                tag => PathComponent::Key(EscapedStr::new(read_string(
+                    &mut record,
+                    tag,
+                    interned,
+                    &mut strings,
+                )?)),
+            };
+
+            path.push(component);
+        }
+
+        let value = match record.u8()? {
+            NULL => JsonAtom::Null,
+            FALSE => JsonAtom::Bool(false),
+            TRUE => JsonAtom::Bool(true),
+            POS_INT => JsonAtom::Number(Number::PosInt(record.varint()?)),
+            NEG_INT => {
+                let n = record.varint()?;
+                JsonAtom::Number(Number::NegInt((n >> 1) as i64 ^ -((n & 1) as i64)))
+            }
+            FLOAT => JsonAtom::Number(Number::Float(f64::from_le_bytes(
+                record.take(8)?.try_into().unwrap(),
+            ))),
+            EMPTY_OBJECT => JsonAtom::EmptyObject,
+            EMPTY_ARRAY => JsonAtom::EmptyArray,
+            tag => JsonAtom::String(EscapedStr::new(read_string(
+                &mut record,
+                tag,
+                interned,
+                &mut strings,
+            )?)),
+        };
+
+        writer.write_path_and_value(&path, value)?;
+    }
+
+    Ok(())
+}
+
+fn read_string<'input>(
+    record: &mut Input<'input>,
+    tag: u8,
+    interned: bool,
+    strings: &mut Vec<&'input str>,
+) -> std::io::Result<&'input str> {
+    match tag {
+        STRING => {
+            let len = record.varint()?;
+            let bytes = record.take(len)?;
+            let s = text(record, bytes)?;
+
+            if interned {
+                strings.push(s);
+            }
+
+            Ok(s)
+        }
+        INTERNED_STRING if interned => {
+            let n = record.varint()?;
+
+            usize::try_from(n)
+                .ok()
+                .and_then(|n| strings.get(n))
+                .copied()
+                .ok_or_else(|| record.error("unknown interned string"))
+        }
+        _ => Err(record.error("unknown type")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Options as BinaryWriterOptions, Writer as BinaryWriter, stream as read_binary};
+    use crate::path_value_writer::json_pointer::{
+        Options as JSONPointerWriterOptions, Writer as JSONPointerWriter,
+    };
+    use crate::stream;
+
+    fn lines(f: impl FnOnce(&mut JSONPointerWriter<Vec<u8>>)) -> String {
+        let mut buf = vec![];
+        let mut writer = JSONPointerWriter::new(
+            &mut buf,
+            JSONPointerWriterOptions::default().write_empty_collections(true),
+        );
+
+        f(&mut writer);
+
+        String::from_utf8(buf).unwrap()
+    }
+
+    fn binary(s: &[u8], options: BinaryWriterOptions) -> Vec<u8> {
+        let mut buf = vec![];
+        let mut writer = BinaryWriter::new(&mut buf, options).unwrap();
+
+        stream(s, &mut writer).unwrap();
+
+        buf
+    }
+
+    #[test]
+    fn round_trip() {
+        let s = std::fs::read("fixtures/one.json").unwrap();
+        let s = [
+            s.as_slice(),
+            br#"{"n": [-1, -9223372036854775808, 18446744073709551615, 1.5e300], "e": [{}, []]}"#,
+        ]
+        .concat();
+
+        let challenge = lines(|writer| stream(&s, writer).unwrap());
+
+        for intern_strings in [false, true] {
+            let binary = binary(
+                &s,
+                BinaryWriterOptions::default().intern_strings(intern_strings),
+            );
+
+            assert_eq!(
+                lines(|writer| read_binary(&binary, writer).unwrap()),
+                challenge
+            );
+        }
+    }
+
+    #[test]
+    fn interning_and_shared_paths() {
+        let s = format!(
+            "[{}]",
+            vec![r#"{"category": "misc", "properties": {"name": "x", "kind": "misc"}}"#; 100]
+                .join(",")
+        );
+        let s = s.as_bytes();
+
+        let plain = binary(s, BinaryWriterOptions::default());
+        let interned = binary(s, BinaryWriterOptions::default().intern_strings(true));
+
+        assert!(plain.len() < s.len(), "{} {}", plain.len(), s.len());
+        assert!(
+            interned.len() < plain.len() / 2,
+            "{} {}",
+            interned.len(),
+            plain.len()
+        );
+    }
+
+    #[test]
+    fn invalid_input() {
+        let mut buf = vec![];
+        assert!(
+            read_binary(
+                b"not binary",
+                &mut JSONPointerWriter::new(&mut buf, Default::default())
+            )
+            .is_err()
+        );
+
+        let mut binary = binary(br#"{"a": "b"}"#, BinaryWriterOptions::default());
+        binary.pop();
+        assert!(
+            read_binary(
+                &binary,
+                &mut JSONPointerWriter::new(&mut buf, Default::default())
+            )
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,247 @@
+//! writes paths and values as environment variable assignments, i.e.
+//! `DATABASE__REPLICAS__0__HOST=db1`, which can be sourced by a POSIX shell
+//! or read as a `.env` file.
+
+use super::{PathValueWriter, write_number};
+use crate::{JsonAtom, Path, PathComponent};
+use std::collections::HashMap;
+use std::io::Write;
+
+pub struct Writer<'writer, W: Write> {
+    writer: &'writer mut W,
+    options: Options<'writer>,
+    /// every variable name written so far, and the path it was written for
+    names: HashMap<String, String>,
+    /// reused for building each variable name
+    name: String,
+}
+
+impl<'writer, W: Write> Writer<'writer, W> {
+    pub fn new(writer: &'writer mut W, options: Options<'writer>) -> Self {
+        Self {
+            writer,
+            options,
+            names: HashMap::new(),
+            name: String::new(),
+        }
+    }
+}
+
+pub struct Options<'options> {
+    prefix: &'options str,
+    joiner: &'options str,
+}
+
+impl<'options> Options<'options> {
+    /// written as-is at the start of every variable name. defaults to nothing.
+    pub fn prefix(mut self, prefix: &'options str) -> Self {
+        self.prefix = prefix;
+        self
+    }
+
+    /// the string written between each path component. defaults to `__`.
+    pub fn joiner(mut self, joiner: &'options str) -> Self {
+        self.joiner = joiner;
+        self
+    }
+}
+
+impl Default for Options<'_> {
+    fn default() -> Self {
+        Self {
+            prefix: "",
+            joiner: "__",
+        }
+    }
+}
+
+impl<W: Write> PathValueWriter for Writer<'_, W> {
+    fn write_path_and_value(&mut self, path: Path, value: JsonAtom) -> std::io::Result<()> {
+        // there is no way to write an empty collection as a variable
+        if matches!(value, JsonAtom::EmptyObject | JsonAtom::EmptyArray) {
+            return Ok(());
+        }
+
+        self.build_name(path)?;
+
+        self.writer.write_all(self.name.as_bytes())?;
+        self.writer.write_all(b"=")?;
+
+        match value {
+            JsonAtom::String(s) => {
+                let s = s
+                    .to_unescaped()
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+                write_quoted(self.writer, &s)?;
+            }
+            JsonAtom::Number(n) => write_number(self.writer, n)?,
+            JsonAtom::Bool(true) => self.writer.write_all(b"true")?,
+            JsonAtom::Bool(false) => self.writer.write_all(b"false")?,
+            // null is written as an empty value
+            JsonAtom::Null => {}
+            JsonAtom::EmptyObject | JsonAtom::EmptyArray => unreachable!("handled above"),
+        }
+
+        self.writer.write_all(b"\n")
+    }
+}
+
+impl<W: Write> Writer<'_, W> {
+    /// builds the variable name for `path` into `self.name`,
+    /// returning an error if a different path has already been written with the same name
+    fn build_name(&mut self, path: Path) -> std::io::Result<()> {
+        self.name.clear();
+        self.name.push_str(self.options.prefix);
+
+        for (i, component) in path.iter().enumerate() {
+            if i > 0 {
+                self.name.push_str(self.options.joiner);
+            }
+
+            match component {
+                PathComponent::Key(k) => {
+                    let k = k
+                        .to_unescaped()
+                        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+                    // anything that can't appear in a variable name becomes `_`
+                    self.name.extend(k.chars().map(|c| {
+                        if c.is_ascii_alphanumeric() {
+                            c.to_ascii_uppercase()
+                        } else {
+                            '_'
+                        }
+                    }));
+                }
+                PathComponent::Index(index) => {
+                    let mut b = itoa::Buffer::new();
+                    self.name.push_str(b.format(*index));
+                }
+            }
+        }
+
+        // variable names can't be empty or start with a digit
+        if self.name.is_empty() || self.name.starts_with(|c: char| c.is_ascii_digit()) {
+            self.name.insert(0, '_');
+        }
+
+        let json_pointer = super::json_pointer::to_string(path);
+
+        match self.names.get(&self.name) {
+            Some(previous) if *previous != json_pointer => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "{previous} and {json_pointer} would both be written as {}",
+                    self.name
+                ),
+            )),
+            Some(_) => Ok(()),
+            None => {
+                self.names.insert(self.name.clone(), json_pointer);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// writes `s` as-is if the shell wouldn't interpret any of it,
+/// otherwise in single quotes, in which only `'` itself needs escaping
+fn write_quoted<W: Write>(writer: &mut W, s: &str) -> std::io::Result<()> {
+    let is_safe = |b: u8| b.is_ascii_alphanumeric() || b"%+,-./:@_".contains(&b);
+
+    if !s.is_empty() && s.bytes().all(is_safe) {
+        return writer.write_all(s.as_bytes());
+    }
+
+    writer.write_all(b"'")?;
+
+    let mut parts = s.split('\'');
+
+    if let Some(first) = parts.next() {
+        writer.write_all(first.as_bytes())?;
+    }
+
+    for part in parts {
+        writer.write_all(b"'\\''")?;
+        writer.write_all(part.as_bytes())?;
+    }
+
+    writer.write_all(b"'")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Options as EnvWriterOptions, Writer as EnvWriter};
+    use crate::stream;
+
+    #[test]
+    fn nested_values() {
+        let s = br#"{
+            "database": {
+                "replicas": [{"host": "db1"}, {"host": "db2", "port": 5433}],
+                "password": "it's a \"secret\" $HOME",
+                "read-only": true,
+                "empty": "",
+                "none": null,
+                "tags": []
+            }
+        }"#;
+
+        let mut buf = vec![];
+        let mut writer = EnvWriter::new(&mut buf, EnvWriterOptions::default());
+
+        stream(s, &mut writer).unwrap();
+
+        let challenge = r#"DATABASE__REPLICAS__0__HOST=db1
+DATABASE__REPLICAS__1__HOST=db2
+DATABASE__REPLICAS__1__PORT=5433
+DATABASE__PASSWORD='it'\''s a "secret" $HOME'
+DATABASE__READ_ONLY=true
+DATABASE__EMPTY=''
+DATABASE__NONE=
+"#;
+
+        assert_eq!(std::str::from_utf8(&buf).unwrap(), challenge);
+    }
+
+    #[test]
+    fn prefix_and_joiner() {
+        let s = br#"[{"a": {"b": 1}}]"#;
+
+        let mut buf = vec![];
+        let mut writer = EnvWriter::new(
+            &mut buf,
+            EnvWriterOptions::default().prefix("APP_").joiner("_"),
+        );
+
+        stream(s, &mut writer).unwrap();
+
+        assert_eq!(std::str::from_utf8(&buf).unwrap(), "APP_0_A_B=1\n");
+
+        let mut buf = vec![];
+        let mut writer = EnvWriter::new(&mut buf, EnvWriterOptions::default());
+
+        stream(s, &mut writer).unwrap();
+
+        assert_eq!(std::str::from_utf8(&buf).unwrap(), "_0__A__B=1\n");
+    }
+
+    #[test]
+    fn collisions() {
+        let mut buf = vec![];
+        let mut writer = EnvWriter::new(&mut buf, EnvWriterOptions::default());
+
+        let e = stream(br#"{"a-b": 1, "A_B": 2}"#, &mut writer).unwrap_err();
+
+        assert_eq!(e.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(e.to_string(), "/a-b and /A_B would both be written as A_B");
+
+        // the same path appearing twice is not a collision
+        let mut buf = vec![];
+        let mut writer = EnvWriter::new(&mut buf, EnvWriterOptions::default());
+
+        stream(br#"{"a": 1} {"a": 2}"#, &mut writer).unwrap();
+
+        assert_eq!(std::str::from_utf8(&buf).unwrap(), "A=1\nA=2\n");
+    }
+}
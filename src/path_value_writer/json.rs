@@ -0,0 +1,378 @@
+//! reconstructs a JSON document from the stream of paths and values.
+//!
+//! objects and arrays are opened and closed by comparing each path
+//! to the previous one, so the writer only needs to be given paths
+//! in document order, which is what [`crate::stream`] does.
+//! array indices are not checked for gaps, so any elements that
+//! were never written (i.e., by a filtering writer) are simply absent
+//! from the output array.
+//! each top-level value of the input, as announced by
+//! [`PathValueWriter::start_document`], is written as a separate document on its own line.
+//! infinite and NaN numbers, which JSON5, CBOR and MessagePack can hold
+//! but JSON cannot, are written as `null`.
+//!
+//! a key that appears twice in the same object has the same path both times,
+//! so when its values are objects or arrays, their members would be merged into one.
+//! to keep them apart, stream with [`crate::DuplicateKeys::Report`] or
+//! [`crate::DuplicateKeys::Warn`], which tell the writer where each repeated key starts.
+
+use super::{PathValueWriter, write_number};
+use crate::{JsonAtom, Number, Path, PathComponent};
+use std::io::Write;
+
+pub struct Writer<'writer, W: Write> {
+    writer: &'writer mut W,
+    options: Options,
+    /// the path of the most recently written value.
+    /// every component is a member of an object or array that is still open.
+    previous_path: Vec<OwnedPathComponent>,
+    /// whether anything has been written yet
+    started: bool,
+    /// the depth of a key that was just repeated, which starts a new member
+    /// even though the path of the next value is the same up to and including it
+    repeated_key_depth: Option<usize>,
+    /// whether a new top-level value has started since the last value was written
+    new_document: bool,
+}
+
+#[derive(Debug, PartialEq)]
+enum OwnedPathComponent {
+    Key(String),
+    Index(usize),
+}
+
+impl OwnedPathComponent {
+    fn is(&self, other: &PathComponent) -> bool {
+        match (self, other) {
+            (OwnedPathComponent::Key(a), PathComponent::Key(b)) => a == b.as_escaped_str(),
+            (OwnedPathComponent::Index(a), PathComponent::Index(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    fn same_container_kind(&self, other: &PathComponent) -> bool {
+        matches!(
+            (self, other),
+            (OwnedPathComponent::Key(_), PathComponent::Key(_))
+                | (OwnedPathComponent::Index(_), PathComponent::Index(_))
+        )
+    }
+}
+
+impl From<&PathComponent<'_>> for OwnedPathComponent {
+    fn from(component: &PathComponent) -> Self {
+        match component {
+            PathComponent::Key(k) => OwnedPathComponent::Key(k.as_escaped_str().to_owned()),
+            PathComponent::Index(i) => OwnedPathComponent::Index(*i),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Options {
+    pretty: bool,
+}
+
+impl Options {
+    /// whether to write one value per line, indented by two spaces per level of nesting,
+    /// rather than the most compact form. defaults to `false`.
+    pub fn pretty(mut self, pretty: bool) -> Self {
+        self.pretty = pretty;
+        self
+    }
+}
+
+impl<'writer, W: Write> Writer<'writer, W> {
+    pub fn new(writer: &'writer mut W, options: Options) -> Self {
+        Self {
+            writer,
+            options,
+            previous_path: vec![],
+            started: false,
+            repeated_key_depth: None,
+            new_document: false,
+        }
+    }
+
+    /// closes any objects and arrays that are still open.
+    /// this must be called after the last value has been written,
+    /// or the output will not be valid JSON.
+    pub fn finish(mut self) -> std::io::Result<()> {
+        self.close_down_to(0)?;
+
+        if self.started {
+            self.writer.write_all(b"\n")?;
+        }
+
+        Ok(())
+    }
+
+    /// closes open containers until only `depth` remain
+    fn close_down_to(&mut self, depth: usize) -> std::io::Result<()> {
+        while self.previous_path.len() > depth {
+            let component = self.previous_path.pop().unwrap();
+            self.newline_and_indent(self.previous_path.len())?;
+            match component {
+                OwnedPathComponent::Key(_) => self.writer.write_all(b"}")?,
+                OwnedPathComponent::Index(_) => self.writer.write_all(b"]")?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn newline_and_indent(&mut self, depth: usize) -> std::io::Result<()> {
+        if self.options.pretty {
+            self.writer.write_all(b"\n")?;
+            for _ in 0..depth {
+                self.writer.write_all(b"  ")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// writes the key (if any) for `component`, which is
+    /// at depth `depth`, and records it in `previous_path`
+    fn write_member(&mut self, depth: usize, component: &PathComponent) -> std::io::Result<()> {
+        self.newline_and_indent(depth + 1)?;
+
+        if let PathComponent::Key(k) = component {
+            self.writer.write_all(b"\"")?;
+            self.writer.write_all(k.as_escaped_str().as_bytes())?;
+            if self.options.pretty {
+                self.writer.write_all(b"\": ")?;
+            } else {
+                self.writer.write_all(b"\":")?;
+            }
+        }
+
+        self.previous_path.push(component.into());
+
+        Ok(())
+    }
+}
+
+impl<W: Write> PathValueWriter for Writer<'_, W> {
+    fn write_path_and_value(&mut self, path: Path, value: JsonAtom) -> std::io::Result<()> {
+        // the container at this depth is shared between the previous value and this one,
+        // and is where the two paths diverge.
+        // the last component of a path is always a new member of its container,
+        // even if it is equal to the previous one, i.e., for duplicate keys.
+        let shared = self
+            .previous_path
+            .iter()
+            .zip(path)
+            .take_while(|(previous, current)| previous.is(current))
+            .count()
+            .min(path.len().saturating_sub(1))
+            .min(self.previous_path.len().saturating_sub(1))
+            .min(self.repeated_key_depth.take().unwrap_or(usize::MAX));
+
+        let continues_document = !std::mem::take(&mut self.new_document)
+            && !path.is_empty()
+            && !self.previous_path.is_empty()
+            && self.previous_path[shared].same_container_kind(&path[shared]);
+
+        if continues_document {
+            self.close_down_to(shared + 1)?;
+            self.previous_path.pop();
+            self.writer.write_all(b",")?;
+            self.write_member(shared, &path[shared])?;
+        } else {
+            // a new top-level value
+            self.close_down_to(0)?;
+            if self.started {
+                self.writer.write_all(b"\n")?;
+            }
+        }
+
+        let first_new = if continues_document { shared + 1 } else { 0 };
+
+        for (depth, component) in path.iter().enumerate().skip(first_new) {
+            match component {
+                PathComponent::Key(_) => self.writer.write_all(b"{")?,
+                PathComponent::Index(_) => self.writer.write_all(b"[")?,
+            }
+            self.write_member(depth, component)?;
+        }
+
+        match value {
+            JsonAtom::String(s) => {
+                self.writer.write_all(b"\"")?;
+                self.writer.write_all(s.as_escaped_str().as_bytes())?;
+                self.writer.write_all(b"\"")?;
+            }
+            JsonAtom::Number(Number::Float(f)) if !f.is_finite() => {
+                self.writer.write_all(b"null")?
+            }
+            JsonAtom::Number(n) => write_number(self.writer, n)?,
+            JsonAtom::Bool(true) => self.writer.write_all(b"true")?,
+            JsonAtom::Bool(false) => self.writer.write_all(b"false")?,
+            JsonAtom::Null => self.writer.write_all(b"null")?,
+            JsonAtom::EmptyObject => self.writer.write_all(b"{}")?,
+            JsonAtom::EmptyArray => self.writer.write_all(b"[]")?,
+        }
+
+        self.started = true;
+
+        Ok(())
+    }
+
+    fn write_duplicate_key(
+        &mut self,
+        path: Path,
+        _first_offset: usize,
+        _second_offset: usize,
+    ) -> std::io::Result<()> {
+        self.repeated_key_depth = Some(path.len() - 1);
+        Ok(())
+    }
+
+    fn start_document(&mut self) -> std::io::Result<()> {
+        self.new_document = true;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Options as JSONWriterOptions, Writer as JSONWriter};
+    use crate::{DuplicateKeys, Options, Syntax, stream, stream_with_options};
+
+    fn roundtrip(s: &[u8], options: JSONWriterOptions) -> String {
+        let mut buf = vec![];
+        let mut writer = JSONWriter::new(&mut buf, options);
+        stream(s, &mut writer).unwrap();
+        writer.finish().unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn compact() {
+        let s = br#"
+        {
+            "a": 1,
+            "b": [1, [2, [3]], {}, [], {"c": null}],
+            "d": {"e": {"f": [{}, 9.5, "g\n"]}},
+            "h": true
+        }"#;
+
+        assert_eq!(
+            roundtrip(s, JSONWriterOptions::default()),
+            "{\"a\":1,\"b\":[1,[2,[3]],{},[],{\"c\":null}],\"d\":{\"e\":{\"f\":[{},9.5,\"g\\n\"]}},\"h\":true}\n"
+        );
+    }
+
+    #[test]
+    fn pretty() {
+        let s = br#"{"a": [1, {"b": 2}], "c": {}}"#;
+
+        assert_eq!(
+            roundtrip(s, JSONWriterOptions::default().pretty(true)),
+            "{\n  \"a\": [\n    1,\n    {\n      \"b\": 2\n    }\n  ],\n  \"c\": {}\n}\n"
+        );
+    }
+
+    #[test]
+    fn scalars_and_empty_documents() {
+        assert_eq!(roundtrip(b"1", JSONWriterOptions::default()), "1\n");
+        assert_eq!(roundtrip(b"{}", JSONWriterOptions::default()), "{}\n");
+        assert_eq!(roundtrip(b"[]", JSONWriterOptions::default()), "[]\n");
+        assert_eq!(roundtrip(b"", JSONWriterOptions::default()), "");
+    }
+
+    #[test]
+    fn documents_are_kept_apart() {
+        for (s, challenge) in [
+            ("[1,2] [3]", "[1,2]\n[3]\n"),
+            (r#"{"a":1} {"a":2}"#, "{\"a\":1}\n{\"a\":2}\n"),
+            (r#"{"a":[1]} {"a":[2]} 3"#, "{\"a\":[1]}\n{\"a\":[2]}\n3\n"),
+        ] {
+            assert_eq!(
+                roundtrip(s.as_bytes(), JSONWriterOptions::default()),
+                challenge,
+                "{s}"
+            );
+        }
+    }
+
+    #[test]
+    fn duplicate_keys_are_kept() {
+        let s = br#"{"a": 1, "a": {"b": 2}, "a": 3}"#;
+
+        assert_eq!(
+            roundtrip(s, JSONWriterOptions::default()),
+            "{\"a\":1,\"a\":{\"b\":2},\"a\":3}\n"
+        );
+    }
+
+    #[test]
+    fn duplicate_keys_with_container_values_are_kept_apart() {
+        for s in [
+            r#"{"a":[1],"a":[2]}"#,
+            r#"{"a":{"b":1},"a":{"b":2}}"#,
+            r#"{"x":{"a":{"b":[1,{"c":2}]},"a":{"b":[3]},"d":4},"x":[5]}"#,
+        ] {
+            for duplicate_keys in [DuplicateKeys::Report, DuplicateKeys::Warn] {
+                let mut buf = vec![];
+                let mut writer = JSONWriter::new(&mut buf, JSONWriterOptions::default());
+                stream_with_options(
+                    s.as_bytes(),
+                    &mut writer,
+                    &Options::default().duplicate_keys(duplicate_keys),
+                )
+                .unwrap();
+                writer.finish().unwrap();
+
+                assert_eq!(String::from_utf8(buf).unwrap(), format!("{s}\n"));
+            }
+        }
+
+        // a duplicate key inside of an object is still a member of that same object
+        let s = r#"{"a":{"b":1,"b":2}}"#;
+        let mut buf = vec![];
+        let mut writer = JSONWriter::new(&mut buf, JSONWriterOptions::default());
+        stream_with_options(
+            s.as_bytes(),
+            &mut writer,
+            &Options::default().duplicate_keys(DuplicateKeys::Report),
+        )
+        .unwrap();
+        writer.finish().unwrap();
+
+        assert_eq!(String::from_utf8(buf).unwrap(), format!("{s}\n"));
+    }
+
+    #[test]
+    fn non_finite_numbers_are_null() {
+        let mut buf = vec![];
+        let mut writer = JSONWriter::new(&mut buf, JSONWriterOptions::default());
+        stream_with_options(
+            br#"{"x": [Infinity, -Infinity, NaN, 1.5]}"#,
+            &mut writer,
+            &Options::default().syntax(Syntax::Json5),
+        )
+        .unwrap();
+        writer.finish().unwrap();
+
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "{\"x\":[null,null,null,1.5]}\n"
+        );
+    }
+
+    #[test]
+    fn fixtures_roundtrip() {
+        for fixture in [
+            "fixtures/one.json",
+            "fixtures/three.json",
+            "fixtures/github.json",
+        ] {
+            let s = std::fs::read(fixture).unwrap();
+            let once = roundtrip(&s, JSONWriterOptions::default());
+            let twice = roundtrip(once.as_bytes(), JSONWriterOptions::default());
+            assert_eq!(once, twice, "{fixture}");
+        }
+    }
+}
@@ -1,21 +1,79 @@
-use super::PathValueWriter;
+use super::{PathValueWriter, write_number};
+use crate::span::Span;
 use crate::{JsonAtom, Path, PathComponent};
 use std::io::Write;
 
 pub struct Writer<'writer, W: Write> {
     writer: &'writer mut W,
-    options: Options<'writer>,
+    line: Line<'writer>,
 }
 
 impl<'writer, W: Write> Writer<'writer, W> {
     pub fn new(writer: &'writer mut W, options: Options<'writer>) -> Self {
-        Self { writer, options }
+        Self {
+            writer,
+            line: Line::new(options),
+        }
     }
 }
 
+#[derive(Clone, Copy)]
 pub struct Options<'options> {
     separator: &'options str,
     write_empty_collections: bool,
+    decode_strings: bool,
+    quote_strings: bool,
+    write_offsets: bool,
+    write_duplicate_keys: bool,
+}
+
+impl<'options> Options<'options> {
+    /// the string written between each path and its value. defaults to a tab.
+    pub fn separator(mut self, separator: &'options str) -> Self {
+        self.separator = separator;
+        self
+    }
+
+    /// whether to write paths for empty objects and empty arrays. defaults to `false`.
+    pub fn write_empty_collections(mut self, write_empty_collections: bool) -> Self {
+        self.write_empty_collections = write_empty_collections;
+        self
+    }
+
+    /// whether to write string values with their JSON escapes decoded,
+    /// i.e., `café` rather than `caf\u00e9`.
+    /// control characters are still escaped, so that every value stays on one line.
+    /// note that this means a decoded `\n` and an escaped newline are written the same way.
+    /// defaults to `false`.
+    pub fn decode_strings(mut self, decode_strings: bool) -> Self {
+        self.decode_strings = decode_strings;
+        self
+    }
+
+    /// whether to write string values surrounded by double quotes. defaults to `true`.
+    pub fn quote_strings(mut self, quote_strings: bool) -> Self {
+        self.quote_strings = quote_strings;
+        self
+    }
+
+    /// whether to write the byte offset in the input at which each value starts
+    /// after the value, separated by the separator.
+    /// nothing is written for values whose offsets aren't known,
+    /// i.e., those not read from JSON. defaults to `false`.
+    pub fn write_offsets(mut self, write_offsets: bool) -> Self {
+        self.write_offsets = write_offsets;
+        self
+    }
+
+    /// whether to write a line for each key that appears more than once in the same object:
+    /// its path, `duplicate key`, and the byte offsets of its first and second appearances,
+    /// separated by the separator.
+    /// keys are only checked for this with [`crate::DuplicateKeys::Report`]
+    /// or [`crate::DuplicateKeys::Warn`]. defaults to `false`.
+    pub fn write_duplicate_keys(mut self, write_duplicate_keys: bool) -> Self {
+        self.write_duplicate_keys = write_duplicate_keys;
+        self
+    }
 }
 
 impl Default for Options<'_> {
@@ -23,76 +81,304 @@ impl Default for Options<'_> {
         Self {
             separator: "\t",
             write_empty_collections: false,
+            decode_strings: false,
+            quote_strings: true,
+            write_offsets: false,
+            write_duplicate_keys: false,
         }
     }
 }
 
-impl<W: Write> PathValueWriter for Writer<'_, W> {
-    fn write_path_and_value(&mut self, path: Path, value: JsonAtom) -> std::io::Result<()> {
+/// the line for each value, built up before it is written all at once.
+///
+/// the line starts with the path of the last value, which is kept between values,
+/// so that when the next path shares some of its components, only the rest are rendered.
+/// in deeply nested input, that is most of the path for most values.
+pub(super) struct Line<'options> {
+    options: Options<'options>,
+    buf: Vec<u8>,
+    /// for each component of the path at the start of `buf`, where it ends in `buf`,
+    /// and its index, if it is an array index
+    components: Vec<(usize, Option<usize>)>,
+}
+
+impl<'options> Line<'options> {
+    pub(super) fn new(options: Options<'options>) -> Self {
+        Self {
+            options,
+            buf: vec![],
+            components: vec![],
+        }
+    }
+
+    /// the line for `value`, if there is one to write
+    pub(super) fn render(
+        &mut self,
+        path: Path,
+        value: JsonAtom,
+        span: Option<Span>,
+    ) -> std::io::Result<Option<&[u8]>> {
+        if matches!(value, JsonAtom::EmptyObject | JsonAtom::EmptyArray)
+            && !self.options.write_empty_collections
+        {
+            return Ok(None);
+        }
+
+        self.render_path(path);
+
+        self.buf
+            .extend_from_slice(self.options.separator.as_bytes());
+
         match value {
             JsonAtom::String(s) => {
-                write_path(self.writer, path)?;
-                self.writer.write_all(self.options.separator.as_bytes())?;
-                self.writer.write_all(b"\"")?;
-                self.writer.write_all(s.as_escaped_str().as_bytes())?;
-                self.writer.write_all(b"\"\n")?;
-            }
-            JsonAtom::Number(n) => {
-                write_path(self.writer, path)?;
-                self.writer.write_all(self.options.separator.as_bytes())?;
-
-                match n {
-                    aws_smithy_types::Number::PosInt(i) => {
-                        let mut b = itoa::Buffer::new();
-                        self.writer.write_all(b.format(i).as_bytes())?;
-                    }
-                    aws_smithy_types::Number::NegInt(i) => {
-                        let mut b = itoa::Buffer::new();
-                        self.writer.write_all(b.format(i).as_bytes())?;
-                    }
-                    aws_smithy_types::Number::Float(f) => {
-                        let mut b = ryu::Buffer::new();
-                        self.writer.write_all(b.format(f).as_bytes())?;
-                    }
+                if self.options.quote_strings {
+                    self.buf.push(b'"');
                 }
 
-                self.writer.write_all(b"\n")?;
-            }
-            JsonAtom::Bool(b) => {
-                write_path(self.writer, path)?;
-                self.writer.write_all(self.options.separator.as_bytes())?;
-
-                if b {
-                    self.writer.write_all(b"true\n")?;
+                if self.options.decode_strings {
+                    write_decoded(&mut self.buf, s)?;
                 } else {
-                    self.writer.write_all(b"false\n")?;
+                    self.buf.extend_from_slice(s.as_escaped_str().as_bytes());
                 }
-            }
-            JsonAtom::EmptyObject => {
-                if self.options.write_empty_collections {
-                    write_path(self.writer, path)?;
-                    self.writer.write_all(self.options.separator.as_bytes())?;
-                    self.writer.write_all(b"{}\n")?;
+
+                if self.options.quote_strings {
+                    self.buf.push(b'"');
                 }
             }
-            JsonAtom::EmptyArray => {
-                if self.options.write_empty_collections {
-                    write_path(self.writer, path)?;
-                    self.writer.write_all(self.options.separator.as_bytes())?;
-                    self.writer.write_all(b"[]\n")?;
+            JsonAtom::Number(n) => write_number(&mut self.buf, n)?,
+            JsonAtom::Bool(true) => self.buf.extend_from_slice(b"true"),
+            JsonAtom::Bool(false) => self.buf.extend_from_slice(b"false"),
+            JsonAtom::EmptyObject => self.buf.extend_from_slice(b"{}"),
+            JsonAtom::EmptyArray => self.buf.extend_from_slice(b"[]"),
+            JsonAtom::Null => self.buf.extend_from_slice(b"null"),
+        }
+
+        if self.options.write_offsets
+            && let Some(span) = span
+        {
+            self.buf
+                .extend_from_slice(self.options.separator.as_bytes());
+            let mut b = itoa::Buffer::new();
+            self.buf.extend_from_slice(b.format(span.start).as_bytes());
+        }
+
+        self.buf.push(b'\n');
+
+        Ok(Some(&self.buf))
+    }
+
+    /// the line for a repeated key, if there is one to write
+    pub(super) fn render_duplicate_key(
+        &mut self,
+        path: Path,
+        first: usize,
+        second: usize,
+    ) -> Option<&[u8]> {
+        if !self.options.write_duplicate_keys {
+            return None;
+        }
+
+        self.render_path(path);
+
+        let separator = self.options.separator.as_bytes();
+        self.buf.extend_from_slice(separator);
+        self.buf.extend_from_slice(b"duplicate key");
+
+        for offset in [first, second] {
+            self.buf.extend_from_slice(separator);
+            let mut b = itoa::Buffer::new();
+            self.buf.extend_from_slice(b.format(offset).as_bytes());
+        }
+
+        self.buf.push(b'\n');
+
+        Some(&self.buf)
+    }
+
+    /// leaves `buf` holding just `path`, keeping the components it shares with the last path
+    fn render_path(&mut self, path: Path) {
+        let mut shared = 0;
+        let mut shared_end = 0;
+
+        for (&(end, index), component) in self.components.iter().zip(path) {
+            let same = match component {
+                PathComponent::Key(k) => {
+                    index.is_none()
+                        && self.buf[shared_end + 1..end] == *k.as_escaped_str().as_bytes()
                 }
+                PathComponent::Index(i) => index == Some(*i),
+            };
+
+            if !same {
+                break;
             }
-            JsonAtom::Null => {
-                write_path(self.writer, path)?;
-                self.writer.write_all(self.options.separator.as_bytes())?;
-                self.writer.write_all(b"null\n")?;
-            }
+
+            shared += 1;
+            shared_end = end;
+        }
+
+        self.components.truncate(shared);
+        self.buf.truncate(shared_end);
+
+        for component in &path[shared..] {
+            self.buf.push(b'/');
+
+            let index = match component {
+                PathComponent::Key(k) => {
+                    self.buf.extend_from_slice(k.as_escaped_str().as_bytes());
+                    None
+                }
+                PathComponent::Index(index) => {
+                    let mut b = itoa::Buffer::new();
+                    self.buf.extend_from_slice(b.format(*index).as_bytes());
+                    Some(*index)
+                }
+            };
+
+            self.components.push((self.buf.len(), index));
+        }
+    }
+}
+
+impl<W: Write> PathValueWriter for Writer<'_, W> {
+    fn write_path_and_value(&mut self, path: Path, value: JsonAtom) -> std::io::Result<()> {
+        match self.line.render(path, value, None)? {
+            Some(line) => self.writer.write_all(line),
+            None => Ok(()),
+        }
+    }
+
+    fn write_path_and_value_with_span(
+        &mut self,
+        path: Path,
+        value: JsonAtom,
+        span: Span,
+    ) -> std::io::Result<()> {
+        match self.line.render(path, value, Some(span))? {
+            Some(line) => self.writer.write_all(line),
+            None => Ok(()),
+        }
+    }
+
+    fn write_duplicate_key(
+        &mut self,
+        path: Path,
+        first_offset: usize,
+        second_offset: usize,
+    ) -> std::io::Result<()> {
+        match self
+            .line
+            .render_duplicate_key(path, first_offset, second_offset)
+        {
+            Some(line) => self.writer.write_all(line),
+            None => Ok(()),
+        }
+    }
+}
+
+/// writes the same lines as [`Writer`], to async I/O.
+/// each line is written with its own `write_all`, so `writer` should be buffered.
+#[cfg(feature = "async")]
+pub struct AsyncWriter<'writer, W: tokio::io::AsyncWrite + Unpin + Send> {
+    writer: &'writer mut W,
+    line: Line<'writer>,
+}
+
+#[cfg(feature = "async")]
+impl<'writer, W: tokio::io::AsyncWrite + Unpin + Send> AsyncWriter<'writer, W> {
+    pub fn new(writer: &'writer mut W, options: Options<'writer>) -> Self {
+        Self {
+            writer,
+            line: Line::new(options),
+        }
+    }
+
+    async fn write_line(
+        writer: &mut W,
+        line: std::io::Result<Option<&[u8]>>,
+    ) -> std::io::Result<()> {
+        use tokio::io::AsyncWriteExt;
+
+        match line? {
+            Some(line) => writer.write_all(line).await,
+            None => Ok(()),
         }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<W: tokio::io::AsyncWrite + Unpin + Send> super::AsyncPathValueWriter for AsyncWriter<'_, W> {
+    fn write_path_and_value(
+        &mut self,
+        path: Path,
+        value: JsonAtom,
+    ) -> impl Future<Output = std::io::Result<()>> + Send {
+        let line = self.line.render(path, value, None);
+        Self::write_line(self.writer, line)
+    }
+
+    fn write_path_and_value_with_span(
+        &mut self,
+        path: Path,
+        value: JsonAtom,
+        span: Span,
+    ) -> impl Future<Output = std::io::Result<()>> + Send {
+        let line = self.line.render(path, value, Some(span));
+        Self::write_line(self.writer, line)
+    }
 
-        Ok(())
+    fn write_duplicate_key(
+        &mut self,
+        path: Path,
+        first_offset: usize,
+        second_offset: usize,
+    ) -> impl Future<Output = std::io::Result<()>> + Send {
+        let line = self
+            .line
+            .render_duplicate_key(path, first_offset, second_offset);
+        Self::write_line(self.writer, Ok(line))
     }
 }
 
+/// writes `s` unescaped, except for control characters
+fn write_decoded<W: Write>(writer: &mut W, s: crate::EscapedStr) -> std::io::Result<()> {
+    // JSON strings can't contain literal control characters,
+    // so if there are no escapes, there is nothing to do
+    if !s.as_escaped_str().contains('\\') {
+        return writer.write_all(s.as_escaped_str().as_bytes());
+    }
+
+    let Ok(decoded) = s.to_unescaped() else {
+        return writer.write_all(s.as_escaped_str().as_bytes());
+    };
+
+    let mut start = 0;
+
+    for (i, b) in decoded.bytes().enumerate() {
+        if b.is_ascii_control() && b != 0x7F {
+            writer.write_all(&decoded.as_bytes()[start..i])?;
+
+            match b {
+                b'\n' => writer.write_all(b"\\n")?,
+                b'\r' => writer.write_all(b"\\r")?,
+                b'\t' => writer.write_all(b"\\t")?,
+                _ => write!(writer, "\\u{b:04x}")?,
+            }
+
+            start = i + 1;
+        }
+    }
+
+    writer.write_all(&decoded.as_bytes()[start..])
+}
+
+/// renders `path` the same way as it appears in this writer's output
+pub(crate) fn to_string(path: Path) -> String {
+    let mut buf = vec![];
+    write_path(&mut buf, path).expect("writing to a Vec cannot fail");
+    String::from_utf8(buf).expect("paths are made of valid UTF-8")
+}
+
 fn write_path<W: Write>(writer: &mut W, path_components: &[PathComponent]) -> std::io::Result<()> {
     for item in path_components {
         writer.write_all(b"/")?;
@@ -118,7 +404,10 @@ fn write_path<W: Write>(writer: &mut W, path_components: &[PathComponent]) -> st
 #[cfg(test)]
 mod tests {
     use super::{Options as JSONPointerWriterOptions, Writer as JSONPointerWriter};
-    use crate::stream;
+    use crate::path_value_writer::PathValueWriter;
+    use crate::{
+        DuplicateKeys, EscapedStr, JsonAtom, Options, PathComponent, stream, stream_with_options,
+    };
 
     #[test]
     fn simple_object() {
@@ -200,7 +489,7 @@ mod tests {
 
     #[test]
     fn weird_nested_objects_and_arrays() {
-        let s = br#"{"a":[{"b":[1,2,3]}]"#;
+        let s = br#"{"a":[{"b":[1,2,3]}]}"#;
         let mut buf = vec![];
         let mut writer = JSONPointerWriter::new(&mut buf, JSONPointerWriterOptions::default());
 
@@ -386,4 +675,144 @@ mod tests {
 
         assert_eq!(buf, challenge);
     }
+
+    #[test]
+    fn empty_collections() {
+        let s = br#"{"a": {}, "b": [], "c": [{}, [], 1], "d": {"e": 1}, "f": [2]}"#;
+
+        let mut buf = vec![];
+        let mut writer = JSONPointerWriter::new(
+            &mut buf,
+            JSONPointerWriterOptions::default().write_empty_collections(true),
+        );
+
+        stream(s, &mut writer).unwrap();
+
+        let challenge = b"/a\t{}\n/b\t[]\n/c/0\t{}\n/c/1\t[]\n/c/2\t1\n/d/e\t1\n/f/0\t2\n";
+
+        assert_eq!(buf, challenge);
+    }
+
+    #[test]
+    fn decoded_and_unquoted_strings() {
+        let s =
+            br#"{"a": "caf\u00e9", "b": "tab\there\nnewline \"quoted\" \\ \u0001", "c": "plain"}"#;
+
+        let mut buf = vec![];
+        let mut writer = JSONPointerWriter::new(
+            &mut buf,
+            JSONPointerWriterOptions::default().decode_strings(true),
+        );
+
+        stream(s, &mut writer).unwrap();
+
+        let challenge =
+            "/a\t\"café\"\n/b\t\"tab\\there\\nnewline \"quoted\" \\ \\u0001\"\n/c\t\"plain\"\n";
+
+        assert_eq!(std::str::from_utf8(&buf).unwrap(), challenge);
+
+        let mut buf = vec![];
+        let mut writer = JSONPointerWriter::new(
+            &mut buf,
+            JSONPointerWriterOptions::default()
+                .decode_strings(true)
+                .quote_strings(false),
+        );
+
+        stream(s, &mut writer).unwrap();
+
+        let challenge = "/a\tcafé\n/b\ttab\\there\\nnewline \"quoted\" \\ \\u0001\n/c\tplain\n";
+
+        assert_eq!(std::str::from_utf8(&buf).unwrap(), challenge);
+    }
+
+    #[test]
+    fn offsets() {
+        let s = br#"{"a": [1, "two"], "b": {}}"#;
+
+        let mut buf = vec![];
+        let mut writer = JSONPointerWriter::new(
+            &mut buf,
+            JSONPointerWriterOptions::default()
+                .write_empty_collections(true)
+                .write_offsets(true),
+        );
+
+        stream(s, &mut writer).unwrap();
+
+        let challenge = "/a/0\t1\t7\n/a/1\t\"two\"\t10\n/b\t{}\t23\n";
+
+        assert_eq!(std::str::from_utf8(&buf).unwrap(), challenge);
+    }
+
+    #[test]
+    fn paths_sharing_prefixes() {
+        let key = |k| PathComponent::Key(EscapedStr::new(k));
+        let index = PathComponent::Index;
+
+        let paths = [
+            vec![key("a"), key("b"), index(0)],
+            vec![key("a"), key("b"), index(1)],
+            vec![key("a"), key("bc")],
+            vec![key("a")],
+            vec![key("a"), key("0")],
+            vec![key("a"), index(0)],
+            vec![key("a"), index(10), key("b")],
+            vec![],
+            vec![key("a"), index(10), key("b")],
+            vec![key("a/b")],
+            vec![key("a"), key("b")],
+        ];
+
+        let mut buf = vec![];
+        let mut writer = JSONPointerWriter::new(&mut buf, JSONPointerWriterOptions::default());
+
+        for path in &paths {
+            writer.write_path_and_value(path, JsonAtom::Null).unwrap();
+        }
+
+        let challenge = "/a/b/0\tnull\n/a/b/1\tnull\n/a/bc\tnull\n/a\tnull\n/a/0\tnull\n/a/0\tnull\n\
+                         /a/10/b\tnull\n\tnull\n/a/10/b\tnull\n/a/b\tnull\n/a/b\tnull\n";
+
+        assert_eq!(std::str::from_utf8(&buf).unwrap(), challenge);
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        let s = br#"{"a": 1, "b": {"c": 2, "c": 3}, "a": [4]}"#;
+
+        let mut buf = vec![];
+        let mut writer = JSONPointerWriter::new(
+            &mut buf,
+            JSONPointerWriterOptions::default().write_duplicate_keys(true),
+        );
+
+        stream_with_options(
+            s,
+            &mut writer,
+            &Options::default().duplicate_keys(DuplicateKeys::Report),
+        )
+        .unwrap();
+
+        let challenge = "/a\t1\n/b/c\t2\n/b/c\tduplicate key\t15\t23\n/b/c\t3\n\
+                         /a\tduplicate key\t1\t32\n/a/0\t4\n";
+
+        assert_eq!(std::str::from_utf8(&buf).unwrap(), challenge);
+
+        // not written unless asked for
+        let mut buf = vec![];
+        let mut writer = JSONPointerWriter::new(&mut buf, JSONPointerWriterOptions::default());
+
+        stream_with_options(
+            s,
+            &mut writer,
+            &Options::default().duplicate_keys(DuplicateKeys::Report),
+        )
+        .unwrap();
+
+        assert_eq!(
+            std::str::from_utf8(&buf).unwrap(),
+            "/a\t1\n/b/c\t2\n/b/c\t3\n/a/0\t4\n"
+        );
+    }
 }
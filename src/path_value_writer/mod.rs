@@ -1,7 +1,128 @@
+use crate::span::Span;
 use crate::{JsonAtom, Path};
+use std::io::Write;
 
+pub mod binary;
+pub mod env;
+pub mod json;
 pub mod json_pointer;
+pub mod paths;
+pub mod properties;
+pub mod sorted;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
 
 pub trait PathValueWriter {
     fn write_path_and_value(&mut self, path: Path, value: JsonAtom) -> std::io::Result<()>;
+
+    /// called instead of [`PathValueWriter::write_path_and_value`] when it is known
+    /// where in the input the value is, which it is when streaming JSON.
+    ///
+    /// calls [`PathValueWriter::write_path_and_value`] by default.
+    fn write_path_and_value_with_span(
+        &mut self,
+        path: Path,
+        value: JsonAtom,
+        _span: Span,
+    ) -> std::io::Result<()> {
+        self.write_path_and_value(path, value)
+    }
+
+    /// called when the last key in `path` has already appeared in the same object,
+    /// if [`crate::DuplicateKeys::Report`] or [`crate::DuplicateKeys::Warn`] is set.
+    /// `first_offset` and `second_offset` are the byte offsets in the input of the two keys.
+    ///
+    /// does nothing by default.
+    fn write_duplicate_key(
+        &mut self,
+        _path: Path,
+        _first_offset: usize,
+        _second_offset: usize,
+    ) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    /// called before the values of each top-level value in the input,
+    /// as the input can be a sequence of documents, such as `[1] [2]`,
+    /// whose values would otherwise run together.
+    ///
+    /// does nothing by default.
+    fn start_document(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// like [`PathValueWriter`], for writers that write to async I/O, with [`crate::stream_async`]
+#[cfg(feature = "async")]
+pub trait AsyncPathValueWriter: Send {
+    fn write_path_and_value(
+        &mut self,
+        path: Path,
+        value: JsonAtom,
+    ) -> impl Future<Output = std::io::Result<()>> + Send;
+
+    /// like [`PathValueWriter::write_path_and_value_with_span`]
+    ///
+    /// calls [`AsyncPathValueWriter::write_path_and_value`] by default.
+    fn write_path_and_value_with_span(
+        &mut self,
+        path: Path,
+        value: JsonAtom,
+        _span: Span,
+    ) -> impl Future<Output = std::io::Result<()>> + Send {
+        self.write_path_and_value(path, value)
+    }
+
+    /// like [`PathValueWriter::write_duplicate_key`]
+    ///
+    /// does nothing by default.
+    fn write_duplicate_key(
+        &mut self,
+        _path: Path,
+        _first_offset: usize,
+        _second_offset: usize,
+    ) -> impl Future<Output = std::io::Result<()>> + Send {
+        std::future::ready(Ok(()))
+    }
+
+    /// like [`PathValueWriter::start_document`]
+    ///
+    /// does nothing by default.
+    fn start_document(&mut self) -> impl Future<Output = std::io::Result<()>> + Send {
+        std::future::ready(Ok(()))
+    }
+}
+
+/// the JSON5 spelling of `f` if it is infinite or NaN,
+/// which has no JSON number form
+pub(crate) fn non_finite_float(f: f64) -> Option<&'static str> {
+    if f.is_nan() {
+        Some("NaN")
+    } else if f.is_infinite() {
+        Some(if f > 0.0 { "Infinity" } else { "-Infinity" })
+    } else {
+        None
+    }
+}
+
+/// writes `n` the same way it would appear in a JSON document,
+/// or in a JSON5 one if it is a non-finite float
+pub(crate) fn write_number<W: Write>(writer: &mut W, n: crate::Number) -> std::io::Result<()> {
+    match n {
+        crate::Number::PosInt(i) => {
+            let mut b = itoa::Buffer::new();
+            writer.write_all(b.format(i).as_bytes())
+        }
+        crate::Number::NegInt(i) => {
+            let mut b = itoa::Buffer::new();
+            writer.write_all(b.format(i).as_bytes())
+        }
+        crate::Number::Float(f) => match non_finite_float(f) {
+            Some(s) => writer.write_all(s.as_bytes()),
+            None => {
+                let mut b = ryu::Buffer::new();
+                writer.write_all(b.format_finite(f).as_bytes())
+            }
+        },
+    }
 }
@@ -0,0 +1,174 @@
+//! writes each distinct path once, without values, with array indexes generalized,
+//! i.e., `/features/*/properties/name`.
+//! paths to empty objects and arrays are included.
+//!
+//! memory use is proportional to the number of distinct generalized paths,
+//! not the size of the input.
+
+use super::PathValueWriter;
+use crate::{JsonAtom, Path, PathComponent};
+use std::collections::HashMap;
+use std::io::Write;
+
+pub struct Writer<'writer, W: Write> {
+    writer: &'writer mut W,
+    options: Options<'writer>,
+    /// each distinct path, and its position in `counts`
+    seen: HashMap<String, usize>,
+    /// only used with [`Options::count`], in the order the paths were first seen
+    counts: Vec<usize>,
+    /// reused for building each generalized path
+    scratch: String,
+}
+
+impl<'writer, W: Write> Writer<'writer, W> {
+    pub fn new(writer: &'writer mut W, options: Options<'writer>) -> Self {
+        Self {
+            writer,
+            options,
+            seen: HashMap::new(),
+            counts: vec![],
+            scratch: String::new(),
+        }
+    }
+
+    /// writes the paths and their counts, if [`Options::count`] is set,
+    /// as they can't be written until every path has been seen
+    pub fn finish(self) -> std::io::Result<()> {
+        if !self.options.count {
+            return Ok(());
+        }
+
+        let mut paths: Vec<(&String, &usize)> = self.seen.iter().collect();
+        paths.sort_unstable_by_key(|(_, i)| **i);
+
+        for (path, i) in paths {
+            let count = self.counts[*i];
+
+            self.writer.write_all(path.as_bytes())?;
+            self.writer.write_all(b"\t")?;
+            let mut b = itoa::Buffer::new();
+            self.writer.write_all(b.format(count).as_bytes())?;
+            self.writer.write_all(b"\n")?;
+        }
+
+        Ok(())
+    }
+}
+
+pub struct Options<'options> {
+    index_placeholder: &'options str,
+    count: bool,
+}
+
+impl<'options> Options<'options> {
+    /// written in place of every array index. defaults to `*`.
+    pub fn index_placeholder(mut self, index_placeholder: &'options str) -> Self {
+        self.index_placeholder = index_placeholder;
+        self
+    }
+
+    /// whether to write the number of times each path occurs after it, separated by a tab.
+    /// if set, nothing is written until [`Writer::finish`] is called. defaults to `false`.
+    pub fn count(mut self, count: bool) -> Self {
+        self.count = count;
+        self
+    }
+}
+
+impl Default for Options<'_> {
+    fn default() -> Self {
+        Self {
+            index_placeholder: "*",
+            count: false,
+        }
+    }
+}
+
+impl<W: Write> PathValueWriter for Writer<'_, W> {
+    fn write_path_and_value(&mut self, path: Path, _value: JsonAtom) -> std::io::Result<()> {
+        self.scratch.clear();
+
+        for component in path {
+            self.scratch.push('/');
+
+            match component {
+                PathComponent::Key(k) => self.scratch.push_str(k.as_escaped_str()),
+                PathComponent::Index(_) => self.scratch.push_str(self.options.index_placeholder),
+            }
+        }
+
+        if let Some(i) = self.seen.get(&self.scratch) {
+            if self.options.count {
+                self.counts[*i] += 1;
+            }
+
+            return Ok(());
+        }
+
+        self.seen.insert(self.scratch.clone(), self.counts.len());
+
+        if self.options.count {
+            self.counts.push(1);
+        } else {
+            self.writer.write_all(self.scratch.as_bytes())?;
+            self.writer.write_all(b"\n")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Options as PathsWriterOptions, Writer as PathsWriter};
+    use crate::stream;
+
+    const S: &[u8] = br#"{
+        "features": [
+            {"id": 1, "properties": {"name": "a"}},
+            {"id": 2, "properties": {"name": "b", "tags": []}},
+            {"id": 3, "properties": {"name": "c"}}
+        ],
+        "matrix": [[1, 2], [3]]
+    }"#;
+
+    #[test]
+    fn distinct_paths() {
+        let mut buf = vec![];
+        let mut writer = PathsWriter::new(&mut buf, PathsWriterOptions::default());
+
+        stream(S, &mut writer).unwrap();
+        writer.finish().unwrap();
+
+        let challenge = "/features/*/id
+/features/*/properties/name
+/features/*/properties/tags
+/matrix/*/*
+";
+
+        assert_eq!(std::str::from_utf8(&buf).unwrap(), challenge);
+    }
+
+    #[test]
+    fn counts_and_placeholder() {
+        let mut buf = vec![];
+        let mut writer = PathsWriter::new(
+            &mut buf,
+            PathsWriterOptions::default()
+                .index_placeholder("[]")
+                .count(true),
+        );
+
+        stream(S, &mut writer).unwrap();
+        writer.finish().unwrap();
+
+        let challenge = "/features/[]/id\t3
+/features/[]/properties/name\t3
+/features/[]/properties/tags\t1
+/matrix/[]/[]\t3
+";
+
+        assert_eq!(std::str::from_utf8(&buf).unwrap(), challenge);
+    }
+}
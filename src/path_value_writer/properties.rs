@@ -0,0 +1,196 @@
+//! writes paths and values as Java `.properties` entries, i.e. `a.b[0].c=value`.
+//!
+//! keys and values are escaped as `java.util.Properties` would, except that every
+//! character outside of printable ASCII is written as a `\uXXXX` escape,
+//! not just those outside of Latin-1, so the output is read the same way whether
+//! it is loaded as Latin-1 (the default for `.properties` files) or UTF-8.
+
+use super::{PathValueWriter, write_number};
+use crate::{JsonAtom, Path, PathComponent};
+use std::io::Write;
+
+pub struct Writer<'writer, W: Write> {
+    writer: &'writer mut W,
+    options: Options,
+}
+
+impl<'writer, W: Write> Writer<'writer, W> {
+    pub fn new(writer: &'writer mut W, options: Options) -> Self {
+        Self { writer, options }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IndexStyle {
+    /// `a.b[0].c`, as Spring expects
+    #[default]
+    Brackets,
+    /// `a.b.0.c`
+    Dotted,
+}
+
+#[derive(Default)]
+pub struct Options {
+    index_style: IndexStyle,
+}
+
+impl Options {
+    /// how array indexes are written in keys. defaults to [`IndexStyle::Brackets`].
+    pub fn index_style(mut self, index_style: IndexStyle) -> Self {
+        self.index_style = index_style;
+        self
+    }
+}
+
+impl<W: Write> PathValueWriter for Writer<'_, W> {
+    fn write_path_and_value(&mut self, path: Path, value: JsonAtom) -> std::io::Result<()> {
+        // there is no way to write an empty collection as a property
+        if matches!(value, JsonAtom::EmptyObject | JsonAtom::EmptyArray) {
+            return Ok(());
+        }
+
+        self.write_key(path)?;
+        self.writer.write_all(b"=")?;
+
+        match value {
+            JsonAtom::String(s) => {
+                let s = s
+                    .to_unescaped()
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+                write_escaped(self.writer, &s, false)?;
+            }
+            JsonAtom::Number(n) => write_number(self.writer, n)?,
+            JsonAtom::Bool(true) => self.writer.write_all(b"true")?,
+            JsonAtom::Bool(false) => self.writer.write_all(b"false")?,
+            // null is written as an empty value
+            JsonAtom::Null => {}
+            JsonAtom::EmptyObject | JsonAtom::EmptyArray => unreachable!("handled above"),
+        }
+
+        self.writer.write_all(b"\n")
+    }
+}
+
+impl<W: Write> Writer<'_, W> {
+    fn write_key(&mut self, path: Path) -> std::io::Result<()> {
+        for (i, component) in path.iter().enumerate() {
+            match component {
+                PathComponent::Key(k) => {
+                    if i > 0 {
+                        self.writer.write_all(b".")?;
+                    }
+
+                    let k = k
+                        .to_unescaped()
+                        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+                    write_escaped(self.writer, &k, true)?;
+                }
+                PathComponent::Index(index) => {
+                    let mut b = itoa::Buffer::new();
+                    let index = b.format(*index).as_bytes();
+
+                    match self.options.index_style {
+                        IndexStyle::Brackets => {
+                            self.writer.write_all(b"[")?;
+                            self.writer.write_all(index)?;
+                            self.writer.write_all(b"]")?;
+                        }
+                        IndexStyle::Dotted => {
+                            if i > 0 {
+                                self.writer.write_all(b".")?;
+                            }
+                            self.writer.write_all(index)?;
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// writes `s` with the escapes `java.util.Properties` needs to read it back.
+/// every space in a key is escaped, as a space would end the key,
+/// but only a leading space in a value is, as the rest are kept.
+fn write_escaped<W: Write>(writer: &mut W, s: &str, is_key: bool) -> std::io::Result<()> {
+    for (i, c) in s.chars().enumerate() {
+        match c {
+            ' ' if is_key || i == 0 => writer.write_all(b"\\ ")?,
+            '\\' | '=' | ':' | '#' | '!' => {
+                writer.write_all(b"\\")?;
+                writer.write_all(&[c as u8])?;
+            }
+            '\t' => writer.write_all(b"\\t")?,
+            '\n' => writer.write_all(b"\\n")?,
+            '\r' => writer.write_all(b"\\r")?,
+            '\x0C' => writer.write_all(b"\\f")?,
+            ' '..='~' => writer.write_all(&[c as u8])?,
+            _ => {
+                let mut utf16 = [0; 2];
+                for unit in c.encode_utf16(&mut utf16) {
+                    write!(writer, "\\u{unit:04X}")?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IndexStyle, Options as PropertiesWriterOptions, Writer as PropertiesWriter};
+    use crate::stream;
+
+    #[test]
+    fn nested_values() {
+        let s = r#"{
+            "spring": {
+                "datasource": {"url": "jdbc:postgresql://db:5432/app", "password": null},
+                "profiles": {"active": ["dev", "local"]}
+            },
+            "greeting": " café ☕ 🎉",
+            "key with=special:chars": "a=b #c !d \\ \t",
+            "enabled": true,
+            "ratio": 0.25,
+            "tags": []
+        }"#
+        .as_bytes();
+
+        let mut buf = vec![];
+        let mut writer = PropertiesWriter::new(&mut buf, PropertiesWriterOptions::default());
+
+        stream(s, &mut writer).unwrap();
+
+        let challenge = r#"spring.datasource.url=jdbc\:postgresql\://db\:5432/app
+spring.datasource.password=
+spring.profiles.active[0]=dev
+spring.profiles.active[1]=local
+greeting=\ caf\u00E9 \u2615 \uD83C\uDF89
+key\ with\=special\:chars=a\=b \#c \!d \\ \t
+enabled=true
+ratio=0.25
+"#;
+
+        assert_eq!(std::str::from_utf8(&buf).unwrap(), challenge);
+    }
+
+    #[test]
+    fn index_styles() {
+        let s = br#"[{"a": [[1]]}]"#;
+
+        let mut buf = vec![];
+        let mut writer = PropertiesWriter::new(&mut buf, PropertiesWriterOptions::default());
+        stream(s, &mut writer).unwrap();
+        assert_eq!(std::str::from_utf8(&buf).unwrap(), "[0].a[0][0]=1\n");
+
+        let mut buf = vec![];
+        let mut writer = PropertiesWriter::new(
+            &mut buf,
+            PropertiesWriterOptions::default().index_style(IndexStyle::Dotted),
+        );
+        stream(s, &mut writer).unwrap();
+        assert_eq!(std::str::from_utf8(&buf).unwrap(), "0.a.0.0=1\n");
+    }
+}
@@ -0,0 +1,468 @@
+//! writes the same lines as [`super::json_pointer::Writer`], sorted by path,
+//! so that output is the same from one version of jstream to the next, and can be diffed.
+//!
+//! paths are compared component by component. array indexes are compared as numbers,
+//! so `/2` comes before `/10`, and come before keys, which are compared as they are written.
+//! a path comes before the paths inside it, and values with the same path are written in
+//! the order they appear in the input.
+//!
+//! lines are sorted in memory until they take up more than [`Options::memory_limit`],
+//! after which each sorted batch of lines is written to a temporary file,
+//! and the files are merged once all of the input has been read.
+//! at most [`MERGE_WIDTH`] files are merged at once, so when there are more than that,
+//! they are first merged into fewer, larger files.
+
+use super::PathValueWriter;
+use super::json_pointer::{Line, Options as JSONPointerWriterOptions};
+use crate::span::Span;
+use crate::{JsonAtom, Path, PathComponent};
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::ops::Range;
+use std::path::{Path as FsPath, PathBuf};
+
+/// how many temporary files are merged at once, and so open at once
+const MERGE_WIDTH: usize = 64;
+
+pub struct Writer<'writer, W: Write> {
+    writer: &'writer mut W,
+    options: Options<'writer>,
+    /// renders each line, keeping the path of the last one to render the next one from
+    line: Line<'writer>,
+    /// the sort keys and lines of the current batch, one after the other
+    bytes: Vec<u8>,
+    /// where each sort key and line are in `bytes`
+    lines: Vec<(Range<usize>, Range<usize>)>,
+    /// the batches that have been written to temporary files
+    runs: Vec<Run>,
+    /// how many temporary files have been created, to name the next one
+    runs_created: usize,
+}
+
+impl<'writer, W: Write> Writer<'writer, W> {
+    pub fn new(writer: &'writer mut W, options: Options<'writer>) -> Self {
+        Self {
+            writer,
+            line: Line::new(options.json_pointer),
+            options,
+            bytes: vec![],
+            lines: vec![],
+            runs: vec![],
+            runs_created: 0,
+        }
+    }
+
+    /// writes the sorted lines, as they can't be written until every line has been seen
+    pub fn finish(mut self) -> std::io::Result<()> {
+        if self.runs.is_empty() {
+            self.sort_batch();
+
+            for (_, line) in &self.lines {
+                self.writer.write_all(&self.bytes[line.clone()])?;
+            }
+
+            return Ok(());
+        }
+
+        self.spill()?;
+
+        // neighbouring runs are merged, so lines with the same path stay in input order
+        while self.runs.len() > MERGE_WIDTH {
+            let runs = std::mem::take(&mut self.runs);
+
+            for group in runs.chunks(MERGE_WIDTH) {
+                let (run, file) = self.create_run()?;
+                let mut file = BufWriter::new(file);
+
+                merge(group, |head| write_entry(&mut file, &head.key, &head.line))?;
+
+                file.flush()?;
+                self.runs.push(run);
+            }
+        }
+
+        merge(&self.runs, |head| self.writer.write_all(&head.line))
+    }
+
+    fn create_run(&mut self) -> std::io::Result<(Run, File)> {
+        self.runs_created += 1;
+        Run::create(self.options.temp_dir, self.runs_created)
+    }
+
+    fn sort_batch(&mut self) {
+        let bytes = &self.bytes;
+        // stable, so lines with the same path stay in input order
+        self.lines
+            .sort_by(|(a, _), (b, _)| bytes[a.clone()].cmp(&bytes[b.clone()]));
+    }
+
+    /// sorts the current batch and writes it to a temporary file
+    fn spill(&mut self) -> std::io::Result<()> {
+        self.sort_batch();
+
+        let (run, file) = self.create_run()?;
+        let mut file = BufWriter::new(file);
+
+        for (key, line) in &self.lines {
+            write_entry(
+                &mut file,
+                &self.bytes[key.clone()],
+                &self.bytes[line.clone()],
+            )?;
+        }
+
+        file.flush()?;
+
+        self.runs.push(run);
+        self.bytes.clear();
+        self.lines.clear();
+
+        Ok(())
+    }
+
+    /// adds the line that `render` renders for `path` to those to be sorted
+    fn write<F>(&mut self, path: Path, render: F) -> std::io::Result<()>
+    where
+        F: for<'line> FnOnce(&'line mut Line<'writer>) -> std::io::Result<Option<&'line [u8]>>,
+    {
+        // empty objects and arrays, and duplicate keys, may not be written at all
+        let Some(line) = render(&mut self.line)? else {
+            return Ok(());
+        };
+
+        let key_start = self.bytes.len();
+        write_sort_key(&mut self.bytes, path);
+        let line_start = self.bytes.len();
+        self.bytes.extend_from_slice(line);
+
+        self.lines
+            .push((key_start..line_start, line_start..self.bytes.len()));
+
+        let used = self.bytes.len()
+            + self.lines.len() * std::mem::size_of::<(Range<usize>, Range<usize>)>();
+
+        if used > self.options.memory_limit {
+            self.spill()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<W: Write> PathValueWriter for Writer<'_, W> {
+    fn write_path_and_value(&mut self, path: Path, value: JsonAtom) -> std::io::Result<()> {
+        self.write(path, |line| line.render(path, value, None))
+    }
+
+    fn write_path_and_value_with_span(
+        &mut self,
+        path: Path,
+        value: JsonAtom,
+        span: Span,
+    ) -> std::io::Result<()> {
+        self.write(path, |line| line.render(path, value, Some(span)))
+    }
+
+    fn write_duplicate_key(
+        &mut self,
+        path: Path,
+        first_offset: usize,
+        second_offset: usize,
+    ) -> std::io::Result<()> {
+        self.write(path, |line| {
+            Ok(line.render_duplicate_key(path, first_offset, second_offset))
+        })
+    }
+}
+
+pub struct Options<'options> {
+    json_pointer: JSONPointerWriterOptions<'options>,
+    memory_limit: usize,
+    temp_dir: Option<&'options FsPath>,
+}
+
+impl<'options> Options<'options> {
+    /// how each line is written. defaults to [`JSONPointerWriterOptions::default`].
+    pub fn json_pointer(mut self, json_pointer: JSONPointerWriterOptions<'options>) -> Self {
+        self.json_pointer = json_pointer;
+        self
+    }
+
+    /// roughly how many bytes of lines to sort in memory before writing them to a
+    /// temporary file. defaults to 256 MiB.
+    pub fn memory_limit(mut self, memory_limit: usize) -> Self {
+        self.memory_limit = memory_limit;
+        self
+    }
+
+    /// where to write temporary files. defaults to [`std::env::temp_dir`].
+    pub fn temp_dir(mut self, temp_dir: &'options FsPath) -> Self {
+        self.temp_dir = Some(temp_dir);
+        self
+    }
+}
+
+impl Default for Options<'_> {
+    fn default() -> Self {
+        Self {
+            json_pointer: JSONPointerWriterOptions::default(),
+            memory_limit: 256 * 1024 * 1024,
+            temp_dir: None,
+        }
+    }
+}
+
+/// a sorted batch of lines in a temporary file, which is removed when this is dropped
+struct Run {
+    path: PathBuf,
+}
+
+impl Run {
+    fn create(temp_dir: Option<&FsPath>, n: usize) -> std::io::Result<(Self, File)> {
+        let temp_dir = temp_dir.map_or_else(std::env::temp_dir, FsPath::to_path_buf);
+
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .subsec_nanos();
+
+        let path = temp_dir.join(format!("jstream-sort-{}-{nanos}-{n}", std::process::id()));
+        let file = File::create_new(&path)?;
+
+        Ok((Self { path }, file))
+    }
+}
+
+impl Drop for Run {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// the next line of a run, while merging
+struct Head {
+    key: Vec<u8>,
+    run: usize,
+    line: Vec<u8>,
+}
+
+impl Head {
+    fn read<R: BufRead>(reader: &mut R, run: usize) -> std::io::Result<Option<Self>> {
+        if reader.fill_buf()?.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            key: read_bytes(reader)?,
+            run,
+            line: read_bytes(reader)?,
+        }))
+    }
+}
+
+impl Ord for Head {
+    /// lines with the same path are written in the order of their runs,
+    /// which is the order they appear in the input
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key
+            .cmp(&other.key)
+            .then_with(|| self.run.cmp(&other.run))
+    }
+}
+
+impl PartialEq for Head {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Head {}
+
+impl PartialOrd for Head {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// merges the lines of `runs`, passing each one to `write` in order
+fn merge<F>(runs: &[Run], mut write: F) -> std::io::Result<()>
+where
+    F: FnMut(&Head) -> std::io::Result<()>,
+{
+    let mut readers = runs
+        .iter()
+        .map(|run| Ok(BufReader::new(File::open(&run.path)?)))
+        .collect::<std::io::Result<Vec<_>>>()?;
+
+    let mut heads = BinaryHeap::new();
+
+    for (run, reader) in readers.iter_mut().enumerate() {
+        if let Some(head) = Head::read(reader, run)? {
+            heads.push(Reverse(head));
+        }
+    }
+
+    while let Some(Reverse(head)) = heads.pop() {
+        write(&head)?;
+
+        if let Some(next) = Head::read(&mut readers[head.run], head.run)? {
+            heads.push(Reverse(next));
+        }
+    }
+
+    Ok(())
+}
+
+/// writes a sort key and its line to a run, to be read back by [`Head::read`]
+fn write_entry<W: Write>(writer: &mut W, key: &[u8], line: &[u8]) -> std::io::Result<()> {
+    writer.write_all(&(key.len() as u64).to_le_bytes())?;
+    writer.write_all(key)?;
+    writer.write_all(&(line.len() as u64).to_le_bytes())?;
+    writer.write_all(line)
+}
+
+fn read_bytes<R: Read>(reader: &mut R) -> std::io::Result<Vec<u8>> {
+    let mut len = [0; 8];
+    reader.read_exact(&mut len)?;
+
+    let mut bytes = vec![0; u64::from_le_bytes(len) as usize];
+    reader.read_exact(&mut bytes)?;
+
+    Ok(bytes)
+}
+
+/// writes `path` such that comparing the bytes written for two paths compares
+/// the paths as described in the module documentation
+fn write_sort_key(buf: &mut Vec<u8>, path: Path) {
+    for component in path {
+        match component {
+            PathComponent::Index(i) => {
+                buf.push(1);
+                buf.extend_from_slice(&(*i as u64).to_be_bytes());
+            }
+            // keys end with two zero bytes, and a zero byte in the key is followed by 0xFF,
+            // so a key comes before every longer key it is the start of
+            PathComponent::Key(k) => {
+                buf.push(2);
+
+                for b in k.as_escaped_str().bytes() {
+                    buf.push(b);
+
+                    if b == 0 {
+                        buf.push(0xFF);
+                    }
+                }
+
+                buf.extend_from_slice(&[0, 0]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Options as SortedWriterOptions, Writer as SortedWriter};
+    use crate::stream;
+
+    const S: &[u8] = br#"{
+        "b": [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+        "a": {"z": 1, "y": {"x": true}, "y2": null},
+        "b2": "last",
+        "a": "again"
+    }"#;
+
+    fn sorted(options: SortedWriterOptions) -> String {
+        let mut buf = vec![];
+        let mut writer = SortedWriter::new(&mut buf, options);
+
+        stream(S, &mut writer).unwrap();
+        writer.finish().unwrap();
+
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn sorted_paths() {
+        let challenge = "/a\t\"again\"
+/a/y/x\ttrue
+/a/y2\tnull
+/a/z\t1
+/b/0\t0
+/b/1\t1
+/b/2\t2
+/b/3\t3
+/b/4\t4
+/b/5\t5
+/b/6\t6
+/b/7\t7
+/b/8\t8
+/b/9\t9
+/b/10\t10
+/b/11\t11
+/b2\t\"last\"
+";
+
+        assert_eq!(sorted(SortedWriterOptions::default()), challenge);
+    }
+
+    #[test]
+    fn spilled_to_disk() {
+        let dir = std::env::temp_dir().join(format!("jstream-sorted-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // every few lines is written to its own file
+        assert_eq!(
+            sorted(
+                SortedWriterOptions::default()
+                    .memory_limit(100)
+                    .temp_dir(&dir)
+            ),
+            sorted(SortedWriterOptions::default())
+        );
+
+        // and they are all removed afterwards
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn more_runs_than_are_merged_at_once() {
+        let dir = std::env::temp_dir().join(format!("jstream-merged-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // a run for every line, with the same paths far apart in the input
+        let values = (0..super::MERGE_WIDTH * 3)
+            .map(|i| format!(r#"{{"b": {i}, "a": [{i}]}}"#))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let sorted = |options| {
+            let mut buf = vec![];
+            let mut writer = SortedWriter::new(&mut buf, options);
+
+            stream(values.as_bytes(), &mut writer).unwrap();
+            writer.finish().unwrap();
+
+            String::from_utf8(buf).unwrap()
+        };
+
+        let in_memory = sorted(SortedWriterOptions::default());
+        assert!(in_memory.starts_with("/a/0\t0\n/a/0\t1\n/a/0\t2\n"));
+
+        assert_eq!(
+            sorted(
+                SortedWriterOptions::default()
+                    .memory_limit(0)
+                    .temp_dir(&dir)
+            ),
+            in_memory
+        );
+
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,255 @@
+//! writes paths and values as rows of a SQLite table, so they can be queried with SQL.
+//!
+//! rows are inserted into a `path_values` table, which is created if it doesn't exist:
+//!
+//! | column         | contents                                                                  |
+//! |----------------|---------------------------------------------------------------------------|
+//! | `doc_id`       | identifies the input document, so many documents can share one database   |
+//! | `path`         | the JSON Pointer of the value                                             |
+//! | `parent_path`  | the JSON Pointer of the object or array the value is in                   |
+//! | `key_or_index` | the value's key (as text) or index (as an integer) in its parent, or null |
+//! | `depth`        | the number of components in `path`                                        |
+//! | `type`         | `string`, `number`, `boolean`, `null`, `object` or `array`                |
+//! | `value_text`   | the value as text. strings are unescaped                                  |
+//! | `value_num`    | the value of numbers, and null for everything else                        |
+//!
+//! `object` and `array` rows are only written for empty objects and arrays, with
+//! `{}` and `[]` as their text.
+//!
+//! rows are inserted in transactions of [`Options::batch_size`] rows,
+//! and [`Writer::finish`] must be called to commit the last one.
+
+use super::PathValueWriter;
+use crate::Number;
+use crate::{JsonAtom, Path, PathComponent};
+use rusqlite::Connection;
+use rusqlite::types::Value;
+
+const CREATE_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS path_values (
+    doc_id TEXT NOT NULL,
+    path TEXT NOT NULL,
+    parent_path TEXT NOT NULL,
+    key_or_index,
+    depth INTEGER NOT NULL,
+    type TEXT NOT NULL,
+    value_text TEXT,
+    value_num
+);
+CREATE INDEX IF NOT EXISTS path_values_doc_id_path ON path_values (doc_id, path);
+";
+
+const INSERT: &str = "
+INSERT INTO path_values
+    (doc_id, path, parent_path, key_or_index, depth, type, value_text, value_num)
+VALUES
+    (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+";
+
+pub struct Writer<'conn> {
+    conn: &'conn Connection,
+    doc_id: String,
+    options: Options,
+    /// the number of rows inserted in the open transaction, if there is one
+    rows_in_transaction: Option<usize>,
+}
+
+impl<'conn> Writer<'conn> {
+    /// creates the `path_values` table in `conn` if it doesn't exist.
+    /// every row is written with `doc_id`.
+    pub fn new(
+        conn: &'conn Connection,
+        doc_id: impl Into<String>,
+        options: Options,
+    ) -> std::io::Result<Self> {
+        conn.execute_batch(CREATE_TABLE).map_err(to_io_error)?;
+
+        Ok(Self {
+            conn,
+            doc_id: doc_id.into(),
+            options,
+            rows_in_transaction: None,
+        })
+    }
+
+    /// commits any rows not yet committed
+    pub fn finish(mut self) -> std::io::Result<()> {
+        self.commit()
+    }
+
+    fn commit(&mut self) -> std::io::Result<()> {
+        if self.rows_in_transaction.take().is_some() {
+            self.conn.execute_batch("COMMIT").map_err(to_io_error)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub struct Options {
+    batch_size: usize,
+}
+
+impl Options {
+    /// the number of rows inserted in each transaction. defaults to 10,000.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self { batch_size: 10_000 }
+    }
+}
+
+impl PathValueWriter for Writer<'_> {
+    fn write_path_and_value(&mut self, path: Path, value: JsonAtom) -> std::io::Result<()> {
+        let rows_in_transaction = match self.rows_in_transaction {
+            Some(rows) => rows,
+            None => {
+                self.conn.execute_batch("BEGIN").map_err(to_io_error)?;
+                0
+            }
+        };
+
+        let (type_, value_text, value_num) = match value {
+            JsonAtom::String(s) => {
+                let s = s.to_unescaped().map_err(to_io_error)?;
+                ("string", Value::Text(s.into_owned()), Value::Null)
+            }
+            JsonAtom::Number(n) => {
+                let mut buf = vec![];
+                super::write_number(&mut buf, n)?;
+                let text = String::from_utf8(buf).expect("numbers are ASCII");
+
+                let num = match n {
+                    Number::PosInt(i) => match i64::try_from(i) {
+                        Ok(i) => Value::Integer(i),
+                        Err(_) => Value::Real(i as f64),
+                    },
+                    Number::NegInt(i) => Value::Integer(i),
+                    Number::Float(f) => Value::Real(f),
+                };
+
+                ("number", Value::Text(text), num)
+            }
+            JsonAtom::Bool(b) => ("boolean", Value::Text(b.to_string()), Value::Null),
+            JsonAtom::Null => ("null", Value::Null, Value::Null),
+            JsonAtom::EmptyObject => ("object", Value::Text("{}".to_string()), Value::Null),
+            JsonAtom::EmptyArray => ("array", Value::Text("[]".to_string()), Value::Null),
+        };
+
+        let key_or_index = match path.last() {
+            Some(PathComponent::Key(k)) => {
+                Value::Text(k.to_unescaped().map_err(to_io_error)?.into_owned())
+            }
+            Some(PathComponent::Index(i)) => Value::Integer(*i as i64),
+            None => Value::Null,
+        };
+
+        let parent_path = super::json_pointer::to_string(&path[..path.len().saturating_sub(1)]);
+
+        self.conn
+            .prepare_cached(INSERT)
+            .and_then(|mut statement| {
+                statement.execute(rusqlite::params![
+                    self.doc_id,
+                    super::json_pointer::to_string(path),
+                    parent_path,
+                    key_or_index,
+                    path.len() as i64,
+                    type_,
+                    value_text,
+                    value_num,
+                ])
+            })
+            .map_err(to_io_error)?;
+
+        self.rows_in_transaction = Some(rows_in_transaction + 1);
+
+        if rows_in_transaction + 1 >= self.options.batch_size {
+            self.commit()?;
+        }
+
+        Ok(())
+    }
+}
+
+fn to_io_error<E: std::error::Error + Send + Sync + 'static>(e: E) -> std::io::Error {
+    std::io::Error::other(e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Options as SqliteWriterOptions, Writer as SqliteWriter};
+    use crate::stream;
+    use rusqlite::Connection;
+
+    #[test]
+    fn rows() {
+        let conn = Connection::open_in_memory().unwrap();
+
+        for (doc_id, s) in [
+            (
+                "a.json",
+                r#"{"name": "caf\u00e9", "tags": ["x", []], "n": 1.5, "ok": true}"#,
+            ),
+            ("b.json", r#"{"name": null, "n": -2}"#),
+        ] {
+            let mut writer =
+                SqliteWriter::new(&conn, doc_id, SqliteWriterOptions::default().batch_size(2))
+                    .unwrap();
+            stream(s.as_bytes(), &mut writer).unwrap();
+            writer.finish().unwrap();
+        }
+
+        let mut statement = conn
+            .prepare(
+                "SELECT doc_id, path, parent_path, quote(key_or_index), depth, type,
+                        quote(value_text), quote(value_num)
+                 FROM path_values ORDER BY rowid",
+            )
+            .unwrap();
+
+        let rows: Vec<String> = statement
+            .query_map([], |row| {
+                Ok(format!(
+                    "{}|{}|{}|{}|{}|{}|{}|{}",
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, i64>(4)?,
+                    row.get::<_, String>(5)?,
+                    row.get::<_, String>(6)?,
+                    row.get::<_, String>(7)?,
+                ))
+            })
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(
+            rows,
+            [
+                "a.json|/name||'name'|1|string|'café'|NULL",
+                "a.json|/tags/0|/tags|0|2|string|'x'|NULL",
+                "a.json|/tags/1|/tags|1|2|array|'[]'|NULL",
+                "a.json|/n||'n'|1|number|'1.5'|1.5",
+                "a.json|/ok||'ok'|1|boolean|'true'|NULL",
+                "b.json|/name||'name'|1|null|NULL|NULL",
+                "b.json|/n||'n'|1|number|'-2'|-2",
+            ]
+        );
+
+        let sum: f64 = conn
+            .query_row("SELECT sum(value_num) FROM path_values", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+
+        assert_eq!(sum, -0.5);
+    }
+}
@@ -0,0 +1,591 @@
+//! predicates on values, for keeping only the paths whose values match, i.e.,
+//! `is string and =~ /@example\.com$/` or `> 1000`.
+//!
+//! a predicate is made of tests on the value, combined with `and`, `or`, `not`
+//! and parentheses. `not` binds tightest, then `and`, then `or`.
+//!
+//! - `is string`, `is number`, `is integer`, `is bool`, `is null` and `is empty`
+//!   (an empty object or array) test the type of the value
+//! - `== LITERAL` and `!= LITERAL` compare the value to a JSON literal, i.e.,
+//!   `== "a"`, `== 1.5`, `== true` or `== null`. numbers are compared by value, so `== 1` matches `1.0`.
+//!   values of a different type than the literal are never equal to it
+//! - `< LITERAL`, `<= LITERAL`, `> LITERAL` and `>= LITERAL` compare numbers to a number,
+//!   or strings to a string, and are false for any other value
+//! - `=~ /REGEX/` matches strings against a regular expression. a `/` in the
+//!   regular expression is written `\/`. a trailing `i` (`/REGEX/i`) ignores case
+//!
+//! strings are compared and matched with their escapes decoded.
+
+use crate::EscapedStr;
+use crate::Number;
+use crate::path_value_writer::PathValueWriter;
+use crate::span::Span;
+use crate::{JsonAtom, Path};
+use regex::Regex;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+#[derive(Clone, Debug)]
+pub struct Predicate {
+    expr: Expr,
+}
+
+#[derive(Clone, Debug)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Is(Type),
+    Compare(Comparison, Literal),
+    Matches(Regex),
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Type {
+    String,
+    Number,
+    Integer,
+    Bool,
+    Null,
+    Empty,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Clone, Debug)]
+enum Literal {
+    String(String),
+    Number(Number),
+    Bool(bool),
+    Null,
+}
+
+/// an error in the text of a predicate
+#[derive(Debug)]
+pub struct ParseError {
+    /// the byte offset in the predicate text
+    pub offset: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at byte {}", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl Predicate {
+    pub fn new(predicate: &str) -> Result<Self, ParseError> {
+        let mut parser = Parser {
+            s: predicate,
+            offset: 0,
+        };
+
+        let expr = parser.or()?;
+
+        parser.skip_whitespace();
+        if parser.offset < parser.s.len() {
+            return Err(parser.error("expected `and`, `or` or the end of the predicate"));
+        }
+
+        Ok(Self { expr })
+    }
+
+    pub fn matches(&self, value: &JsonAtom) -> bool {
+        self.expr.matches(value)
+    }
+}
+
+impl FromStr for Predicate {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl Expr {
+    fn matches(&self, value: &JsonAtom) -> bool {
+        match self {
+            Expr::And(a, b) => a.matches(value) && b.matches(value),
+            Expr::Or(a, b) => a.matches(value) || b.matches(value),
+            Expr::Not(a) => !a.matches(value),
+            Expr::Is(type_) => match (type_, value) {
+                (Type::String, JsonAtom::String(_))
+                | (Type::Number, JsonAtom::Number(_))
+                | (Type::Bool, JsonAtom::Bool(_))
+                | (Type::Null, JsonAtom::Null)
+                | (Type::Empty, JsonAtom::EmptyObject | JsonAtom::EmptyArray) => true,
+                (Type::Integer, JsonAtom::Number(n)) => match n {
+                    Number::PosInt(_) | Number::NegInt(_) => true,
+                    Number::Float(f) => f.fract() == 0.0,
+                },
+                _ => false,
+            },
+            Expr::Compare(comparison, literal) => {
+                let ordering = match (value, literal) {
+                    (JsonAtom::String(s), Literal::String(literal)) => {
+                        decode(s).map(|s| s.as_ref().cmp(literal.as_str()))
+                    }
+                    (JsonAtom::Number(n), Literal::Number(literal)) => {
+                        compare_numbers(*n, *literal)
+                    }
+                    (JsonAtom::Bool(b), Literal::Bool(literal)) => {
+                        Some(b.cmp(literal)).filter(|o| o.is_eq())
+                    }
+                    (JsonAtom::Null, Literal::Null) => Some(Ordering::Equal),
+                    _ => None,
+                };
+
+                match comparison {
+                    Comparison::Eq => ordering == Some(Ordering::Equal),
+                    // values of different types are not equal
+                    Comparison::Ne => ordering != Some(Ordering::Equal),
+                    Comparison::Lt => ordering == Some(Ordering::Less),
+                    Comparison::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+                    Comparison::Gt => ordering == Some(Ordering::Greater),
+                    Comparison::Ge => {
+                        matches!(ordering, Some(Ordering::Greater | Ordering::Equal))
+                    }
+                }
+            }
+            Expr::Matches(regex) => match value {
+                JsonAtom::String(s) => decode(s).is_some_and(|s| regex.is_match(&s)),
+                _ => false,
+            },
+        }
+    }
+}
+
+fn decode<'a>(s: &EscapedStr<'a>) -> Option<Cow<'a, str>> {
+    s.to_unescaped().ok()
+}
+
+fn compare_numbers(a: Number, b: Number) -> Option<Ordering> {
+    match (a, b) {
+        (Number::PosInt(a), Number::PosInt(b)) => Some(a.cmp(&b)),
+        (Number::NegInt(a), Number::NegInt(b)) => Some(a.cmp(&b)),
+        (Number::PosInt(_), Number::NegInt(_)) => Some(Ordering::Greater),
+        (Number::NegInt(_), Number::PosInt(_)) => Some(Ordering::Less),
+        (a, b) => a.to_f64_lossy().partial_cmp(&b.to_f64_lossy()),
+    }
+}
+
+struct Parser<'s> {
+    s: &'s str,
+    offset: usize,
+}
+
+impl Parser<'_> {
+    fn or(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.and()?;
+
+        while self.keyword("or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.not()?;
+
+        while self.keyword("and") {
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, ParseError> {
+        if self.keyword("not") {
+            Ok(Expr::Not(Box::new(self.not()?)))
+        } else {
+            self.test()
+        }
+    }
+
+    fn test(&mut self) -> Result<Expr, ParseError> {
+        if self.symbol("(") {
+            let expr = self.or()?;
+
+            if !self.symbol(")") {
+                return Err(self.error("expected `)`"));
+            }
+
+            return Ok(expr);
+        }
+
+        if self.keyword("is") {
+            self.skip_whitespace();
+
+            let type_ = match self.word() {
+                "string" => Type::String,
+                "number" => Type::Number,
+                "integer" => Type::Integer,
+                "bool" | "boolean" => Type::Bool,
+                "null" => Type::Null,
+                "empty" => Type::Empty,
+                _ => {
+                    return Err(self.error(
+                        "expected `string`, `number`, `integer`, `bool`, `null` or `empty`",
+                    ));
+                }
+            };
+
+            self.offset += self.word().len();
+
+            return Ok(Expr::Is(type_));
+        }
+
+        if self.symbol("=~") {
+            return self.regex().map(Expr::Matches);
+        }
+
+        // the two-character operators must be tried first
+        for (symbol, comparison) in [
+            ("==", Comparison::Eq),
+            ("!=", Comparison::Ne),
+            ("<=", Comparison::Le),
+            (">=", Comparison::Ge),
+            ("<", Comparison::Lt),
+            (">", Comparison::Gt),
+        ] {
+            if self.symbol(symbol) {
+                let literal = self.literal()?;
+
+                if matches!(
+                    (comparison, &literal),
+                    (
+                        Comparison::Lt | Comparison::Le | Comparison::Gt | Comparison::Ge,
+                        Literal::Bool(_) | Literal::Null
+                    )
+                ) {
+                    return Err(self.error("only numbers and strings can be ordered"));
+                }
+
+                return Ok(Expr::Compare(comparison, literal));
+            }
+        }
+
+        Err(self.error("expected `(`, `not`, `is`, `=~` or a comparison"))
+    }
+
+    fn literal(&mut self) -> Result<Literal, ParseError> {
+        self.skip_whitespace();
+
+        let rest = &self.s[self.offset..];
+
+        if rest.starts_with('"') {
+            let Some(len) = string_len(rest) else {
+                return Err(self.error("unterminated string"));
+            };
+
+            let Ok(s) = EscapedStr::new(&rest[1..len - 1]).to_unescaped() else {
+                return Err(self.error("invalid string"));
+            };
+            let s = s.into_owned();
+
+            self.offset += len;
+
+            return Ok(Literal::String(s));
+        }
+
+        let word = self.word();
+
+        let literal = match word {
+            "true" => Literal::Bool(true),
+            "false" => Literal::Bool(false),
+            "null" => Literal::Null,
+            _ => match parse_number(word) {
+                Some(n) => Literal::Number(n),
+                None => {
+                    return Err(self.error("expected a string, number, `true`, `false` or `null`"));
+                }
+            },
+        };
+
+        self.offset += word.len();
+
+        Ok(literal)
+    }
+
+    fn regex(&mut self) -> Result<Regex, ParseError> {
+        self.skip_whitespace();
+
+        let start = self.offset;
+        let rest = &self.s[self.offset..];
+
+        if !rest.starts_with('/') {
+            return Err(self.error("expected a regular expression, i.e. `/[a-z]+/`"));
+        }
+
+        let mut pattern = String::new();
+        let mut chars = rest.char_indices().skip(1);
+
+        let end = loop {
+            match chars.next() {
+                Some((i, '/')) => break i,
+                Some((_, '\\')) => match chars.next() {
+                    Some((_, '/')) => pattern.push('/'),
+                    Some((_, c)) => {
+                        pattern.push('\\');
+                        pattern.push(c);
+                    }
+                    None => return Err(self.error("unterminated regular expression")),
+                },
+                Some((_, c)) => pattern.push(c),
+                None => return Err(self.error("unterminated regular expression")),
+            }
+        };
+
+        self.offset += end + 1;
+
+        if self.s[self.offset..].starts_with('i') {
+            pattern.insert_str(0, "(?i)");
+            self.offset += 1;
+        }
+
+        Regex::new(&pattern).map_err(|e| ParseError {
+            offset: start,
+            message: e.to_string(),
+        })
+    }
+
+    /// consumes `keyword` if it is the next word
+    fn keyword(&mut self, keyword: &str) -> bool {
+        self.skip_whitespace();
+
+        if self.word() == keyword {
+            self.offset += keyword.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// consumes `symbol` if it is next
+    fn symbol(&mut self, symbol: &str) -> bool {
+        self.skip_whitespace();
+
+        if self.s[self.offset..].starts_with(symbol) {
+            self.offset += symbol.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// the next run of characters that can make up a keyword or number, without consuming it
+    fn word(&self) -> &str {
+        let rest = &self.s[self.offset..];
+        let len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || "+-._".contains(c)))
+            .unwrap_or(rest.len());
+        &rest[..len]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.s[self.offset..];
+        self.offset += rest.len() - rest.trim_start().len();
+    }
+
+    fn error(&self, message: &str) -> ParseError {
+        ParseError {
+            offset: self.offset,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// the length of the double-quoted string at the start of `s`, including the quotes
+fn string_len(s: &str) -> Option<usize> {
+    let mut escaped = false;
+
+    for (i, b) in s.bytes().enumerate().skip(1) {
+        match b {
+            _ if escaped => escaped = false,
+            b'\\' => escaped = true,
+            b'"' => return Some(i + 1),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+fn parse_number(s: &str) -> Option<Number> {
+    if let Ok(i) = s.parse::<u64>() {
+        Some(Number::PosInt(i))
+    } else if let Ok(i) = s.parse::<i64>() {
+        Some(Number::NegInt(i))
+    } else {
+        s.parse::<f64>()
+            .ok()
+            .filter(|f| f.is_finite())
+            .map(Number::Float)
+    }
+}
+
+/// writes only the values that match a [`Predicate`] to the wrapped writer
+pub struct Writer<'writer, W: PathValueWriter + ?Sized> {
+    writer: &'writer mut W,
+    predicate: &'writer Predicate,
+}
+
+impl<'writer, W: PathValueWriter + ?Sized> Writer<'writer, W> {
+    pub fn new(writer: &'writer mut W, predicate: &'writer Predicate) -> Self {
+        Self { writer, predicate }
+    }
+}
+
+impl<W: PathValueWriter + ?Sized> PathValueWriter for Writer<'_, W> {
+    fn write_path_and_value(&mut self, path: Path, value: JsonAtom) -> std::io::Result<()> {
+        if self.predicate.matches(&value) {
+            self.writer.write_path_and_value(path, value)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn write_path_and_value_with_span(
+        &mut self,
+        path: Path,
+        value: JsonAtom,
+        span: Span,
+    ) -> std::io::Result<()> {
+        if self.predicate.matches(&value) {
+            self.writer
+                .write_path_and_value_with_span(path, value, span)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn write_duplicate_key(
+        &mut self,
+        path: Path,
+        first_offset: usize,
+        second_offset: usize,
+    ) -> std::io::Result<()> {
+        self.writer
+            .write_duplicate_key(path, first_offset, second_offset)
+    }
+
+    fn start_document(&mut self) -> std::io::Result<()> {
+        self.writer.start_document()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Predicate, Writer as PredicateWriter};
+    use crate::EscapedStr;
+    use crate::JsonAtom;
+    use crate::Number;
+    use crate::path_value_writer::json_pointer::{
+        Options as JSONPointerWriterOptions, Writer as JSONPointerWriter,
+    };
+
+    fn matches(predicate: &str, value: &JsonAtom) -> bool {
+        Predicate::new(predicate).unwrap().matches(value)
+    }
+
+    #[test]
+    fn tests() {
+        let s = JsonAtom::String(EscapedStr::new("bob\\u0040example.com"));
+
+        assert!(matches("is string", &s));
+        assert!(matches(r"=~ /@example\.com$/", &s));
+        assert!(matches(r"=~ /^BOB@/i", &s));
+        assert!(!matches(r"=~ /^BOB@/", &s));
+        assert!(matches(r#"== "bob@example.com""#, &s));
+        assert!(matches(r#"> "alice""#, &s));
+        assert!(!matches("> 1000", &s));
+        assert!(matches("!= 1000", &s));
+
+        let n = JsonAtom::Number(Number::PosInt(1001));
+
+        assert!(matches("> 1000", &n));
+        assert!(matches(">= 1000.5", &n));
+        assert!(matches("== 1001.0", &n));
+        assert!(matches("> -5", &n));
+        assert!(matches("is integer", &n));
+        assert!(!matches("is string", &n));
+        assert!(!matches("=~ /1001/", &n));
+
+        let f = JsonAtom::Number(Number::Float(-2.5));
+
+        assert!(matches("< -2", &f));
+        assert!(!matches("is integer", &f));
+
+        assert!(matches("is null", &JsonAtom::Null));
+        assert!(matches("== null", &JsonAtom::Null));
+        assert!(matches("== false", &JsonAtom::Bool(false)));
+        assert!(matches("is bool", &JsonAtom::Bool(true)));
+        assert!(matches("is empty", &JsonAtom::EmptyArray));
+        assert!(!matches("is empty", &JsonAtom::Null));
+    }
+
+    #[test]
+    fn combinators() {
+        let n = JsonAtom::Number(Number::PosInt(5));
+
+        assert!(matches("> 1 and < 10", &n));
+        assert!(!matches("> 1 and > 10", &n));
+        assert!(matches("> 10 or < 6", &n));
+        assert!(matches("not is string", &n));
+        assert!(matches("not not > 1", &n));
+        // `and` binds tighter than `or`
+        assert!(matches("is null or > 1 and < 10", &n));
+        assert!(!matches("(is null or > 1) and > 10", &n));
+        assert!(matches("not (is null or is string)", &n));
+    }
+
+    #[test]
+    fn parse_errors() {
+        for (predicate, offset) in [
+            ("", 0),
+            ("is strnig", 3),
+            ("> 1 and", 7),
+            ("(> 1", 4),
+            ("> 1 1", 4),
+            ("=~ /unterminated", 3),
+            ("=~ /(/", 3),
+            ("> true", 6),
+            (r#"== "unterminated"#, 3),
+        ] {
+            let e = Predicate::new(predicate).unwrap_err();
+            assert_eq!(e.offset, offset, "{predicate}: {e}");
+        }
+    }
+
+    #[test]
+    fn writer() {
+        let s = br#"{"users": [{"email": "a@example.com", "age": 30}, {"email": "b@test.org", "age": 1500}]}"#;
+
+        let predicate = Predicate::new(r"=~ /@example\.com$/ or > 1000").unwrap();
+
+        let mut buf = vec![];
+        let mut writer = JSONPointerWriter::new(&mut buf, JSONPointerWriterOptions::default());
+        let mut predicate_writer = PredicateWriter::new(&mut writer, &predicate);
+
+        crate::stream(s, &mut predicate_writer).unwrap();
+
+        assert_eq!(
+            std::str::from_utf8(&buf).unwrap(),
+            "/users/0/email\t\"a@example.com\"\n/users/1/age\t1500\n"
+        );
+    }
+}
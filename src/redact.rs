@@ -0,0 +1,303 @@
+//! masking of string and number values at paths matching a set of [`PathPattern`]s.
+//!
+//! redacted values can either be passed on to another [`PathValueWriter`]
+//! with [`Writer`], or spliced back into the original document with [`rewrite`],
+//! which leaves every other byte of the input untouched.
+
+use crate::EscapedStr;
+use crate::Number;
+use crate::escape::{escape_json_str, hex_into};
+use crate::path_pattern::PathPattern;
+use crate::path_value_writer::{PathValueWriter, non_finite_float};
+use crate::span::Span;
+use crate::{JsonAtom, Path};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::borrow::Cow;
+use std::io::Write;
+
+/// what a matching value is replaced with
+#[derive(Clone, Debug)]
+pub enum Replacement {
+    /// the string `"***"`, regardless of the type of the original value
+    Mask,
+    /// the hex-encoded HMAC-SHA256 of the value, as a string.
+    /// strings are hashed in their unescaped form,
+    /// so equal strings always hash equally.
+    Hash { key: Vec<u8> },
+    /// a value of the same type that keeps the rough shape of the original:
+    /// in strings, letters become `x`/`X` and digits become `0`,
+    /// and numbers become `0`
+    Fake,
+}
+
+pub struct Redactor {
+    patterns: Vec<PathPattern>,
+    replacement: Replacement,
+}
+
+/// the result of redacting a single value.
+/// for `String`, the escaped replacement is in the scratch buffer.
+enum Redacted {
+    String,
+    Number(Number),
+}
+
+impl Redactor {
+    pub fn new(patterns: Vec<PathPattern>, replacement: Replacement) -> Self {
+        Self {
+            patterns,
+            replacement,
+        }
+    }
+
+    pub fn matches(&self, path: Path) -> bool {
+        PathPattern::any_matches(&self.patterns, path)
+    }
+
+    /// returns `None` if `path` is not matched or `value` is not a string or number
+    fn redact(&self, path: Path, value: &JsonAtom, scratch: &mut String) -> Option<Redacted> {
+        if !matches!(value, JsonAtom::String(_) | JsonAtom::Number(_)) || !self.matches(path) {
+            return None;
+        }
+
+        scratch.clear();
+
+        match (&self.replacement, value) {
+            (Replacement::Mask, _) => {
+                scratch.push_str("***");
+                Some(Redacted::String)
+            }
+            (Replacement::Hash { key }, value) => {
+                let mut mac =
+                    Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+
+                match value {
+                    JsonAtom::String(s) => mac.update(unescaped(*s).as_bytes()),
+                    JsonAtom::Number(n) => mac.update(format_number(*n).as_bytes()),
+                    _ => unreachable!(),
+                }
+
+                hex_into(&mac.finalize().into_bytes(), scratch);
+                Some(Redacted::String)
+            }
+            (Replacement::Fake, JsonAtom::String(s)) => {
+                let fake: String = unescaped(*s)
+                    .chars()
+                    .map(|c| {
+                        if c.is_uppercase() {
+                            'X'
+                        } else if c.is_alphabetic() {
+                            'x'
+                        } else if c.is_numeric() {
+                            '0'
+                        } else {
+                            c
+                        }
+                    })
+                    .collect();
+                escape_json_str(&fake, scratch);
+                Some(Redacted::String)
+            }
+            (Replacement::Fake, JsonAtom::Number(n)) => match n {
+                Number::Float(_) => Some(Redacted::Number(Number::Float(0.0))),
+                _ => Some(Redacted::Number(Number::PosInt(0))),
+            },
+            (Replacement::Fake, _) => unreachable!(),
+        }
+    }
+}
+
+/// a [`PathValueWriter`] that redacts values before passing them on to `writer`
+pub struct Writer<'writer, W: PathValueWriter + ?Sized> {
+    writer: &'writer mut W,
+    redactor: &'writer Redactor,
+    scratch: String,
+}
+
+impl<'writer, W: PathValueWriter + ?Sized> Writer<'writer, W> {
+    pub fn new(writer: &'writer mut W, redactor: &'writer Redactor) -> Self {
+        Self {
+            writer,
+            redactor,
+            scratch: String::new(),
+        }
+    }
+}
+
+impl<W: PathValueWriter + ?Sized> PathValueWriter for Writer<'_, W> {
+    fn write_path_and_value(&mut self, path: Path, value: JsonAtom) -> std::io::Result<()> {
+        let value = redacted(self.redactor, path, value, &mut self.scratch);
+        self.writer.write_path_and_value(path, value)
+    }
+
+    fn write_path_and_value_with_span(
+        &mut self,
+        path: Path,
+        value: JsonAtom,
+        span: Span,
+    ) -> std::io::Result<()> {
+        let value = redacted(self.redactor, path, value, &mut self.scratch);
+        self.writer
+            .write_path_and_value_with_span(path, value, span)
+    }
+
+    fn write_duplicate_key(
+        &mut self,
+        path: Path,
+        first_offset: usize,
+        second_offset: usize,
+    ) -> std::io::Result<()> {
+        self.writer
+            .write_duplicate_key(path, first_offset, second_offset)
+    }
+
+    fn start_document(&mut self) -> std::io::Result<()> {
+        self.writer.start_document()
+    }
+}
+
+/// `value`, or what it is replaced with if it matches
+fn redacted<'a>(
+    redactor: &Redactor,
+    path: Path,
+    value: JsonAtom<'a>,
+    scratch: &'a mut String,
+) -> JsonAtom<'a> {
+    match redactor.redact(path, &value, scratch) {
+        Some(Redacted::String) => JsonAtom::String(EscapedStr::new(scratch)),
+        Some(Redacted::Number(n)) => JsonAtom::Number(n),
+        None => value,
+    }
+}
+
+/// writes `buf` to `out` with all matching values replaced.
+/// all other bytes, including whitespace, are written exactly as they appear in `buf`.
+pub fn rewrite<W: Write>(buf: &[u8], redactor: &Redactor, out: &mut W) -> std::io::Result<()> {
+    let mut scratch = String::new();
+    let mut written_up_to = 0;
+
+    crate::for_each_event(buf, &crate::Options::default(), |path, event| {
+        let crate::Event::Value(value, span) = event else {
+            return Ok(());
+        };
+
+        match redactor.redact(path, &value, &mut scratch) {
+            Some(Redacted::String) => {
+                out.write_all(&buf[written_up_to..span.start])?;
+                out.write_all(b"\"")?;
+                out.write_all(scratch.as_bytes())?;
+                out.write_all(b"\"")?;
+            }
+            Some(Redacted::Number(n)) => {
+                out.write_all(&buf[written_up_to..span.start])?;
+                out.write_all(format_number(n).as_bytes())?;
+            }
+            None => return Ok(()),
+        }
+
+        written_up_to = span.end;
+
+        Ok(())
+    })?;
+
+    out.write_all(&buf[written_up_to..])
+}
+
+fn unescaped(s: EscapedStr) -> Cow<str> {
+    s.to_unescaped()
+        .unwrap_or(Cow::Borrowed(s.as_escaped_str()))
+}
+
+fn format_number(n: Number) -> String {
+    match n {
+        Number::PosInt(i) => itoa::Buffer::new().format(i).to_owned(),
+        Number::NegInt(i) => itoa::Buffer::new().format(i).to_owned(),
+        Number::Float(f) => match non_finite_float(f) {
+            Some(s) => s.to_owned(),
+            None => ryu::Buffer::new().format_finite(f).to_owned(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Redactor, Replacement, Writer as RedactWriter, rewrite};
+    use crate::path_pattern::PathPattern;
+    use crate::path_value_writer::json_pointer::{
+        Options as JSONPointerWriterOptions, Writer as JSONPointerWriter,
+    };
+    use crate::stream;
+
+    const DOC: &[u8] = br#"{
+  "users": [
+    {"name": "Ann", "email": "ann@example.com", "ssn": 123456789},
+    {"name": "Bob",   "email": "B.o.b@example.com", "meta": {"ssn": "123-45-6789"}}
+  ],
+  "count": 2
+}"#;
+
+    fn redactor(replacement: Replacement) -> Redactor {
+        Redactor::new(
+            vec![
+                PathPattern::new("/users/*/email"),
+                PathPattern::new("**/ssn"),
+            ],
+            replacement,
+        )
+    }
+
+    #[test]
+    fn mask_lines() {
+        let redactor = redactor(Replacement::Mask);
+        let mut buf = vec![];
+        let mut json_pointer_writer =
+            JSONPointerWriter::new(&mut buf, JSONPointerWriterOptions::default());
+        let mut writer = RedactWriter::new(&mut json_pointer_writer, &redactor);
+
+        stream(DOC, &mut writer).unwrap();
+
+        let challenge = "/users/0/name\t\"Ann\"\n/users/0/email\t\"***\"\n/users/0/ssn\t\"***\"\n/users/1/name\t\"Bob\"\n/users/1/email\t\"***\"\n/users/1/meta/ssn\t\"***\"\n/count\t2\n";
+
+        assert_eq!(std::str::from_utf8(&buf).unwrap(), challenge);
+    }
+
+    #[test]
+    fn fake_rewrite_is_otherwise_byte_identical() {
+        let redactor = redactor(Replacement::Fake);
+        let mut buf = vec![];
+
+        rewrite(DOC, &redactor, &mut buf).unwrap();
+
+        let challenge = br#"{
+  "users": [
+    {"name": "Ann", "email": "xxx@xxxxxxx.xxx", "ssn": 0},
+    {"name": "Bob",   "email": "X.x.x@xxxxxxx.xxx", "meta": {"ssn": "000-00-0000"}}
+  ],
+  "count": 2
+}"#;
+
+        assert_eq!(
+            std::str::from_utf8(&buf).unwrap(),
+            std::str::from_utf8(challenge).unwrap()
+        );
+    }
+
+    #[test]
+    fn hash_is_keyed_and_stable() {
+        let hash = |key: &[u8], doc: &[u8]| {
+            let redactor = redactor(Replacement::Hash { key: key.to_vec() });
+            let mut buf = vec![];
+            rewrite(doc, &redactor, &mut buf).unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+
+        let a = hash(b"k1", br#"{"ssn": "abc"}"#);
+        let b = hash(b"k1", br#"{"ssn": "abc"}"#);
+        let c = hash(b"k2", br#"{"ssn": "abc"}"#);
+
+        assert_eq!(a, b);
+        assert_ne!(b, c);
+        assert_eq!(a.len(), r#"{"ssn": ""}"#.len() + 64);
+    }
+}
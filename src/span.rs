@@ -0,0 +1,66 @@
+//! where values appear in the input
+
+/// the byte offsets in the input at which a value starts and ends.
+/// `end` is exclusive, so `&buf[span.start..span.end]` is the value exactly as it is written.
+///
+/// the span of an empty object or array includes everything between its brackets,
+/// i.e., whitespace and comments.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// finds the line and column of byte offsets in a document.
+///
+/// building one reads the whole document once,
+/// after which each lookup is a binary search over its line starts.
+#[derive(Clone, Debug)]
+pub struct LineIndex {
+    /// the byte offset at which each line after the first starts
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(buf: &[u8]) -> Self {
+        Self {
+            line_starts: buf
+                .iter()
+                .enumerate()
+                .filter(|(_, b)| **b == b'\n')
+                .map(|(i, _)| i + 1)
+                .collect(),
+        }
+    }
+
+    /// the line and column of `offset`, both starting at 1.
+    /// columns count bytes, not characters.
+    pub fn line_column(&self, offset: usize) -> (usize, usize) {
+        let line = self.line_starts.partition_point(|start| *start <= offset);
+
+        let line_start = match line {
+            0 => 0,
+            line => self.line_starts[line - 1],
+        };
+
+        (line + 1, offset - line_start + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LineIndex;
+
+    #[test]
+    fn line_column() {
+        let lines = LineIndex::new(b"{\n  \"a\": 1,\n\n  \"b\": 2\n}");
+
+        assert_eq!(lines.line_column(0), (1, 1));
+        assert_eq!(lines.line_column(1), (1, 2));
+        assert_eq!(lines.line_column(2), (2, 1));
+        assert_eq!(lines.line_column(9), (2, 8));
+        assert_eq!(lines.line_column(12), (3, 1));
+        assert_eq!(lines.line_column(13), (4, 1));
+        assert_eq!(lines.line_column(22), (5, 1));
+    }
+}
@@ -0,0 +1,418 @@
+//! [`Streamer`], for input that arrives in pieces and can't be read with [`std::io::Read`],
+//! such as from a socket or a message queue.
+
+use crate::lenient::{Stack, Tokens, normalize_json5_strings};
+use crate::path_value_writer::PathValueWriter;
+use crate::{
+    ArraySampling, DuplicateKeys, Event, Options, OwnedPathComponent, Path, SampledArray,
+    SplitMix64, State, Syntax, write_event,
+};
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// writes the same paths and values as [`crate::stream_with_options`], from input that is
+/// passed in piece by piece, as it arrives. the pieces can be split anywhere,
+/// even in the middle of a string or number.
+///
+/// each value is written as soon as the piece that ends it is fed.
+/// between pieces, only the path and the bytes of the last, unfinished token are kept,
+/// along with the keys of the open objects when checking for duplicate keys,
+/// and the sampled elements of the open arrays with [`ArraySampling::Reservoir`].
+///
+/// once [`Streamer::feed`] returns an error, the rest of the input can't be streamed.
+pub struct Streamer {
+    options: Options,
+    /// bytes that have been fed but not yet tokenized
+    pending: Vec<u8>,
+    /// the byte offset of `pending` in the whole input
+    pending_offset: usize,
+    /// whether the end of `pending` is in a string or comment
+    scan: Scan,
+    /// the containers that tokenizing stopped inside of
+    stack: Stack,
+    state: SavedState,
+}
+
+impl Streamer {
+    pub fn new(options: Options) -> Self {
+        Self {
+            state: SavedState::new(&options),
+            options,
+            pending: vec![],
+            pending_offset: 0,
+            scan: Scan::default(),
+            stack: Stack::default(),
+        }
+    }
+
+    /// writes the paths and values that `chunk`, the next piece of the input, completes
+    pub fn feed<W: PathValueWriter + ?Sized>(
+        &mut self,
+        chunk: &[u8],
+        writer: &mut W,
+    ) -> std::io::Result<()> {
+        self.feed_events(chunk, &mut |path, event| write_event(writer, path, event))
+    }
+
+    /// writes whatever is left once all of the input has been fed,
+    /// failing if the input ends in the middle of a value
+    pub fn finish<W: PathValueWriter + ?Sized>(self, writer: &mut W) -> std::io::Result<()> {
+        self.finish_events(&mut |path, event| write_event(writer, path, event))
+    }
+
+    /// like [`Streamer::feed`], calling `f` for each [`Event`] rather than writing it
+    pub(crate) fn feed_events<F>(&mut self, chunk: &[u8], f: &mut F) -> std::io::Result<()>
+    where
+        F: for<'a> FnMut(Path<'a>, Event<'a>) -> std::io::Result<()>,
+    {
+        let Some(end) = self
+            .scan
+            .last_token_end(chunk, self.options.syntax == Syntax::Json5)
+        else {
+            self.pending.extend_from_slice(chunk);
+            return Ok(());
+        };
+
+        self.pending.extend_from_slice(&chunk[..end]);
+        self.tokenize(true, f)?;
+        self.pending.extend_from_slice(&chunk[end..]);
+
+        Ok(())
+    }
+
+    /// like [`Streamer::finish`], calling `f` for each [`Event`] rather than writing it
+    pub(crate) fn finish_events<F>(mut self, f: &mut F) -> std::io::Result<()>
+    where
+        F: for<'a> FnMut(Path<'a>, Event<'a>) -> std::io::Result<()>,
+    {
+        self.tokenize(false, f)
+    }
+
+    /// tokenizes `pending`, up to the start of a token it ends in the middle of, if `partial`
+    fn tokenize<F>(&mut self, partial: bool, f: &mut F) -> std::io::Result<()>
+    where
+        F: for<'a> FnMut(Path<'a>, Event<'a>) -> std::io::Result<()>,
+    {
+        // strings are never split, as `pending` never ends in one
+        if self.options.syntax == Syntax::Json5
+            && let Cow::Owned(normalized) = normalize_json5_strings(&self.pending)
+        {
+            self.pending = normalized;
+        }
+
+        let mut tokens = Tokens::resume(
+            &self.pending,
+            self.pending_offset,
+            self.options.syntax,
+            std::mem::take(&mut self.stack),
+            partial,
+        );
+
+        let mut state = self.state.load();
+
+        for token in &mut tokens {
+            let token =
+                token.map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+            state.handle_token(&self.pending, self.pending_offset, token, f)?;
+        }
+
+        let saved = SavedState::save(state);
+        let (tokenized, stack) = tokens.suspend();
+
+        self.state = saved;
+        self.stack = stack;
+        self.pending.drain(..tokenized);
+        self.pending_offset += tokenized;
+
+        Ok(())
+    }
+}
+
+/// where the end of the input fed so far is, as far as where tokens can end is concerned
+#[derive(Clone, Copy, Debug, Default)]
+enum Scan {
+    #[default]
+    Code,
+    /// just after a `/`, which might start a comment
+    Slash,
+    String {
+        quote: u8,
+        escaped: bool,
+    },
+    LineComment,
+    BlockComment {
+        after_star: bool,
+    },
+}
+
+impl Scan {
+    /// scans `chunk`, returning the position just after the last byte of it
+    /// that no token can carry on past, if there is one
+    fn last_token_end(&mut self, chunk: &[u8], json5: bool) -> Option<usize> {
+        let mut end = None;
+
+        for (i, b) in chunk.iter().enumerate() {
+            *self = match (*self, *b) {
+                (Scan::Slash, b'/') => Scan::LineComment,
+                (Scan::Slash, b'*') => Scan::BlockComment { after_star: false },
+                (Scan::Code | Scan::Slash, b'"') => Scan::String {
+                    quote: b'"',
+                    escaped: false,
+                },
+                (Scan::Code | Scan::Slash, b'\'') if json5 => Scan::String {
+                    quote: b'\'',
+                    escaped: false,
+                },
+                (Scan::Code, b'/') => Scan::Slash,
+                (
+                    Scan::Code | Scan::Slash,
+                    b'{' | b'}' | b'[' | b']' | b',' | b':' | b' ' | b'\t' | b'\n' | b'\r',
+                ) => {
+                    end = Some(i + 1);
+                    Scan::Code
+                }
+                (Scan::Code | Scan::Slash, _) => Scan::Code,
+                (
+                    Scan::String {
+                        quote,
+                        escaped: false,
+                    },
+                    b'\\',
+                ) => Scan::String {
+                    quote,
+                    escaped: true,
+                },
+                (
+                    Scan::String {
+                        quote,
+                        escaped: false,
+                    },
+                    b,
+                ) if b == quote => {
+                    end = Some(i + 1);
+                    Scan::Code
+                }
+                (Scan::String { quote, .. }, _) => Scan::String {
+                    quote,
+                    escaped: false,
+                },
+                (Scan::LineComment, b'\n') => {
+                    end = Some(i + 1);
+                    Scan::Code
+                }
+                (Scan::LineComment, _) => Scan::LineComment,
+                (Scan::BlockComment { after_star: true }, b'/') => {
+                    end = Some(i + 1);
+                    Scan::Code
+                }
+                (Scan::BlockComment { .. }, b) => Scan::BlockComment {
+                    after_star: b == b'*',
+                },
+            };
+        }
+
+        end
+    }
+}
+
+/// a [`State`] between chunks. only the path, which borrowed from the input, is copied:
+/// everything else is moved out of and back into the [`State`] of each chunk
+#[derive(Debug)]
+struct SavedState {
+    path: Vec<OwnedPathComponent>,
+    depth: usize,
+    object_keys: Vec<HashMap<String, usize>>,
+    arrays: Vec<SampledArray>,
+    skipping_from_depth: Option<usize>,
+    rng: SplitMix64,
+    last_open_offset: usize,
+    duplicate_keys: DuplicateKeys,
+    sampling: ArraySampling,
+}
+
+impl SavedState {
+    fn new(options: &Options) -> Self {
+        Self::save(State::new(options))
+    }
+
+    fn save(state: State<String>) -> Self {
+        Self {
+            path: state.path.iter().map(OwnedPathComponent::from).collect(),
+            depth: state.depth,
+            object_keys: state.object_keys,
+            arrays: state.arrays,
+            skipping_from_depth: state.skipping_from_depth,
+            rng: state.rng,
+            last_open_offset: state.last_open_offset,
+            duplicate_keys: state.duplicate_keys,
+            sampling: state.sampling,
+        }
+    }
+
+    /// the state to carry on from, which is left without its object keys and arrays
+    /// until it is saved again
+    fn load(&mut self) -> State<'_, String> {
+        State {
+            path: self.path.iter().map(OwnedPathComponent::borrow).collect(),
+            depth: self.depth,
+            object_keys: std::mem::take(&mut self.object_keys),
+            arrays: std::mem::take(&mut self.arrays),
+            skipping_from_depth: self.skipping_from_depth,
+            rng: SplitMix64(self.rng.0),
+            last_open_offset: self.last_open_offset,
+            duplicate_keys: self.duplicate_keys,
+            sampling: self.sampling,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Streamer;
+    use crate::path_value_writer::json_pointer::{
+        Options as JSONPointerWriterOptions, Writer as JSONPointerWriter,
+    };
+    use crate::{ArraySampling, DuplicateKeys, Options, Syntax, stream_with_options};
+
+    fn writer_options() -> JSONPointerWriterOptions<'static> {
+        JSONPointerWriterOptions::default()
+            .write_empty_collections(true)
+            .write_offsets(true)
+    }
+
+    fn streamed(s: &[u8], options: &Options) -> std::io::Result<String> {
+        let mut buf = vec![];
+        stream_with_options(
+            s,
+            &mut JSONPointerWriter::new(&mut buf, writer_options()),
+            options,
+        )?;
+        Ok(String::from_utf8(buf).unwrap())
+    }
+
+    fn fed<'a>(
+        chunks: impl IntoIterator<Item = &'a [u8]>,
+        options: &Options,
+    ) -> std::io::Result<String> {
+        let mut buf = vec![];
+        let mut writer = JSONPointerWriter::new(&mut buf, writer_options());
+        let mut streamer = Streamer::new(options.clone());
+
+        for chunk in chunks {
+            streamer.feed(chunk, &mut writer)?;
+        }
+
+        streamer.finish(&mut writer)?;
+
+        Ok(String::from_utf8(buf).unwrap())
+    }
+
+    #[test]
+    fn fixtures_split_at_every_byte() {
+        for fixture in [
+            "fixtures/one.json",
+            "fixtures/three.json",
+            "fixtures/github.json",
+            "fixtures/city_lots_small.json",
+        ] {
+            let s = std::fs::read(fixture).unwrap();
+            let challenge = streamed(&s, &Options::default()).unwrap();
+
+            for i in 0..=s.len() {
+                assert_eq!(
+                    fed([&s[..i], &s[i..]], &Options::default()).unwrap(),
+                    challenge,
+                    "{fixture} split at {i}"
+                );
+            }
+
+            assert_eq!(
+                fed(s.chunks(1), &Options::default()).unwrap(),
+                challenge,
+                "{fixture} one byte at a time"
+            );
+        }
+    }
+
+    #[test]
+    fn options_split_at_every_byte() {
+        let dupes = br#"{"a": 1, "b": {"c": [1, {}, []], "c": "x"}, "a": -2.5e3} 7 "top""#;
+        let json5 = br#"// leading comment
+            {unquoted: 'single \' quoted', "b": [+1, .5, 0x1F, Infinity, NaN,],
+             /* block */ c: {d: null, e: true,},}"#;
+        let numbers = br#"[18446744073709551616, -9223372036854775809, 1., -.5, 1e2, 0]"#;
+        let arrays = format!(
+            "[{}]",
+            (0..30)
+                .map(|i| format!(r#"{{"i": {i}, "s": ["{i}", {i}]}}"#))
+                .collect::<Vec<_>>()
+                .join(",")
+        );
+
+        for (s, options) in [
+            (
+                dupes.as_slice(),
+                Options::default().duplicate_keys(DuplicateKeys::Report),
+            ),
+            (json5.as_slice(), Options::default().syntax(Syntax::Json5)),
+            (json5.as_slice(), Options::default().syntax(Syntax::Jsonc)),
+            (numbers.as_slice(), Options::default()),
+            (numbers.as_slice(), Options::default().syntax(Syntax::Json5)),
+            (
+                arrays.as_bytes(),
+                Options::default().array_sampling(ArraySampling::Reservoir { size: 4, seed: 3 }),
+            ),
+            (
+                arrays.as_bytes(),
+                Options::default().array_sampling(ArraySampling::Rate { rate: 0.3, seed: 3 }),
+            ),
+        ] {
+            let challenge = streamed(s, &options).map_err(|e| e.kind());
+
+            for i in 0..=s.len() {
+                assert_eq!(
+                    fed([&s[..i], &s[i..]], &options).map_err(|e| e.kind()),
+                    challenge,
+                    "{options:?} split at {i}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn errors() {
+        for s in [
+            br#"{"a": 1"#.as_slice(),
+            br#"{"a": "unterminated"#,
+            br#"[1, 2,]"#,
+            br#"[1] // comment"#,
+            br#"{"a" 1}"#,
+            br#"[tru]"#,
+            br#"["\u00"]"#,
+            br#"["\x41"]"#,
+            br#"[1e999]"#,
+        ] {
+            // the same errors as `stream_with_options`, wherever the input is split
+            let challenge = streamed(s, &Options::default()).map_err(|e| e.to_string());
+            assert!(challenge.is_err(), "{}", String::from_utf8_lossy(s));
+
+            for i in 0..=s.len() {
+                assert_eq!(
+                    fed([&s[..i], &s[i..]], &Options::default()).map_err(|e| e.to_string()),
+                    challenge,
+                    "{} split at {i}",
+                    String::from_utf8_lossy(s)
+                );
+            }
+        }
+
+        // and the same duplicate keys error
+        let options = Options::default().duplicate_keys(DuplicateKeys::Error);
+        let s = br#"{"a": 1, "a": 2}"#;
+        assert_eq!(
+            fed([&s[..10], &s[10..]], &options).unwrap_err().to_string(),
+            streamed(s, &options).unwrap_err().to_string()
+        );
+    }
+}
@@ -0,0 +1,121 @@
+//! the tokens that input is read as, whatever its syntax,
+//! and the strings and numbers in them
+
+use std::borrow::Cow;
+
+/// a string as it appears in JSON, without its quotes and with its escapes left in.
+/// most strings are written back out as JSON, so they are only unescaped when asked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EscapedStr<'input>(&'input str);
+
+impl<'input> EscapedStr<'input> {
+    pub fn new(s: &'input str) -> Self {
+        Self(s)
+    }
+
+    pub fn as_escaped_str(&self) -> &'input str {
+        self.0
+    }
+
+    /// the string with its escapes decoded, which is only a new string if it has any
+    pub fn to_unescaped(self) -> Result<Cow<'input, str>, EscapeError> {
+        crate::escape::unescape_json_str(self.0)
+    }
+}
+
+/// an escape in an [`EscapedStr`] that doesn't decode to anything
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EscapeError(pub(crate) &'static str);
+
+impl std::fmt::Display for EscapeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} in JSON string", self.0)
+    }
+}
+
+impl std::error::Error for EscapeError {}
+
+/// a number. integers are kept as integers if they fit in a `u64`, or an `i64` if negative.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Number {
+    PosInt(u64),
+    /// always negative, except for `-0`
+    NegInt(i64),
+    Float(f64),
+}
+
+impl Number {
+    /// the number as a float, rounded if it is an integer too large to be one exactly
+    pub fn to_f64_lossy(self) -> f64 {
+        match self {
+            Number::PosInt(n) => n as f64,
+            Number::NegInt(n) => n as f64,
+            Number::Float(f) => f,
+        }
+    }
+}
+
+/// where input stops being valid, and why
+#[derive(Debug)]
+pub struct SyntaxError {
+    message: &'static str,
+    offset: usize,
+}
+
+impl SyntaxError {
+    pub(crate) fn new(message: &'static str, offset: usize) -> Self {
+        Self { message, offset }
+    }
+
+    /// the byte offset in the input
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl std::fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at byte {}", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for SyntaxError {}
+
+/// the byte offset of a token in the input
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Offset(pub(crate) usize);
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum Token<'input> {
+    StartArray {
+        offset: Offset,
+    },
+    EndArray {
+        offset: Offset,
+    },
+    ObjectKey {
+        offset: Offset,
+        key: EscapedStr<'input>,
+    },
+    StartObject {
+        offset: Offset,
+    },
+    EndObject {
+        offset: Offset,
+    },
+    ValueBool {
+        offset: Offset,
+        value: bool,
+    },
+    ValueNull {
+        offset: Offset,
+    },
+    ValueNumber {
+        offset: Offset,
+        value: Number,
+    },
+    ValueString {
+        offset: Offset,
+        value: EscapedStr<'input>,
+    },
+}
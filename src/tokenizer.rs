@@ -0,0 +1,558 @@
+//! the tokenizer for plain JSON.
+//!
+//! it does as little as it can for each token: strings are checked and returned with
+//! their escapes left in, for writers to unescape only if they need to,
+//! and the bytes of a string are looked at 8 at a time to find the quote,
+//! backslash or control character that ends or interrupts it.
+//!
+//! it produces the same tokens as the tokenizer of `aws-smithy-json`, which jstream used
+//! before, but doesn't accept quite the same documents: that let through strings
+//! with invalid escapes and input that ends inside of an object or array,
+//! and rejected integers too large for a `u64` or `i64`, which become floats here,
+//! as long as they are finite.
+
+use crate::token::{EscapedStr, Number, Offset, SyntaxError, Token};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    Initial,
+    ArrayFirstValueOrEnd,
+    ArrayNextValueOrEnd,
+    ObjectFirstKeyOrEnd,
+    ObjectNextKeyOrEnd,
+    /// after an object key and its `:`
+    ObjectFieldValue,
+}
+
+pub(crate) struct Tokens<'input> {
+    input: &'input [u8],
+    index: usize,
+    state_stack: Vec<State>,
+    /// set after an error, so that iteration stops
+    failed: bool,
+}
+
+impl<'input> Tokens<'input> {
+    pub(crate) fn new(input: &'input [u8]) -> Self {
+        Self {
+            input,
+            index: 0,
+            state_stack: vec![State::Initial],
+            failed: false,
+        }
+    }
+
+    fn error(&self, message: &'static str) -> SyntaxError {
+        SyntaxError::new(message, self.index)
+    }
+
+    fn state(&self) -> State {
+        *self.state_stack.last().unwrap()
+    }
+
+    fn replace_state(&mut self, state: State) {
+        *self.state_stack.last_mut().unwrap() = state;
+    }
+
+    fn skip_whitespace(&mut self) {
+        // indentation, 8 spaces at a time
+        while let Some(bytes) = self.input.get(self.index..self.index + 8)
+            && bytes == b"        "
+        {
+            self.index += 8;
+        }
+
+        while let Some(b' ' | b'\n' | b'\r' | b'\t') = self.input.get(self.index) {
+            self.index += 1;
+        }
+    }
+
+    /// the next byte that isn't whitespace
+    fn next_significant(&mut self) -> Result<u8, SyntaxError> {
+        self.skip_whitespace();
+
+        self.input
+            .get(self.index)
+            .copied()
+            .ok_or_else(|| self.error("unexpected end of input"))
+    }
+
+    /// reads the string starting at the current `"`, returning its escaped contents
+    fn read_string(&mut self) -> Result<&'input str, SyntaxError> {
+        let start = self.index + 1;
+        let mut i = start;
+
+        loop {
+            while let Some(bytes) = self.input.get(i..i + 8) {
+                let special = special_bytes(u64::from_le_bytes(bytes.try_into().unwrap()));
+
+                if special != 0 {
+                    i += special.trailing_zeros() as usize / 8;
+                    break;
+                }
+
+                i += 8;
+            }
+
+            match self.input.get(i) {
+                Some(b'"') => break,
+                Some(b'\\') => match self.input.get(i + 1) {
+                    Some(b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't') => i += 2,
+                    Some(b'u')
+                        if self
+                            .input
+                            .get(i + 2..i + 6)
+                            .is_some_and(|digits| digits.iter().all(u8::is_ascii_hexdigit)) =>
+                    {
+                        i += 6
+                    }
+                    None => {
+                        self.index = self.input.len();
+                        return Err(self.error("unterminated string"));
+                    }
+                    Some(_) => {
+                        self.index = i;
+                        return Err(self.error("invalid escape in string"));
+                    }
+                },
+                Some(0x00..=0x1F) => {
+                    self.index = i;
+                    return Err(self.error("unescaped control character in string"));
+                }
+                // within 8 bytes of the end of the input
+                Some(_) => i += 1,
+                None => {
+                    self.index = self.input.len();
+                    return Err(self.error("unterminated string"));
+                }
+            }
+        }
+
+        let s = std::str::from_utf8(&self.input[start..i]).map_err(|e| {
+            self.index = start + e.valid_up_to();
+            self.error("invalid UTF-8 in string")
+        })?;
+
+        self.index = i + 1;
+
+        Ok(s)
+    }
+
+    fn read_literal(
+        &mut self,
+        literal: &'static [u8],
+        token: Token<'input>,
+    ) -> Result<Token<'input>, SyntaxError> {
+        if self.input[self.index..].starts_with(literal) {
+            self.index += literal.len();
+            Ok(token)
+        } else {
+            Err(self.error("invalid literal"))
+        }
+    }
+
+    fn read_number(&mut self) -> Result<Token<'input>, SyntaxError> {
+        let start = self.index;
+        let negative = self.input[start] == b'-';
+        let digits_start = start + negative as usize;
+
+        let mut i = digits_start;
+        // the value of the digits so far, if they are all there is and fit in a `u64`
+        let mut integer = Some(0u64);
+
+        while let Some(b @ b'0'..=b'9') = self.input.get(i) {
+            integer = integer
+                .and_then(|n| n.checked_mul(10))
+                .and_then(|n| n.checked_add((b - b'0') as u64));
+            i += 1;
+        }
+
+        let mut floating = false;
+
+        // as loose as the tokenizer this replaces, so that the same numbers are accepted,
+        // leaving it to the float parser to reject things like `1.2.3`
+        loop {
+            match self.input.get(i) {
+                Some(b'0'..=b'9') => i += 1,
+                Some(b'.') => {
+                    floating = true;
+                    i += 1;
+                }
+                Some(b'e' | b'E') => {
+                    floating = true;
+                    i += 1;
+
+                    if let Some(b'+' | b'-') = self.input.get(i) {
+                        i += 1;
+                    }
+                }
+                _ => break,
+            }
+        }
+
+        let invalid = || SyntaxError::new("invalid number", start);
+
+        // floats must be finite
+        let parse_float = || match std::str::from_utf8(&self.input[start..i])
+            .unwrap()
+            .parse::<f64>()
+        {
+            Ok(f) if f.is_finite() => Ok(Number::Float(f)),
+            _ => Err(invalid()),
+        };
+
+        let value = if floating {
+            parse_float()?
+        } else {
+            match integer {
+                _ if i == digits_start => return Err(invalid()),
+                // integers too large for a `u64` become floats, so must be finite too
+                None => parse_float()?,
+                Some(n) if !negative => Number::PosInt(n),
+                // negative integers that don't fit in an `i64` become floats
+                Some(n) => match 0i64.checked_sub_unsigned(n) {
+                    Some(n) => Number::NegInt(n),
+                    None => Number::Float(-(n as f64)),
+                },
+            }
+        };
+
+        self.index = i;
+
+        Ok(Token::ValueNumber {
+            offset: Offset(start),
+            value,
+        })
+    }
+
+    fn read_value(&mut self, b: u8) -> Result<Token<'input>, SyntaxError> {
+        let offset = Offset(self.index);
+
+        let token = match b {
+            b'{' => {
+                self.index += 1;
+                self.state_stack.push(State::ObjectFirstKeyOrEnd);
+                return Ok(Token::StartObject { offset });
+            }
+            b'[' => {
+                self.index += 1;
+                self.state_stack.push(State::ArrayFirstValueOrEnd);
+                return Ok(Token::StartArray { offset });
+            }
+            b'"' => {
+                return Ok(Token::ValueString {
+                    offset,
+                    value: EscapedStr::new(self.read_string()?),
+                });
+            }
+            b'n' => self.read_literal(b"null", Token::ValueNull { offset })?,
+            b't' => self.read_literal(
+                b"true",
+                Token::ValueBool {
+                    offset,
+                    value: true,
+                },
+            )?,
+            b'f' => self.read_literal(
+                b"false",
+                Token::ValueBool {
+                    offset,
+                    value: false,
+                },
+            )?,
+            b'-' | b'0'..=b'9' => self.read_number()?,
+            _ => return Err(self.error("expected a value")),
+        };
+
+        // numbers and literals can't run into whatever follows them
+        match self.input.get(self.index) {
+            None | Some(b' ' | b'\t' | b'\n' | b'\r' | b',' | b']' | b'}') => Ok(token),
+            Some(_) => Err(self.error("unexpected character after value")),
+        }
+    }
+
+    fn read_key(&mut self, b: u8) -> Result<Token<'input>, SyntaxError> {
+        let offset = Offset(self.index);
+
+        if b != b'"' {
+            return Err(self.error("expected an object key"));
+        }
+
+        let key = self.read_string()?;
+
+        match self.next_significant()? {
+            b':' => {
+                self.index += 1;
+                self.replace_state(State::ObjectFieldValue);
+            }
+            _ => return Err(self.error("expected ':'")),
+        }
+
+        Ok(Token::ObjectKey {
+            offset,
+            key: EscapedStr::new(key),
+        })
+    }
+
+    fn end_container(&mut self, token: Token<'input>) -> Token<'input> {
+        self.index += 1;
+        self.state_stack.pop();
+        token
+    }
+
+    fn next_token(&mut self) -> Option<Result<Token<'input>, SyntaxError>> {
+        self.skip_whitespace();
+
+        let Some(&b) = self.input.get(self.index) else {
+            return match self.state() {
+                State::Initial => None,
+                _ => Some(Err(self.error("unexpected end of input"))),
+            };
+        };
+
+        let offset = Offset(self.index);
+
+        let result = match (self.state(), b) {
+            (State::Initial, _) => self.read_value(b),
+            (State::ArrayFirstValueOrEnd | State::ArrayNextValueOrEnd, b']') => {
+                Ok(self.end_container(Token::EndArray { offset }))
+            }
+            (State::ArrayFirstValueOrEnd, _) => {
+                self.replace_state(State::ArrayNextValueOrEnd);
+                self.read_value(b)
+            }
+            (State::ArrayNextValueOrEnd, b',') => {
+                self.index += 1;
+                self.next_significant().and_then(|b| self.read_value(b))
+            }
+            (State::ArrayNextValueOrEnd, _) => Err(self.error("expected ',' or ']'")),
+            (State::ObjectFirstKeyOrEnd | State::ObjectNextKeyOrEnd, b'}') => {
+                Ok(self.end_container(Token::EndObject { offset }))
+            }
+            (State::ObjectFirstKeyOrEnd, _) => self.read_key(b),
+            (State::ObjectNextKeyOrEnd, b',') => {
+                self.index += 1;
+                self.next_significant().and_then(|b| self.read_key(b))
+            }
+            (State::ObjectNextKeyOrEnd, _) => Err(self.error("expected ',' or '}'")),
+            (State::ObjectFieldValue, _) => {
+                self.replace_state(State::ObjectNextKeyOrEnd);
+                self.read_value(b)
+            }
+        };
+
+        Some(result)
+    }
+}
+
+impl<'input> Iterator for Tokens<'input> {
+    type Item = Result<Token<'input>, SyntaxError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let next = self.next_token();
+
+        if let Some(Err(_)) = next {
+            self.failed = true;
+        }
+
+        next
+    }
+}
+
+const ONES: u64 = 0x0101_0101_0101_0101;
+const HIGH_BITS: u64 = 0x8080_8080_8080_8080;
+
+/// the high bit of each of the 8 bytes of `word` that is zero, and possibly of bytes
+/// after it, but never before it, so the lowest set bit is always right
+fn zero_bytes(word: u64) -> u64 {
+    word.wrapping_sub(ONES) & !word & HIGH_BITS
+}
+
+/// the high bit of each of the 8 bytes of `word` that is a `"`, `\` or control character,
+/// with the same caveat as [`zero_bytes`]
+fn special_bytes(word: u64) -> u64 {
+    let quotes = zero_bytes(word ^ (ONES * b'"' as u64));
+    let backslashes = zero_bytes(word ^ (ONES * b'\\' as u64));
+    let controls = word.wrapping_sub(ONES * 0x20) & !word & HIGH_BITS;
+
+    quotes | backslashes | controls
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Tokens;
+    use crate::token::{EscapedStr, Number, Offset, Token};
+
+    fn tokens(s: &str) -> Result<Vec<Token<'_>>, String> {
+        Tokens::new(s.as_bytes())
+            .collect::<Result<_, _>>()
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn every_token() {
+        assert_eq!(
+            tokens(r#" {"a": [1, -2, 3.5e1, "s\"é", true, false, null], "": {}} 4"#).unwrap(),
+            vec![
+                Token::StartObject { offset: Offset(1) },
+                Token::ObjectKey {
+                    offset: Offset(2),
+                    key: EscapedStr::new("a"),
+                },
+                Token::StartArray { offset: Offset(7) },
+                Token::ValueNumber {
+                    offset: Offset(8),
+                    value: Number::PosInt(1),
+                },
+                Token::ValueNumber {
+                    offset: Offset(11),
+                    value: Number::NegInt(-2),
+                },
+                Token::ValueNumber {
+                    offset: Offset(15),
+                    value: Number::Float(35.0),
+                },
+                Token::ValueString {
+                    offset: Offset(22),
+                    value: EscapedStr::new(r#"s\"é"#),
+                },
+                Token::ValueBool {
+                    offset: Offset(31),
+                    value: true,
+                },
+                Token::ValueBool {
+                    offset: Offset(37),
+                    value: false,
+                },
+                Token::ValueNull { offset: Offset(44) },
+                Token::EndArray { offset: Offset(48) },
+                Token::ObjectKey {
+                    offset: Offset(51),
+                    key: EscapedStr::new(""),
+                },
+                Token::StartObject { offset: Offset(55) },
+                Token::EndObject { offset: Offset(56) },
+                Token::EndObject { offset: Offset(57) },
+                Token::ValueNumber {
+                    offset: Offset(59),
+                    value: Number::PosInt(4),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn numbers() {
+        for (s, challenge) in [
+            ("0", Number::PosInt(0)),
+            ("18446744073709551615", Number::PosInt(u64::MAX)),
+            ("-9223372036854775808", Number::NegInt(i64::MIN)),
+            (
+                "-9223372036854775809",
+                Number::Float(-9223372036854775809.0),
+            ),
+            (
+                "18446744073709551616",
+                Number::Float(18446744073709551616.0),
+            ),
+            ("-0", Number::NegInt(0)),
+            ("1E+2", Number::Float(100.0)),
+            ("-0.25e-2", Number::Float(-0.0025)),
+        ] {
+            assert_eq!(
+                tokens(s).unwrap(),
+                vec![Token::ValueNumber {
+                    offset: Offset(0),
+                    value: challenge
+                }],
+                "{s}"
+            );
+        }
+
+        // 310 digits, more than `f64::MAX` has
+        let too_large = format!("1{}", "0".repeat(309));
+
+        for s in [
+            "-",
+            "1e999",
+            "1.2.3",
+            "1x",
+            "[1-]",
+            &too_large,
+            &format!("-{too_large}"),
+        ] {
+            assert!(tokens(s).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn long_strings() {
+        // every position of every kind of byte that ends or interrupts a string,
+        // relative to the 8 byte words it is read in
+        for len in 0..20 {
+            let body = "é".repeat(len / 2) + &"a".repeat(len % 2);
+
+            assert_eq!(
+                tokens(&format!(r#""{body}""#)).unwrap(),
+                vec![Token::ValueString {
+                    offset: Offset(0),
+                    value: EscapedStr::new(&body)
+                }]
+            );
+
+            let escaped = format!(r#"{body}\"{body}\\"#);
+            assert_eq!(
+                tokens(&format!(r#"["{escaped}"]"#)).unwrap()[1],
+                Token::ValueString {
+                    offset: Offset(1),
+                    value: EscapedStr::new(&escaped)
+                }
+            );
+
+            assert!(tokens(&format!("\"{body}\n\"")).is_err());
+            assert!(tokens(&format!(r#""{body}"#)).is_err());
+            assert!(tokens(&format!(r#""{body}\x""#)).is_err());
+            assert!(tokens(&format!(r#"["{body}\u12", 1]"#)).is_err());
+        }
+
+        assert!(Tokens::new(b"\"\xff\"").next().unwrap().is_err());
+    }
+
+    #[test]
+    fn invalid() {
+        for s in [
+            "[1,]",
+            "{\"a\": 1,}",
+            "{\"a\" 1}",
+            "{a: 1}",
+            "[1 2]",
+            "[tru]",
+            "nul",
+            "{\"a\":",
+            "[1, ",
+            "[1 ",
+            "{\"a\" ",
+            "]",
+            "// comment",
+        ] {
+            assert!(tokens(s).is_err(), "{s}");
+        }
+
+        assert_eq!(tokens("[1,]").unwrap_err(), "expected a value at byte 3");
+
+        // input can only end between documents
+        assert_eq!(
+            tokens(r#"{"a": [1"#).unwrap_err(),
+            "unexpected end of input at byte 8"
+        );
+        assert_eq!(
+            tokens(r#"{"a""#).unwrap_err(),
+            "unexpected end of input at byte 4"
+        );
+        assert_eq!(tokens("[1]\n").unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,620 @@
+//! strict validation of JSON text against [RFC 8259](https://www.rfc-editor.org/rfc/rfc8259)
+//! or [I-JSON (RFC 7493)](https://www.rfc-editor.org/rfc/rfc7493).
+//!
+//! this does not use the tokenizer that [`crate::stream`] uses,
+//! as that tokenizer is deliberately lenient in places,
+//! and stops at the first problem it finds.
+//! here, problems that don't stop the rest of the document from being understood,
+//! like invalid UTF-8 in a string or a lone surrogate, are reported and
+//! validation continues. syntax errors end validation, as there is
+//! no reliable way to know what the rest of the document was meant to be.
+
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Profile {
+    /// the JSON grammar, in UTF-8, with a single top-level value
+    #[default]
+    Rfc8259,
+    /// RFC 8259, plus: no duplicate object keys,
+    /// no surrogate or noncharacter code points in strings,
+    /// and no numbers that an IEEE 754 double can't represent
+    IJson,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Violation {
+    /// the byte offset in the input at which the problem was found
+    pub offset: usize,
+    /// the JSON Pointer-style path of the value with the problem,
+    /// in the same form as the paths written by [`crate::path_value_writer::json_pointer`]
+    pub path: String,
+    pub kind: ViolationKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ViolationKind {
+    ByteOrderMark,
+    UnexpectedByte(u8),
+    UnexpectedEnd,
+    TrailingContent,
+    InvalidLiteral,
+    InvalidNumber,
+    LeadingZero,
+    InvalidEscape,
+    InvalidUtf8,
+    ControlCharacterInString(u8),
+    /// only for [`Profile::IJson`]
+    LoneSurrogate(u16),
+    /// only for [`Profile::IJson`]
+    Noncharacter(u32),
+    /// only for [`Profile::IJson`]
+    NumberOutOfRange,
+    /// an integer outside of `[-(2**53)+1, (2**53)-1]`.
+    /// only for [`Profile::IJson`]
+    IntegerOutOfRange,
+    /// only for [`Profile::IJson`]
+    DuplicateKey {
+        first_offset: usize,
+    },
+}
+
+impl fmt::Display for ViolationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ViolationKind::ByteOrderMark => write!(f, "byte order mark"),
+            ViolationKind::UnexpectedByte(b) => write!(f, "unexpected byte 0x{b:02x}"),
+            ViolationKind::UnexpectedEnd => write!(f, "unexpected end of input"),
+            ViolationKind::TrailingContent => write!(f, "content after the top-level value"),
+            ViolationKind::InvalidLiteral => write!(f, "invalid literal"),
+            ViolationKind::InvalidNumber => write!(f, "invalid number"),
+            ViolationKind::LeadingZero => write!(f, "number with a leading zero"),
+            ViolationKind::InvalidEscape => write!(f, "invalid escape sequence"),
+            ViolationKind::InvalidUtf8 => write!(f, "invalid UTF-8"),
+            ViolationKind::ControlCharacterInString(b) => {
+                write!(f, "unescaped control character 0x{b:02x} in string")
+            }
+            ViolationKind::LoneSurrogate(s) => write!(f, "lone surrogate \\u{s:04x}"),
+            ViolationKind::Noncharacter(c) => write!(f, "noncharacter U+{c:04X}"),
+            ViolationKind::NumberOutOfRange => {
+                write!(f, "number out of IEEE 754 double precision range")
+            }
+            ViolationKind::IntegerOutOfRange => {
+                write!(f, "integer cannot be represented exactly as a double")
+            }
+            ViolationKind::DuplicateKey { first_offset } => {
+                write!(f, "duplicate key, first seen at byte {first_offset}")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\t{}\t{}", self.offset, self.path, self.kind)
+    }
+}
+
+/// returns every violation of `profile` found in `buf`, in order.
+/// an empty result means `buf` is valid.
+pub fn validate(buf: &[u8], profile: Profile) -> Vec<Violation> {
+    let mut validator = Validator {
+        buf,
+        pos: 0,
+        profile,
+        violations: vec![],
+        path: vec![],
+        containers: vec![],
+    };
+
+    // a syntax error has already been recorded if this fails
+    let _ = validator.document();
+
+    validator.violations
+}
+
+/// a syntax error, which has already been recorded as a violation
+struct Fatal;
+
+enum Segment {
+    /// the range of the escaped key in the input
+    Key(std::ops::Range<usize>),
+    Index(usize),
+}
+
+enum Container {
+    /// the unescaped keys seen so far, and where they were seen.
+    /// only tracked for [`Profile::IJson`]
+    Object(HashMap<Vec<u8>, usize>),
+    Array,
+}
+
+struct Validator<'input> {
+    buf: &'input [u8],
+    pos: usize,
+    profile: Profile,
+    violations: Vec<Violation>,
+    path: Vec<Segment>,
+    containers: Vec<Container>,
+}
+
+impl Validator<'_> {
+    fn violation(&mut self, offset: usize, kind: ViolationKind) {
+        let mut path = String::new();
+
+        for segment in &self.path {
+            path.push('/');
+            match segment {
+                Segment::Key(range) => {
+                    path.push_str(&String::from_utf8_lossy(&self.buf[range.clone()]))
+                }
+                Segment::Index(i) => path.push_str(itoa::Buffer::new().format(*i)),
+            }
+        }
+
+        self.violations.push(Violation { offset, path, kind });
+    }
+
+    fn fatal(&mut self, offset: usize, kind: ViolationKind) -> Fatal {
+        self.violation(offset, kind);
+        Fatal
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.buf.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn unexpected(&mut self) -> Fatal {
+        match self.peek() {
+            Some(b) => self.fatal(self.pos, ViolationKind::UnexpectedByte(b)),
+            None => self.fatal(self.pos, ViolationKind::UnexpectedEnd),
+        }
+    }
+
+    fn document(&mut self) -> Result<(), Fatal> {
+        if self.buf.starts_with(b"\xEF\xBB\xBF") {
+            self.violation(0, ViolationKind::ByteOrderMark);
+            self.pos = 3;
+        }
+
+        'value: loop {
+            self.skip_whitespace();
+
+            match self.peek() {
+                Some(b'{') => {
+                    self.pos += 1;
+                    self.skip_whitespace();
+                    if self.peek() == Some(b'}') {
+                        self.pos += 1;
+                    } else {
+                        self.containers.push(Container::Object(HashMap::new()));
+                        self.path.push(Segment::Key(0..0));
+                        self.key()?;
+                        continue 'value;
+                    }
+                }
+                Some(b'[') => {
+                    self.pos += 1;
+                    self.skip_whitespace();
+                    if self.peek() == Some(b']') {
+                        self.pos += 1;
+                    } else {
+                        self.containers.push(Container::Array);
+                        self.path.push(Segment::Index(0));
+                        continue 'value;
+                    }
+                }
+                Some(b'"') => {
+                    self.string()?;
+                }
+                Some(b'-' | b'0'..=b'9') => self.number()?,
+                Some(b't') => self.literal(b"true")?,
+                Some(b'f') => self.literal(b"false")?,
+                Some(b'n') => self.literal(b"null")?,
+                _ => return Err(self.unexpected()),
+            }
+
+            // a value has ended. close any containers that end with it,
+            // and then move on to the next value, if there is one.
+            loop {
+                self.skip_whitespace();
+
+                let in_object = match self.containers.last() {
+                    Some(Container::Object(_)) => true,
+                    Some(Container::Array) => false,
+                    None => break 'value,
+                };
+
+                match (in_object, self.peek()) {
+                    (_, Some(b',')) => {
+                        self.pos += 1;
+
+                        if in_object {
+                            self.skip_whitespace();
+                            self.key()?;
+                        } else if let Some(Segment::Index(i)) = self.path.last_mut() {
+                            *i += 1;
+                        }
+
+                        continue 'value;
+                    }
+                    (true, Some(b'}')) | (false, Some(b']')) => {
+                        self.pos += 1;
+                        self.containers.pop();
+                        self.path.pop();
+                    }
+                    _ => return Err(self.unexpected()),
+                }
+            }
+        }
+
+        self.skip_whitespace();
+
+        if self.pos < self.buf.len() {
+            self.violation(self.pos, ViolationKind::TrailingContent);
+        }
+
+        Ok(())
+    }
+
+    /// an object key, and the `:` after it
+    fn key(&mut self) -> Result<(), Fatal> {
+        if self.peek() != Some(b'"') {
+            return Err(self.unexpected());
+        }
+
+        let start = self.pos;
+        let unescaped = self.string()?;
+
+        if let Some(Segment::Key(range)) = self.path.last_mut() {
+            *range = start + 1..self.pos - 1;
+        }
+
+        if let (Some(key), Some(Container::Object(keys))) = (unescaped, self.containers.last_mut())
+        {
+            if let Some(first_offset) = keys.get(&key) {
+                let first_offset = *first_offset;
+                self.violation(start, ViolationKind::DuplicateKey { first_offset });
+            } else {
+                keys.insert(key, start);
+            }
+        }
+
+        self.skip_whitespace();
+
+        if self.peek() != Some(b':') {
+            return Err(self.unexpected());
+        }
+
+        self.pos += 1;
+
+        Ok(())
+    }
+
+    fn literal(&mut self, literal: &[u8]) -> Result<(), Fatal> {
+        if self.buf[self.pos..].starts_with(literal) {
+            self.pos += literal.len();
+            Ok(())
+        } else {
+            Err(self.fatal(self.pos, ViolationKind::InvalidLiteral))
+        }
+    }
+
+    fn digits(&mut self) -> usize {
+        let start = self.pos;
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.pos += 1;
+        }
+        self.pos - start
+    }
+
+    fn number(&mut self) -> Result<(), Fatal> {
+        let start = self.pos;
+
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+        }
+
+        let int_start = self.pos;
+
+        match self.digits() {
+            0 => return Err(self.fatal(start, ViolationKind::InvalidNumber)),
+            1 => {}
+            _ if self.buf[int_start] == b'0' => {
+                return Err(self.fatal(start, ViolationKind::LeadingZero));
+            }
+            _ => {}
+        }
+
+        let mut integer = true;
+
+        if self.peek() == Some(b'.') {
+            integer = false;
+            self.pos += 1;
+            if self.digits() == 0 {
+                return Err(self.fatal(start, ViolationKind::InvalidNumber));
+            }
+        }
+
+        if let Some(b'e' | b'E') = self.peek() {
+            integer = false;
+            self.pos += 1;
+            if let Some(b'+' | b'-') = self.peek() {
+                self.pos += 1;
+            }
+            if self.digits() == 0 {
+                return Err(self.fatal(start, ViolationKind::InvalidNumber));
+            }
+        }
+
+        if self.profile == Profile::IJson {
+            // this slice is ASCII, as it only contains the bytes checked above
+            let text = std::str::from_utf8(&self.buf[start..self.pos]).unwrap();
+            let value: f64 = text.parse().unwrap();
+
+            if value.is_infinite() {
+                self.violation(start, ViolationKind::NumberOutOfRange);
+            } else if integer && value.abs() > 9007199254740991.0 {
+                self.violation(start, ViolationKind::IntegerOutOfRange);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// a string, starting at its opening quote.
+    /// returns the unescaped string if it is needed to check for duplicate keys.
+    fn string(&mut self) -> Result<Option<Vec<u8>>, Fatal> {
+        self.pos += 1;
+
+        let mut unescaped = if self.profile == Profile::IJson {
+            Some(Vec::new())
+        } else {
+            None
+        };
+
+        loop {
+            let Some(b) = self.peek() else {
+                return Err(self.fatal(self.pos, ViolationKind::UnexpectedEnd));
+            };
+
+            match b {
+                b'"' => {
+                    self.pos += 1;
+                    return Ok(unescaped);
+                }
+                b'\\' => self.escape(&mut unescaped),
+                0x00..=0x1F => {
+                    self.violation(self.pos, ViolationKind::ControlCharacterInString(b));
+                    self.pos += 1;
+                }
+                0x20..=0x7F => {
+                    if let Some(unescaped) = &mut unescaped {
+                        unescaped.push(b);
+                    }
+                    self.pos += 1;
+                }
+                _ => self.utf8_sequence(&mut unescaped),
+            }
+        }
+    }
+
+    /// a non-ASCII character in a string
+    fn utf8_sequence(&mut self, unescaped: &mut Option<Vec<u8>>) {
+        let rest = &self.buf[self.pos..self.buf.len().min(self.pos + 4)];
+
+        let (c, len) = match std::str::from_utf8(rest) {
+            Ok(s) => {
+                let c = s.chars().next().unwrap();
+                (c, c.len_utf8())
+            }
+            Err(e) if e.valid_up_to() > 0 => {
+                let s = std::str::from_utf8(&rest[..e.valid_up_to()]).unwrap();
+                let c = s.chars().next().unwrap();
+                (c, c.len_utf8())
+            }
+            Err(e) => {
+                self.violation(self.pos, ViolationKind::InvalidUtf8);
+                self.pos += e.error_len().unwrap_or(rest.len()).max(1);
+                return;
+            }
+        };
+
+        self.check_code_point(self.pos, c as u32);
+
+        if let Some(unescaped) = unescaped {
+            unescaped.extend_from_slice(&self.buf[self.pos..self.pos + len]);
+        }
+
+        self.pos += len;
+    }
+
+    /// an escape sequence in a string, starting at its `\`
+    fn escape(&mut self, unescaped: &mut Option<Vec<u8>>) {
+        let start = self.pos;
+        self.pos += 1;
+
+        let c = match self.peek() {
+            Some(b'"') => '"',
+            Some(b'\\') => '\\',
+            Some(b'/') => '/',
+            Some(b'b') => '\u{8}',
+            Some(b'f') => '\u{c}',
+            Some(b'n') => '\n',
+            Some(b'r') => '\r',
+            Some(b't') => '\t',
+            Some(b'u') => {
+                self.pos += 1;
+
+                let Some(high) = self.hex4(self.pos) else {
+                    self.violation(start, ViolationKind::InvalidEscape);
+                    return;
+                };
+
+                self.pos += 4;
+
+                let c = match high {
+                    0xD800..=0xDBFF => {
+                        // a high surrogate must be followed by an escaped low surrogate.
+                        // if it isn't, whatever follows is left to be read on its own.
+                        let low = if self.buf[self.pos..].starts_with(b"\\u") {
+                            self.hex4(self.pos + 2)
+                                .filter(|low| (0xDC00..=0xDFFF).contains(low))
+                        } else {
+                            None
+                        };
+
+                        let Some(low) = low else {
+                            self.lone_surrogate(start, high);
+                            return;
+                        };
+
+                        self.pos += 6;
+
+                        char::from_u32(
+                            0x10000 + ((high as u32 - 0xD800) << 10) + (low as u32 - 0xDC00),
+                        )
+                    }
+                    0xDC00..=0xDFFF => {
+                        self.lone_surrogate(start, high);
+                        return;
+                    }
+                    _ => char::from_u32(high as u32),
+                }
+                .expect("surrogates were handled above");
+
+                self.check_code_point(start, c as u32);
+
+                if let Some(unescaped) = unescaped {
+                    let mut b = [0; 4];
+                    unescaped.extend_from_slice(c.encode_utf8(&mut b).as_bytes());
+                }
+
+                return;
+            }
+            Some(_) => {
+                self.violation(start, ViolationKind::InvalidEscape);
+                return;
+            }
+            None => return,
+        };
+
+        self.pos += 1;
+
+        if let Some(unescaped) = unescaped {
+            unescaped.push(c as u8);
+        }
+    }
+
+    fn lone_surrogate(&mut self, offset: usize, surrogate: u16) {
+        if self.profile == Profile::IJson {
+            self.violation(offset, ViolationKind::LoneSurrogate(surrogate));
+        }
+    }
+
+    /// the four hex digits of a `\u` escape, at `at`
+    fn hex4(&self, at: usize) -> Option<u16> {
+        let digits = self.buf.get(at..at + 4)?;
+
+        if !digits.iter().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        u16::from_str_radix(std::str::from_utf8(digits).ok()?, 16).ok()
+    }
+
+    fn check_code_point(&mut self, offset: usize, c: u32) {
+        let is_noncharacter = (0xFDD0..=0xFDEF).contains(&c) || (c & 0xFFFE) == 0xFFFE;
+
+        if self.profile == Profile::IJson && is_noncharacter {
+            self.violation(offset, ViolationKind::Noncharacter(c));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Profile, ViolationKind, validate};
+
+    /// `y_` fixtures are valid in every profile, `n_` fixtures are invalid in every profile,
+    /// and `i_` fixtures are valid RFC 8259 but not valid I-JSON
+    #[test]
+    fn fixtures() {
+        let mut entries: Vec<_> = std::fs::read_dir("fixtures/validate")
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        entries.sort();
+
+        assert!(!entries.is_empty());
+
+        for path in entries {
+            let name = path.file_name().unwrap().to_str().unwrap().to_owned();
+            let buf = std::fs::read(&path).unwrap();
+
+            let rfc8259 = validate(&buf, Profile::Rfc8259);
+            let ijson = validate(&buf, Profile::IJson);
+
+            if name.starts_with("y_") {
+                assert_eq!(rfc8259, vec![], "{name}");
+                assert_eq!(ijson, vec![], "{name}");
+            } else if name.starts_with("n_") {
+                assert_ne!(rfc8259, vec![], "{name}");
+                assert_ne!(ijson, vec![], "{name}");
+            } else if name.starts_with("i_") {
+                assert_eq!(rfc8259, vec![], "{name}");
+                assert_ne!(ijson, vec![], "{name}");
+            } else {
+                panic!("fixture {name} must start with y_, n_, or i_");
+            }
+        }
+    }
+
+    #[test]
+    fn every_violation_is_reported_with_its_path() {
+        let s = b"{\"a\": [1, \"\xff\", \"\\ud800\"], \"a\": 1e999, \"b\": {\"c\": \"\x01\"}}";
+
+        let violations: Vec<_> = validate(s, Profile::IJson)
+            .into_iter()
+            .map(|v| (v.offset, v.path, v.kind))
+            .collect();
+
+        assert_eq!(
+            violations,
+            vec![
+                (11, "/a/1".to_string(), ViolationKind::InvalidUtf8),
+                (16, "/a/2".to_string(), ViolationKind::LoneSurrogate(0xd800)),
+                (
+                    26,
+                    "/a".to_string(),
+                    ViolationKind::DuplicateKey { first_offset: 1 }
+                ),
+                (31, "/a".to_string(), ViolationKind::NumberOutOfRange),
+                (
+                    50,
+                    "/b/c".to_string(),
+                    ViolationKind::ControlCharacterInString(1)
+                ),
+            ]
+        );
+
+        // only the UTF-8 and control character problems are RFC 8259 violations
+        assert_eq!(validate(s, Profile::Rfc8259).len(), 2);
+    }
+
+    #[test]
+    fn syntax_errors_stop_validation() {
+        let violations = validate(b"[1, \"\xff\", 01, \"\xff\"]", Profile::Rfc8259);
+
+        assert_eq!(violations.len(), 2);
+        assert_eq!(violations[1].kind, ViolationKind::LeadingZero);
+        assert_eq!(violations[1].path, "/2");
+    }
+}
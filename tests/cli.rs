@@ -0,0 +1,110 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// runs jstream with `args`, giving it `input` on stdin
+fn jstream(args: &[&str], input: impl AsRef<[u8]>) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_jstream"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // jstream may exit with an error before reading all of its input
+    let _ = child.stdin.take().unwrap().write_all(input.as_ref());
+
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn duplicate_keys_report() {
+    let input = r#"{"a": 1, "b": {"c": 2, "c": 3}, "a": [4]}"#;
+
+    let output = jstream(&["--duplicate-keys", "report"], input);
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "/a\t1\n/b/c\t2\n/b/c\tduplicate key\t15\t23\n/b/c\t3\n/a\tduplicate key\t1\t32\n/a/0\t4\n"
+    );
+
+    let output = jstream(&["--duplicate-keys", "report", "--sort"], input);
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "/a\t1\n/a\tduplicate key\t1\t32\n/a/0\t4\n/b/c\t2\n/b/c\tduplicate key\t15\t23\n/b/c\t3\n"
+    );
+}
+
+#[test]
+fn yaml_documents_stay_apart() {
+    for (input, expected) in [
+        ("a: 1\n---\nb: 2\n", "{\"a\":1}\n{\"b\":2}\n"),
+        ("- 1\n---\n- 2\n", "[1]\n[2]\n"),
+    ] {
+        let output = jstream(&["--from", "yaml", "-o", "json"], input);
+
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), expected);
+    }
+}
+
+#[test]
+fn json_only_options_with_other_input_formats() {
+    for option in [
+        &["--jsonc"][..],
+        &["--json5"],
+        &["--array-limit", "1"],
+        &["--sample-rate", "0.5"],
+        &["--reservoir", "1"],
+    ] {
+        let args = [&["--from", "yaml"], option].concat();
+
+        let output = jstream(&args, "a: [1, 2]\n");
+
+        assert!(!output.status.success(), "{option:?}");
+        assert!(output.stdout.is_empty(), "{option:?}");
+    }
+}
+
+#[test]
+fn errors_are_displayed() {
+    for (args, input, expected) in [
+        (
+            &[][..],
+            &b"{\"a\": [1"[..],
+            "Error: unexpected end of input at byte 8\n",
+        ),
+        (
+            &["--from", "yaml"],
+            b"[1",
+            "Error: while parsing a flow sequence, expected ',' or ']' at byte 2 line 2 column 1\n",
+        ),
+        (
+            &["--from", "cbor"],
+            b"\x82\x01",
+            "Error: unexpected end of input at byte 2\n",
+        ),
+        (
+            &["--from", "msgpack"],
+            b"\x92\x01",
+            "Error: unexpected end of input at byte 2\n",
+        ),
+        (
+            &["--from", "cbor"],
+            b"\x81\xff",
+            "Error: unexpected break at byte 1\n",
+        ),
+    ] {
+        let output = jstream(args, input);
+
+        assert_eq!(output.status.code(), Some(1), "{args:?}");
+        assert_eq!(
+            String::from_utf8(output.stderr).unwrap(),
+            expected,
+            "{args:?}"
+        );
+    }
+}